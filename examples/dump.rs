@@ -4,7 +4,7 @@ use std::{
     io::{self, BufRead, BufReader, BufWriter, Write},
 };
 
-use amarkl::{AmarkReader, AmarkToken};
+use amark::{AmarkReader, AmarkToken};
 
 fn main() -> io::Result<()> {
     if let Some(path) = env::args_os().nth(1) {
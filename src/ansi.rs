@@ -0,0 +1,531 @@
+//! A renderer for previews in terminals with ANSI escape codes. Items are sections with their
+//! name as a bold label and their content indented below it, boxed items are framed by a colored
+//! border instead. Items with a style that isn't boxed are rendered inside of the text around
+//! them, like `em` in italics.
+//!
+//! Lines of text are joined by spaces, an empty line starts a new paragraph and `\n` breaks the
+//! line, like `br;` does. `br(N);` breaks it N times and other bare items like `l;` are only their
+//! label.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    mem,
+};
+
+use crate::{
+    event::{parse_count, Event, EventReader, Item},
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// Resets all styles
+const RESET: &[u8] = b"\x1b[0m";
+/// Bold text for labels
+const BOLD: &[u8] = b"\x1b[1m";
+/// The name of the item breaking the line
+const BR: &[u8] = b"br";
+
+/// One of the eight basic terminal colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiColor {
+    /// Black
+    Black,
+    /// Red
+    Red,
+    /// Green
+    Green,
+    /// Yellow
+    Yellow,
+    /// Blue
+    Blue,
+    /// Magenta
+    Magenta,
+    /// Cyan
+    Cyan,
+    /// White
+    White,
+}
+
+impl AnsiColor {
+    /// The SGR code of the foreground color
+    fn code(self) -> u8 {
+        30 + self as u8
+    }
+}
+
+/// How an item is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnsiStyle {
+    /// The color of the text or of the border of boxed items
+    pub color: Option<AnsiColor>,
+    /// Whether the text is bold
+    pub bold: bool,
+    /// Whether the text is in italics
+    pub italic: bool,
+    /// Whether the text is underlined
+    pub underline: bool,
+    /// Whether the item is framed by a border instead of indented below its label
+    pub boxed: bool,
+}
+
+impl AnsiStyle {
+    /// A style without any effect
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the color of the text or border
+    pub fn color(mut self, color: AnsiColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Make the text bold
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Write the text in italics
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    /// Underline the text
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Frame the item by a border
+    pub fn boxed(mut self) -> Self {
+        self.boxed = true;
+        self
+    }
+
+    /// The SGR sequence for the text, empty if the text is not styled
+    fn text_sgr(self, with_color: bool) -> Vec<u8> {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push(1);
+        }
+        if self.italic {
+            codes.push(3);
+        }
+        if self.underline {
+            codes.push(4);
+        }
+        if let Some(color) = self.color.filter(|_| with_color) {
+            codes.push(color.code());
+        }
+        sgr(&codes)
+    }
+
+    /// The SGR sequence for the border and label, empty for the default color
+    fn color_sgr(self) -> Vec<u8> {
+        sgr(&self
+            .color
+            .map(AnsiColor::code)
+            .into_iter()
+            .collect::<Vec<_>>())
+    }
+}
+
+/// Renders Amark documents for terminals
+#[derive(Debug, Clone)]
+pub struct AnsiRenderer {
+    /// Styles configured for specific item names
+    styles: HashMap<Vec<u8>, AnsiStyle>,
+    /// The number of spaces the content of sections is indented by
+    indent: usize,
+}
+
+impl AnsiRenderer {
+    /// Create a new renderer with colored boxes for `gb`, `bb`, `rb` and `yb`, italic `em`, bold
+    /// `strong` and cyan `code`
+    pub fn new() -> Self {
+        let boxed = AnsiStyle::new().boxed();
+        Self {
+            styles: HashMap::from([
+                (b"gb".to_vec(), boxed.color(AnsiColor::Green)),
+                (b"bb".to_vec(), boxed.color(AnsiColor::Blue)),
+                (b"rb".to_vec(), boxed.color(AnsiColor::Red)),
+                (b"yb".to_vec(), boxed.color(AnsiColor::Yellow)),
+                (b"em".to_vec(), AnsiStyle::new().italic()),
+                (b"strong".to_vec(), AnsiStyle::new().bold()),
+                (b"code".to_vec(), AnsiStyle::new().color(AnsiColor::Cyan)),
+            ]),
+            indent: 2,
+        }
+    }
+
+    /// Render items with the given name in the given style
+    pub fn style(mut self, name: impl Into<Vec<u8>>, style: AnsiStyle) -> Self {
+        self.styles.insert(name.into(), style);
+        self
+    }
+
+    /// Indent the content of sections by the given number of spaces
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        let mut output = Output {
+            writer,
+            frames: Vec::new(),
+            sep: Sep::None,
+            started: false,
+            at_line_start: true,
+            last: Last::Start,
+        };
+
+        while let Some(event) = events.next_event()? {
+            match event {
+                Event::Start(item) => output.start(self.frame(item))?,
+                Event::End(_) => output.end()?,
+                Event::Empty(item) if item.name == BR => {
+                    output.br(item.param(0).and_then(parse_count).unwrap_or(1))?;
+                }
+                Event::Empty(item) => {
+                    output.start(self.frame(&item))?;
+                    output.end()?;
+                }
+                Event::Text(text) => output.text(text)?,
+                Event::Escape(escape) => {
+                    for _ in 0..escape.count() {
+                        match escape.seq {
+                            b'n' => output.line_break()?,
+                            _ => output.glued(&[escape.resolved()])?,
+                        }
+                    }
+                }
+                Event::EmptyLine => output.empty_line(),
+            }
+        }
+
+        if !output.at_line_start {
+            output.writer.write_all(b"\n")?;
+        }
+        output.writer.flush()?;
+        Ok(())
+    }
+
+    /// The frame an item is rendered in
+    fn frame(&self, item: &Item) -> Frame {
+        let style = self.styles.get(&item.name).copied();
+        let inline = style.is_some_and(|style| !style.boxed);
+        let style = style.unwrap_or_default();
+        let mut frame = Frame {
+            inline,
+            label: Vec::new(),
+            prefix: Vec::new(),
+            prefix_sgr: Vec::new(),
+            footer: Vec::new(),
+            text_sgr: style.text_sgr(!style.boxed),
+        };
+        if inline {
+            return frame;
+        }
+
+        let name = String::from_utf8_lossy(&item.name);
+        if style.boxed {
+            let color = style.color_sgr();
+            frame.label = [
+                color.as_slice(),
+                "┌ ".as_bytes(),
+                BOLD,
+                name.as_bytes(),
+                RESET,
+            ]
+            .concat();
+            frame.prefix = "│ ".as_bytes().to_vec();
+            frame.prefix_sgr = color.clone();
+            frame.footer = [color.as_slice(), "└─".as_bytes(), RESET].concat();
+        } else {
+            frame.label = [BOLD, name.as_bytes(), RESET].concat();
+            frame.prefix = vec![b' '; self.indent];
+        }
+        frame
+    }
+}
+
+impl Default for AnsiRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The SGR escape sequence which sets the given codes, empty without codes
+fn sgr(codes: &[u8]) -> Vec<u8> {
+    if codes.is_empty() {
+        return Vec::new();
+    }
+    let codes = codes.iter().map(u8::to_string).collect::<Vec<_>>();
+    format!("\x1b[{}m", codes.join(";")).into_bytes()
+}
+
+/// An item that is currently rendered
+#[derive(Debug)]
+struct Frame {
+    /// Whether the item is rendered inside of the text around it
+    inline: bool,
+    /// The line introducing a section
+    label: Vec<u8>,
+    /// Written at the start of every line inside of the section
+    prefix: Vec<u8>,
+    /// The style of the prefix
+    prefix_sgr: Vec<u8>,
+    /// The line ending a section
+    footer: Vec<u8>,
+    /// The style of the text inside of the item
+    text_sgr: Vec<u8>,
+}
+
+/// Whitespace owed between the last and the next output, larger ones replace smaller ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    /// Nothing
+    None,
+    /// A space
+    Space,
+    /// A line break
+    Line,
+    /// An empty line
+    Paragraph,
+}
+
+/// What was written last, text continues after escape sequences without a space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing or a separator
+    Start,
+    /// A line of text or an inline item
+    Text,
+    /// An escape sequence
+    Escape,
+}
+
+/// The state of the output while rendering
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The items being rendered
+    frames: Vec<Frame>,
+    /// Whitespace owed before the next output
+    sep: Sep,
+    /// Whether anything was written yet
+    started: bool,
+    /// Whether the next output starts a line
+    at_line_start: bool,
+    /// What was written last
+    last: Last,
+}
+
+impl<W: Write> Output<'_, W> {
+    /// Start rendering an item
+    fn start(&mut self, frame: Frame) -> io::Result<()> {
+        if frame.inline {
+            if self.last == Last::Text {
+                self.separate(Sep::Space);
+            }
+            self.last = Last::Start;
+        } else {
+            self.separate(Sep::Line);
+            self.write(&frame.label)?;
+            self.separate(Sep::Line);
+        }
+
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Finish rendering the current item
+    fn end(&mut self) -> io::Result<()> {
+        let Some(frame) = self.frames.pop() else {
+            return Ok(());
+        };
+
+        if frame.inline {
+            self.last = Last::Text;
+        } else {
+            if !frame.footer.is_empty() {
+                self.separate(Sep::Line);
+                self.write(&frame.footer)?;
+            }
+            self.separate(Sep::Line);
+        }
+        Ok(())
+    }
+
+    /// Write the words of a line of text
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        if self.last == Last::Text {
+            self.separate(Sep::Space);
+        }
+        let words = text
+            .split(u8::is_ascii_whitespace)
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+        self.styled(&words.join(b" ".as_slice()))?;
+        // Trailing whitespace is kept before escape sequences on the same line
+        if text.last().is_some_and(u8::is_ascii_whitespace) {
+            self.separate(Sep::Space);
+        }
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Write text which sticks to what was written before
+    fn glued(&mut self, text: &[u8]) -> io::Result<()> {
+        self.styled(text)?;
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Write an explicit line break, repeated ones leave empty lines
+    fn line_break(&mut self) -> io::Result<()> {
+        if self.sep >= Sep::Line {
+            self.write_sep()?;
+        }
+        self.sep = Sep::None;
+        self.newline()?;
+        self.started = true;
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Break the line for a `br` item, unlike after `\n` an empty line after it starts a new
+    /// paragraph
+    fn br(&mut self, count: usize) -> io::Result<()> {
+        for _ in 0..count {
+            self.line_break()?;
+        }
+        self.last = Last::Start;
+        Ok(())
+    }
+
+    /// Leave an empty line before the next paragraph. The reader also gives an empty line when
+    /// a line of text ends with an escape sequence, on the terminal that is just a space.
+    fn empty_line(&mut self) {
+        if self.last != Last::Escape {
+            self.separate(Sep::Paragraph);
+            return;
+        }
+        if !self.at_line_start {
+            self.separate(Sep::Space);
+        }
+        // A real empty line may follow the end of the line
+        self.last = Last::Start;
+    }
+
+    /// Owe at least the given whitespace before the next output
+    fn separate(&mut self, sep: Sep) {
+        self.sep = self.sep.max(sep);
+        if sep > Sep::Space {
+            self.last = Last::Start;
+        }
+    }
+
+    /// Write text in the style of the items around it
+    fn styled(&mut self, text: &[u8]) -> io::Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let sgr = self
+            .frames
+            .iter()
+            .flat_map(|frame| frame.text_sgr.iter().copied())
+            .collect::<Vec<_>>();
+        if sgr.is_empty() {
+            return self.write(text);
+        }
+        self.write(&[sgr.as_slice(), text, RESET].concat())
+    }
+
+    /// Write output after the owed whitespace and the prefixes of a new line
+    fn write(&mut self, output: &[u8]) -> io::Result<()> {
+        self.write_sep()?;
+        if self.at_line_start {
+            self.write_prefixes(false)?;
+            self.at_line_start = false;
+        }
+        self.started = true;
+        self.writer.write_all(output)
+    }
+
+    /// Write the owed whitespace, nothing at the start of the output
+    fn write_sep(&mut self) -> io::Result<()> {
+        let sep = mem::replace(&mut self.sep, Sep::None);
+        if !self.started {
+            return Ok(());
+        }
+
+        match sep {
+            Sep::None => (),
+            Sep::Space if self.at_line_start => (),
+            Sep::Space => self.writer.write_all(b" ")?,
+            Sep::Line => self.end_line()?,
+            Sep::Paragraph => {
+                self.end_line()?;
+                self.newline()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// End the current line if anything was written on it
+    fn end_line(&mut self) -> io::Result<()> {
+        if !self.at_line_start {
+            self.newline()?;
+        }
+        Ok(())
+    }
+
+    /// Write a line break, an empty line only gets the prefixes up to the last visible one
+    fn newline(&mut self) -> io::Result<()> {
+        if self.at_line_start {
+            self.write_prefixes(true)?;
+        }
+        self.writer.write_all(b"\n")?;
+        self.at_line_start = true;
+        Ok(())
+    }
+
+    /// Write the prefixes of all sections, trimmed at the end for empty lines
+    fn write_prefixes(&mut self, trim: bool) -> io::Result<()> {
+        let visible = self
+            .frames
+            .iter()
+            .rposition(|frame| !frame.prefix.trim_ascii().is_empty());
+        for (i, frame) in self.frames.iter().enumerate() {
+            let prefix = match visible {
+                Some(last) if trim && i == last => frame.prefix.trim_ascii_end(),
+                Some(last) if trim && i > last => break,
+                None if trim => break,
+                _ => &frame.prefix,
+            };
+            if frame.prefix_sgr.is_empty() {
+                self.writer.write_all(prefix)?;
+            } else {
+                self.writer.write_all(&frame.prefix_sgr)?;
+                self.writer.write_all(prefix)?;
+                self.writer.write_all(RESET)?;
+            }
+        }
+        Ok(())
+    }
+}
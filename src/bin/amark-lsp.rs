@@ -0,0 +1,59 @@
+//! The `amark-lsp` language server for Amark documents over stdin and stdout
+
+use std::{env, fs, io, process::ExitCode};
+
+use amark::{lsp::Server, schema::Schema, AmarkError};
+
+/// Exit code when the client exits without shutting the server down first or the connection
+/// fails
+const EXIT_UNCLEAN: u8 = 1;
+/// Exit code when the arguments are invalid
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let mut server = Server::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Clients like VS Code pass the transport, stdio is the only one
+            "--stdio" => (),
+            "--schema" => {
+                let Some(path) = args.next() else {
+                    eprintln!("error: --schema needs the path of a schema");
+                    return ExitCode::from(EXIT_ERROR);
+                };
+                match fs::read(&path)
+                    .map_err(AmarkError::from)
+                    .and_then(|definitions| Schema::load(&definitions))
+                {
+                    Ok(schema) => server = server.schema(schema),
+                    Err(e) => {
+                        eprintln!("error: {}: {}", path, e.to_string().replace('\n', " "));
+                        return ExitCode::from(EXIT_ERROR);
+                    }
+                }
+            }
+            "--version" => {
+                println!("amark-lsp {}", env!("CARGO_PKG_VERSION"));
+                return ExitCode::SUCCESS;
+            }
+            arg => {
+                eprintln!(
+                    "error: Unknown argument {}, usage: amark-lsp [--schema PATH]",
+                    arg
+                );
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    match server.run(io::stdin().lock(), io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_UNCLEAN),
+        Err(e) => {
+            eprintln!("error: {}", e.to_string().replace('\n', " "));
+            ExitCode::from(EXIT_UNCLEAN)
+        }
+    }
+}
@@ -0,0 +1,857 @@
+//! The `amark` command-line tool to check, inspect and convert Amark documents
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write},
+    path::PathBuf,
+    process::{self, Child, ExitCode, Stdio},
+};
+
+use amark::{
+    ansi::AnsiRenderer,
+    commonmark::MarkdownImporter,
+    conditions::ConditionFilter,
+    format::{AmarkFormatter, Indent},
+    gutenberg::GutenbergRenderer,
+    html::HtmlRenderer,
+    include::IncludeExpander,
+    json,
+    latex::LatexRenderer,
+    lint::{Linter, Severity},
+    macros::MacroExpander,
+    markdown::MarkdownRenderer,
+    metadata,
+    repeat::RepeatExpander,
+    schema::{Schema, Validator},
+    template::TemplateRenderer,
+    text::TextRenderer,
+    variables::{UndefinedVariable, VariableExpander},
+    xml, AmarkError, AmarkResult, AmarkStream, AmarkToken, DecodingReader, TokenStream,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Exit code when an input is not a valid Amark document
+const EXIT_INVALID: u8 = 1;
+/// Exit code when an input or output couldn't be opened
+const EXIT_ERROR: u8 = 2;
+
+/// Check, inspect and convert Amark documents
+#[derive(Debug, Parser)]
+#[command(name = "amark", version)]
+struct Cli {
+    /// The command to run
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The commands of the tool. Every command reads files, glob patterns or stdin if no input or `-`
+/// is given.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Parse the inputs and report every error, exits with 1 if any input is invalid
+    Check {
+        /// Also report items which don't follow the schema in this file
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Print the tokens of the inputs
+    Dump {
+        /// Print the tokens as a JSON array
+        #[arg(long)]
+        json: bool,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Report style and quality problems of the inputs, exits with 1 if any of them is an error
+    Lint {
+        /// The Amark file setting the severities and options of the rules
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Fix what can be fixed and overwrite the files with the formatted source, stdin is
+        /// printed
+        #[arg(long)]
+        fix: bool,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Format the inputs as canonical Amark source
+    Fmt {
+        /// The number of spaces to indent with per nesting level
+        #[arg(long, default_value_t = 4, conflicts_with = "tabs")]
+        indent: usize,
+        /// Indent with tabs instead of spaces
+        #[arg(long)]
+        tabs: bool,
+        /// Don't print anything, exit with 1 if any input isn't formatted
+        #[arg(long, conflicts_with = "write")]
+        check: bool,
+        /// Overwrite the files with the formatted source instead of printing it
+        #[arg(short, long)]
+        write: bool,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Convert the inputs into another format
+    Convert {
+        /// The format to convert to
+        #[arg(long, value_enum)]
+        to: Format,
+        /// Wrap plain text at this column
+        #[arg(long)]
+        wrap: Option<usize>,
+        /// Introduce items in plain text by their name
+        #[arg(long)]
+        labels: bool,
+        /// Write a whole LaTeX document with the default preamble
+        #[arg(long)]
+        standalone: bool,
+        /// Write a whole LaTeX document with the preamble from this file
+        #[arg(long, conflicts_with = "standalone")]
+        preamble: Option<PathBuf>,
+        /// The Amark file defining the templates of items
+        #[arg(long, required_if_eq("to", "template"))]
+        templates: Option<PathBuf>,
+        /// Expand includes, conditions, macros, repeat and br items and variables of the inputs
+        #[arg(long)]
+        expand: bool,
+        /// Set a flag for if(key=value) items and a variable, implies --expand
+        #[arg(short = 'D', long, value_name = "KEY=VALUE", value_parser = parse_define)]
+        define: Vec<(String, String)>,
+        /// Warn about undefined variables and replace them by nothing instead of failing
+        #[arg(long)]
+        allow_undefined: bool,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Preview the inputs in the terminal with colors, paged if the output is a terminal
+    View {
+        /// Print directly instead of starting `$PAGER` or `less -R`
+        #[arg(long)]
+        no_pager: bool,
+        /// Expand includes, conditions, macros, repeat and br items and variables of the inputs
+        #[arg(long)]
+        expand: bool,
+        /// Set a flag for if(key=value) items and a variable, implies --expand
+        #[arg(short = 'D', long, value_name = "KEY=VALUE", value_parser = parse_define)]
+        define: Vec<(String, String)>,
+        /// Warn about undefined variables and replace them by nothing instead of failing
+        #[arg(long)]
+        allow_undefined: bool,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+    /// Convert JSON or XML written by `convert` or Markdown into Amark source
+    Import {
+        /// The format to convert from
+        #[arg(long, value_enum, default_value = "json")]
+        from: ImportFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Files or glob patterns to read
+        inputs: Vec<String>,
+    },
+}
+
+/// Output formats of the convert command
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    /// HTML with an element per item
+    Html,
+    /// WordPress block editor markup with core blocks for the HTML names of items
+    #[value(alias = "wp")]
+    Gutenberg,
+    /// A JSON array of the tokens
+    Json,
+    /// A JSON tree of the items with their lines
+    JsonTree,
+    /// LaTeX with environments and commands for the HTML names of items
+    #[value(alias = "tex")]
+    Latex,
+    /// CommonMark with the HTML names of items as elements
+    #[value(alias = "md")]
+    Markdown,
+    /// Items filled into the templates from `--templates`
+    Template,
+    /// Plain text without any markup
+    Text,
+    /// Generic XML with the lines and columns of items, see `schema/amark.xsd`. Expanded items
+    /// only have lines.
+    Xml,
+}
+
+/// Input formats of the import command
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    /// JSON tokens or trees
+    Json,
+    /// CommonMark with HTML names for the items
+    #[value(alias = "md")]
+    Markdown,
+    /// Generic XML written by `convert --to xml`
+    Xml,
+}
+
+/// An input to read from
+#[derive(Debug)]
+enum Input {
+    /// The standard input
+    Stdin,
+    /// A file
+    File(PathBuf),
+}
+
+impl Input {
+    /// The name of the input for diagnostics
+    fn name(&self) -> String {
+        match *self {
+            Self::Stdin => String::from("<stdin>"),
+            Self::File(ref path) => path.display().to_string(),
+        }
+    }
+
+    /// Open the input as a reader, decoding UTF-16 if necessary
+    fn reader(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(match *self {
+            Self::Stdin => Box::new(DecodingReader::new(io::stdin().lock())),
+            Self::File(ref path) => {
+                Box::new(DecodingReader::new(BufReader::new(File::open(path)?)))
+            }
+        })
+    }
+
+    /// Open the input as a token stream
+    fn open(&self) -> io::Result<AmarkStream<Box<dyn BufRead>>> {
+        self.reader().map(AmarkStream::new)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let (patterns, output) = match cli.command {
+        Command::Check { ref inputs, .. }
+        | Command::Dump { ref inputs, .. }
+        | Command::Fmt { ref inputs, .. }
+        | Command::Lint { ref inputs, .. }
+        | Command::View { ref inputs, .. } => (inputs, None),
+        Command::Convert {
+            ref inputs,
+            ref output,
+            ..
+        }
+        | Command::Import {
+            ref inputs,
+            ref output,
+            ..
+        } => (inputs, output.as_ref()),
+    };
+
+    let inputs = match expand_inputs(patterns) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let templates = match cli.command {
+        Command::Convert {
+            to: Format::Template,
+            templates: Some(ref path),
+            ..
+        } => match fs::read(path)
+            .map_err(AmarkError::from)
+            .and_then(|definitions| TemplateRenderer::load(&definitions))
+        {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                eprintln!(
+                    "error: {}: {}",
+                    path.display(),
+                    e.to_string().replace('\n', " ")
+                );
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        _ => TemplateRenderer::new(),
+    };
+
+    let schema = match cli.command {
+        Command::Check {
+            schema: Some(ref path),
+            ..
+        } => match fs::read(path)
+            .map_err(AmarkError::from)
+            .and_then(|definitions| Schema::load(&definitions))
+        {
+            Ok(schema) => Some(schema),
+            Err(e) => {
+                eprintln!(
+                    "error: {}: {}",
+                    path.display(),
+                    e.to_string().replace('\n', " ")
+                );
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        _ => None,
+    };
+    let linter = match cli.command {
+        Command::Lint {
+            config: Some(ref path),
+            ..
+        } => match fs::read(path)
+            .map_err(AmarkError::from)
+            .and_then(|config| Linter::load(&config))
+        {
+            Ok(linter) => linter,
+            Err(e) => {
+                eprintln!(
+                    "error: {}: {}",
+                    path.display(),
+                    e.to_string().replace('\n', " ")
+                );
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        _ => Linter::new(),
+    };
+    // Whether any input doesn't follow the schema
+    let mut violated = false;
+
+    let mut pager = match cli.command {
+        Command::View {
+            no_pager: false, ..
+        } if io::stdout().is_terminal() => start_pager(),
+        _ => None,
+    };
+    let writer: Box<dyn Write> = match output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("error: {}: {}", path.display(), e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        },
+        None => match pager.as_mut().and_then(|pager| pager.stdin.take()) {
+            Some(stdin) => Box::new(stdin),
+            None => Box::new(io::stdout().lock()),
+        },
+    };
+    let mut writer = BufWriter::new(writer);
+
+    let mut code = match cli.command {
+        Command::Fmt {
+            indent,
+            tabs,
+            check,
+            write,
+            ..
+        } => {
+            let indent = if tabs {
+                Indent::Tab
+            } else {
+                Indent::Spaces(indent)
+            };
+            fmt(
+                &inputs,
+                AmarkFormatter::with_indent(indent),
+                check,
+                write,
+                &mut writer,
+            )
+        }
+        Command::Lint { fix, .. } => lint(&inputs, linter, fix, &mut writer),
+        Command::Import { from, .. } => import(&inputs, from, &mut writer),
+        // Without expansion the XML has the columns of items in the source
+        Command::Convert {
+            to: Format::Xml,
+            expand: false,
+            ref define,
+            ..
+        } if define.is_empty() => convert_xml(&inputs, &mut writer),
+        _ => run_each(&inputs, &mut writer, |input, stream, writer| {
+            let mut prepared = preprocess(&cli.command, input, stream);
+            let stream = &mut prepared;
+            let result = match cli.command {
+                Command::Check { .. } => match schema {
+                    Some(ref schema) => {
+                        for violation in Validator::new(schema).validate(stream)? {
+                            eprintln!("error: {}:{}: {}", input.name(), violation.line, violation);
+                            violated = true;
+                        }
+                        Ok(())
+                    }
+                    None => check(stream),
+                },
+                Command::Dump { json: true, .. } => json::write_tokens(stream, writer),
+                Command::Dump { json: false, .. } => dump(stream, writer),
+                Command::Convert {
+                    to: Format::Html, ..
+                } => HtmlRenderer::new().render(stream, writer),
+                Command::Convert {
+                    to: Format::Gutenberg,
+                    ..
+                } => GutenbergRenderer::new().render(stream, writer),
+                Command::Convert {
+                    to: Format::Json, ..
+                } => json::write_tokens(stream, writer),
+                Command::Convert {
+                    to: Format::JsonTree,
+                    ..
+                } => json::write_tree(stream, writer),
+                Command::Convert {
+                    to: Format::Latex,
+                    standalone,
+                    ref preamble,
+                    ..
+                } => {
+                    let mut renderer = LatexRenderer::new();
+                    if let Some(path) = preamble {
+                        renderer = renderer.preamble(fs::read_to_string(path)?);
+                    } else if standalone {
+                        renderer = renderer.preamble(LatexRenderer::DEFAULT_PREAMBLE);
+                    }
+                    renderer.render(stream, writer)
+                }
+                Command::Convert {
+                    to: Format::Markdown,
+                    ..
+                } => MarkdownRenderer::new().render(stream, writer),
+                Command::Convert {
+                    to: Format::Template,
+                    ..
+                } => {
+                    for missing in templates.render(stream, writer)? {
+                        eprintln!("warning: {}:{}: {}", input.name(), missing.line, missing);
+                    }
+                    Ok(())
+                }
+                Command::Convert {
+                    to: Format::Xml, ..
+                } => xml::write_stream_tree(stream, writer),
+                Command::Convert {
+                    to: Format::Text,
+                    wrap,
+                    labels,
+                    ..
+                } => {
+                    let mut renderer = TextRenderer::new().labels(labels);
+                    if let Some(width) = wrap {
+                        renderer = renderer.wrap(width);
+                    }
+                    renderer.render(stream, writer)
+                }
+                Command::View { .. } => AnsiRenderer::new().render(stream, writer),
+                Command::Fmt { .. } | Command::Lint { .. } | Command::Import { .. } => {
+                    unreachable!("Formatting, linting and importing are handled separately")
+                }
+            };
+            for undefined in prepared.undefined() {
+                eprintln!(
+                    "warning: {}:{}: {}",
+                    input.name(),
+                    undefined.line,
+                    undefined
+                );
+            }
+            result
+        }),
+    };
+
+    if violated && code == ExitCode::SUCCESS {
+        code = ExitCode::from(EXIT_INVALID);
+    }
+    if let Err(e) = writer.flush() {
+        eprintln!("error: Writing the output failed: {}", e);
+        return ExitCode::from(EXIT_ERROR);
+    }
+    // The pager ends once its input is closed
+    drop(writer);
+    if let Some(mut pager) = pager {
+        let _ = pager.wait();
+    }
+
+    code
+}
+
+/// The adapters of `--expand` around the stream of an input
+type Expanded<'s> = VariableExpander<
+    RepeatExpander<MacroExpander<ConditionFilter<IncludeExpander<&'s mut dyn TokenStream>>>>,
+>;
+
+/// The stream of an input as the command asks for it
+enum Prepared<'s> {
+    /// The stream as it is read
+    Raw(&'s mut dyn TokenStream),
+    /// The stream with includes, conditions, macros, repeat and br items and variables expanded
+    Expanded(Box<Expanded<'s>>),
+}
+
+impl Prepared<'_> {
+    /// The undefined variables used so far if `--allow-undefined` is set
+    fn undefined(&self) -> &[UndefinedVariable] {
+        match self {
+            Prepared::Raw(_) => &[],
+            Prepared::Expanded(stream) => stream.undefined(),
+        }
+    }
+}
+
+impl TokenStream for Prepared<'_> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        match self {
+            Prepared::Raw(stream) => stream.next_token(),
+            Prepared::Expanded(stream) => stream.next_token(),
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        match self {
+            Prepared::Raw(stream) => stream.cur_line(),
+            Prepared::Expanded(stream) => stream.cur_line(),
+        }
+    }
+}
+
+/// Wrap the stream of an input into the adapters the command asks for
+fn preprocess<'s>(
+    command: &Command,
+    input: &Input,
+    stream: &'s mut dyn TokenStream,
+) -> Prepared<'s> {
+    let (Command::Convert {
+        expand,
+        ref define,
+        allow_undefined,
+        ..
+    }
+    | Command::View {
+        expand,
+        ref define,
+        allow_undefined,
+        ..
+    }) = *command
+    else {
+        return Prepared::Raw(stream);
+    };
+    if !expand && define.is_empty() {
+        return Prepared::Raw(stream);
+    }
+
+    let mut conditions = ConditionFilter::new(IncludeExpander::new(stream, input.name()));
+    for (key, value) in define {
+        conditions = conditions.flag(key.as_str(), value.as_str());
+    }
+    let mut variables = VariableExpander::new(RepeatExpander::new(MacroExpander::new(conditions)))
+        .strict(!allow_undefined);
+    for (key, value) in define {
+        variables = variables.variable(key.as_str(), value.as_str());
+    }
+    Prepared::Expanded(Box::new(variables))
+}
+
+/// Parse `key=value` of `--define`, a `key` alone is set to `true`
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    let (key, value) = define.split_once('=').unwrap_or((define, "true"));
+    if key.is_empty() {
+        return Err(String::from("The key is empty"));
+    }
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+/// Start `$PAGER` or `less -R` with its input piped, `None` if it can't be started
+fn start_pager() -> Option<Child> {
+    let pager = env::var("PAGER").unwrap_or_else(|_| String::from("less -R"));
+    let mut args = pager.split_whitespace();
+    process::Command::new(args.next()?)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()
+}
+
+/// Expand the given files and glob patterns, no patterns or `-` stand for stdin
+fn expand_inputs(patterns: &[String]) -> Result<Vec<Input>, String> {
+    if patterns.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+
+    let mut inputs = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        if pattern == "-" {
+            inputs.push(Input::Stdin);
+        } else if pattern.contains(['*', '?', '[']) {
+            let paths = glob::glob(pattern).map_err(|e| format!("{}: {}", pattern, e))?;
+            let start = inputs.len();
+            for path in paths {
+                inputs.push(Input::File(path.map_err(|e| e.to_string())?));
+            }
+            if inputs.len() == start {
+                return Err(format!("{}: No files match the pattern", pattern));
+            }
+        } else {
+            inputs.push(Input::File(PathBuf::from(pattern)));
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Run the command on every input and report errors with their location. Returns the exit code.
+fn run_each<W: Write>(
+    inputs: &[Input],
+    writer: &mut W,
+    mut command: impl FnMut(
+        &Input,
+        &mut AmarkStream<Box<dyn BufRead>>,
+        &mut W,
+    ) -> Result<(), AmarkError<'static>>,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let mut stream = match input.open() {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: {}: {}", input.name(), e);
+                code = ExitCode::from(EXIT_ERROR);
+                continue;
+            }
+        };
+
+        // Front matter is no content, it is only checked
+        let result = stream
+            .read_metadata()
+            .and_then(|_| command(input, &mut stream, writer));
+        if let Err(e) = result {
+            let _ = writer.flush();
+            report(input, stream.cur_line(), e, &mut code);
+        }
+    }
+
+    code
+}
+
+/// Print an error of an input and update the exit code accordingly
+fn report(input: &Input, line: usize, error: AmarkError, code: &mut ExitCode) {
+    match error {
+        AmarkError::IoError(e) => {
+            eprintln!("error: {}: {}", input.name(), e);
+            *code = ExitCode::from(EXIT_ERROR);
+        }
+        e => {
+            eprintln!(
+                "error: {}:{}: {}",
+                input.name(),
+                line,
+                e.to_string().replace('\n', " ")
+            );
+            if *code == ExitCode::SUCCESS {
+                *code = ExitCode::from(EXIT_INVALID);
+            }
+        }
+    }
+}
+
+/// Format every input and print, check or write back the result. Returns the exit code.
+fn fmt<W: Write>(
+    inputs: &[Input],
+    mut formatter: AmarkFormatter,
+    check: bool,
+    write: bool,
+    writer: &mut W,
+) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let mut source = Vec::new();
+        if let Err(e) = input.reader().and_then(|mut r| r.read_to_end(&mut source)) {
+            report(input, 0, e.into(), &mut code);
+            continue;
+        }
+
+        // Front matter is written back as it is
+        let mut stream = AmarkStream::new(source.as_slice());
+        let mut formatted = Vec::with_capacity(source.len());
+        let result = stream.read_metadata().and_then(|_| {
+            formatted.extend_from_slice(metadata::front_matter(&source)?);
+            formatter.format(&mut stream, &mut formatted)
+        });
+        if let Err(e) = result {
+            report(input, stream.cur_line(), e, &mut code);
+            continue;
+        }
+
+        let result = match *input {
+            _ if check => {
+                if formatted != source {
+                    eprintln!("{}: Not formatted", input.name());
+                    if code == ExitCode::SUCCESS {
+                        code = ExitCode::from(EXIT_INVALID);
+                    }
+                }
+                Ok(())
+            }
+            Input::File(ref path) if write => {
+                if formatted == source {
+                    Ok(())
+                } else {
+                    fs::write(path, &formatted)
+                }
+            }
+            _ => writer.write_all(&formatted),
+        };
+
+        if let Err(e) = result {
+            report(input, 0, e.into(), &mut code);
+        }
+    }
+
+    code
+}
+
+/// Lint every input, fix it first if asked to, and report the lints. Returns the exit code.
+fn lint<W: Write>(inputs: &[Input], mut linter: Linter, fix: bool, writer: &mut W) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let mut source = Vec::new();
+        if let Err(e) = input.reader().and_then(|mut r| r.read_to_end(&mut source)) {
+            report(input, 0, e.into(), &mut code);
+            continue;
+        }
+
+        // Check the source first to report errors with their line
+        let mut stream = AmarkStream::new(source.as_slice());
+        if let Err(e) = stream.read_metadata().and_then(|_| check(&mut stream)) {
+            report(input, stream.cur_line(), e, &mut code);
+            continue;
+        }
+
+        if fix {
+            let result = linter.fix(&source).and_then(|fixed| {
+                match *input {
+                    Input::File(ref path) if fixed != source => fs::write(path, &fixed)?,
+                    Input::File(_) => (),
+                    Input::Stdin => writer.write_all(&fixed)?,
+                }
+                source = fixed;
+                Ok(())
+            });
+            if let Err(e) = result {
+                report(input, 0, e, &mut code);
+                continue;
+            }
+        }
+
+        match linter.lint(&source) {
+            Ok(lints) => {
+                for lint in lints {
+                    eprintln!(
+                        "{}: {}:{}: {}",
+                        lint.severity,
+                        input.name(),
+                        lint.line,
+                        lint
+                    );
+                    if lint.severity == Severity::Error && code == ExitCode::SUCCESS {
+                        code = ExitCode::from(EXIT_INVALID);
+                    }
+                }
+            }
+            Err(e) => report(input, 0, e, &mut code),
+        }
+    }
+
+    code
+}
+
+/// Convert every input to XML, which needs the whole source for the columns of items. Returns the
+/// exit code.
+fn convert_xml<W: Write>(inputs: &[Input], writer: &mut W) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let mut source = Vec::new();
+        if let Err(e) = input.reader().and_then(|mut r| r.read_to_end(&mut source)) {
+            report(input, 0, e.into(), &mut code);
+            continue;
+        }
+
+        // Check the source first to report errors with their line
+        let mut stream = AmarkStream::new(source.as_slice());
+        if let Err(e) = stream.read_metadata().and_then(|_| check(&mut stream)) {
+            report(input, stream.cur_line(), e, &mut code);
+            continue;
+        }
+        if let Err(e) = xml::write_tree(&source, writer) {
+            report(input, 0, e, &mut code);
+        }
+    }
+
+    code
+}
+
+/// Convert every JSON, XML or Markdown input to Amark source. Returns the exit code.
+fn import<W: Write>(inputs: &[Input], from: ImportFormat, writer: &mut W) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+
+    for input in inputs {
+        let mut source = Vec::new();
+        if let Err(e) = input.reader().and_then(|mut r| r.read_to_end(&mut source)) {
+            report(input, 0, e.into(), &mut code);
+            continue;
+        }
+
+        let result = match from {
+            ImportFormat::Json => json::import(&source, writer),
+            ImportFormat::Xml => xml::import(&source, writer),
+            ImportFormat::Markdown => {
+                MarkdownImporter::new().import(&String::from_utf8_lossy(&source), writer)
+            }
+        };
+        match result {
+            Ok(()) => (),
+            Err(AmarkError::IoError(e)) => report(input, 0, e.into(), &mut code),
+            Err(e) => {
+                eprintln!(
+                    "error: {}: {}",
+                    input.name(),
+                    e.to_string().replace('\n', " ")
+                );
+                if code == ExitCode::SUCCESS {
+                    code = ExitCode::from(EXIT_INVALID);
+                }
+            }
+        }
+    }
+
+    code
+}
+
+/// Parse all tokens without any output
+fn check(mut stream: impl TokenStream) -> Result<(), AmarkError<'static>> {
+    while stream.next_token()? != AmarkToken::End {}
+    Ok(())
+}
+
+/// Print all tokens, one per line
+fn dump(mut stream: impl TokenStream, writer: &mut impl Write) -> Result<(), AmarkError<'static>> {
+    loop {
+        let tok = stream.next_token()?;
+        tok.dump(writer)?;
+        writer.write_all(b"\n")?;
+
+        if tok == AmarkToken::End {
+            return Ok(());
+        }
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::io::{self, BufRead};
 
+use crate::encoding::Encoding;
+
 /// A specialized buffer to work process incoming text.
 #[derive(Debug)]
 pub struct Buf {
@@ -9,6 +11,8 @@ pub struct Buf {
     storage: Vec<u8>,
     /// How many bytes have been processed in the given line
     processed: usize,
+    /// Offset of the current line in the whole input
+    line_start: usize,
 }
 
 impl Buf {
@@ -17,6 +21,7 @@ impl Buf {
         Self {
             storage,
             processed: 0,
+            line_start: 0,
         }
     }
 
@@ -35,13 +40,41 @@ impl Buf {
         // another buffer, buffering ourselves, calculating the line breaks ONCE and
         // going over those lines
 
+        self.line_start = self.line_start.saturating_add(self.storage.len());
         self.storage.clear();
         self.processed = 0;
         *cur_line = cur_line.wrapping_add(1);
         reader.read_until(b'\n', &mut self.storage)?;
+
+        if *cur_line == 1 {
+            self.skip_bom()?;
+        }
+
         Ok(())
     }
 
+    /// Skip a UTF-8 byte order mark at the start of the storage. UTF-16 input can't be parsed
+    /// directly and results in an error, it has to be transcoded first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error when the storage starts with a UTF-16 BOM.
+    fn skip_bom(&mut self) -> io::Result<()> {
+        match Encoding::detect(&self.storage) {
+            (Encoding::Utf8, bom_len) => {
+                Self::process(&mut self.processed, bom_len);
+                Ok(())
+            }
+            (encoding, _) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Input is {} encoded, wrap the reader in a DecodingReader to parse it",
+                    encoding
+                ),
+            )),
+        }
+    }
+
     // /// Skips forward in the buffer until the pattern is found. Returns None if the Pattern is not
     // /// found or else the subslice from where the pattern was found until the end of the buffer.
     // pub fn skip_until(&mut self, mut pattern: impl FnMut(u8) -> bool) -> Option<&[u8]> {
@@ -126,9 +159,15 @@ impl Buf {
         Ok(false)
     }
 
-    // pub fn rest(&self) -> &[u8] {
-    //     self.storage.get(self.processed..).unwrap_or(&[])
-    // }
+    /// The bytes of the current line which weren't processed yet
+    pub fn rest(&self) -> &[u8] {
+        self.storage.get(self.processed..).unwrap_or(&[])
+    }
+
+    /// Mark the rest of the current line as processed
+    pub fn skip_rest(&mut self) {
+        self.processed = self.storage.len();
+    }
 
     /// Retrieve the next byte if one is available
     pub fn next_byte(&mut self) -> Option<u8> {
@@ -150,6 +189,11 @@ impl Buf {
         self.processed = self.processed.saturating_sub(n);
     }
 
+    /// The offset of the next byte to process in the whole input
+    pub fn offset(&self) -> usize {
+        self.line_start.saturating_add(self.processed)
+    }
+
     /// Check if the given storage of this buffer is empty. This means no bytes could be read
     /// anymore not. This does not indicate wether there are more bytes to process currently
     pub fn storage_empty(&self) -> bool {
@@ -0,0 +1,344 @@
+//! Conversion of CommonMark to Amark. Every Markdown construct becomes the item configured for
+//! its [`MarkdownElement`], the text is escaped so it reads back exactly as Markdown parsed it.
+//! Whitespace between text and inline items is left out, renderers separate them with a space.
+//!
+//! Constructs without an element use fixed names: links become `a(url)` or `a(url,title)`
+//! blocks, images `img(url,alt);`, thematic breaks `hr;` and raw HTML `html` blocks.
+
+use std::{collections::HashMap, io::Write};
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag};
+
+use crate::{markdown::MarkdownElement, AmarkResult, AmarkWriter};
+
+/// Converts CommonMark to Amark source
+#[derive(Debug, Clone)]
+pub struct MarkdownImporter {
+    /// The item name written for each element
+    names: HashMap<MarkdownElement, Vec<u8>>,
+}
+
+impl MarkdownImporter {
+    /// Create a new importer which writes the HTML names `h1` to `h6`, `p`, `em`, `strong`,
+    /// `code`, `pre`, `blockquote`, `ul`, `ol`, `li` and `br` like
+    /// [`MarkdownRenderer`](crate::markdown::MarkdownRenderer) reads them
+    pub fn new() -> Self {
+        let mut names = HashMap::from([
+            (MarkdownElement::Paragraph, b"p".to_vec()),
+            (MarkdownElement::Emphasis, b"em".to_vec()),
+            (MarkdownElement::Strong, b"strong".to_vec()),
+            (MarkdownElement::Code, b"code".to_vec()),
+            (MarkdownElement::CodeBlock, b"pre".to_vec()),
+            (MarkdownElement::Quote, b"blockquote".to_vec()),
+            (MarkdownElement::List { ordered: false }, b"ul".to_vec()),
+            (MarkdownElement::List { ordered: true }, b"ol".to_vec()),
+            (MarkdownElement::ListItem, b"li".to_vec()),
+            (MarkdownElement::LineBreak, b"br".to_vec()),
+        ]);
+        for level in 1..=6 {
+            names.insert(
+                MarkdownElement::Heading(level),
+                format!("h{}", level).into_bytes(),
+            );
+        }
+
+        Self { names }
+    }
+
+    /// Write the given element as items with the given name
+    pub fn element(mut self, element: MarkdownElement, name: impl Into<Vec<u8>>) -> Self {
+        self.names.insert(element, name.into());
+        self
+    }
+
+    /// Convert the Markdown to Amark source and write it to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when a configured name is not a valid item name or writing fails.
+    pub fn import<W: Write>(&self, markdown: &str, writer: &mut W) -> AmarkResult<'static, ()> {
+        let mut import = Import {
+            names: &self.names,
+            events: Parser::new(markdown).collect(),
+            pos: 0,
+        };
+        let mut amark = AmarkWriter::new(&mut *writer);
+        import.blocks(&mut amark)?;
+        amark.finish()?;
+        Ok(())
+    }
+}
+
+impl Default for MarkdownImporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The state of a single conversion
+struct Import<'n, 'e> {
+    /// The configured item names
+    names: &'n HashMap<MarkdownElement, Vec<u8>>,
+    /// All events of the Markdown document
+    events: Vec<Event<'e>>,
+    /// The index of the next event
+    pos: usize,
+}
+
+impl Import<'_, '_> {
+    /// Write block level events as items until the end of the current block. Inline events
+    /// between them are wrapped into paragraphs.
+    fn blocks<W: Write>(&mut self, w: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        while let Some(event) = self.events.get(self.pos) {
+            match event {
+                Event::End(_) => return Ok(()),
+                Event::Start(tag) if is_block(tag) => {
+                    let tag = tag.clone();
+                    self.pos += 1;
+                    self.block(tag, w)?;
+                }
+                Event::Rule => {
+                    self.pos += 1;
+                    w.item("hr")?.end()?;
+                }
+                _ => {
+                    w.item(self.name(MarkdownElement::Paragraph))?
+                        .block(|w| self.inlines(w))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the block level element which was started with the given tag
+    fn block<W: Write>(&mut self, tag: Tag, w: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        match tag {
+            Tag::Paragraph => {
+                w.item(self.name(MarkdownElement::Paragraph))?
+                    .block(|w| self.inlines(w))?;
+            }
+            Tag::Heading { level, .. } => {
+                w.item(self.name(MarkdownElement::Heading(heading_level(level))))?
+                    .block(|w| self.inlines(w))?;
+            }
+            Tag::CodeBlock(kind) => {
+                w.item(self.name(MarkdownElement::CodeBlock))?;
+                if let CodeBlockKind::Fenced(lang) = kind {
+                    if let Some(lang) = lang.split_whitespace().next() {
+                        w.params([lang])?;
+                    }
+                }
+                let code = self.raw_text();
+                let code = code.strip_suffix('\n').unwrap_or(&code);
+                w.block(|w| {
+                    w.text(code)?;
+                    Ok(())
+                })?;
+            }
+            Tag::HtmlBlock => {
+                let html = self.raw_text();
+                let html = html.strip_suffix('\n').unwrap_or(&html);
+                w.item("html")?.block(|w| {
+                    w.text(html)?;
+                    Ok(())
+                })?;
+            }
+            Tag::BlockQuote(_) => {
+                w.item(self.name(MarkdownElement::Quote))?
+                    .container(|w| self.blocks(w))?;
+            }
+            Tag::List(start) => {
+                let ordered = start.is_some();
+                w.item(self.name(MarkdownElement::List { ordered }))?
+                    .container(|w| self.blocks(w))?;
+            }
+            Tag::Item => {
+                w.item(self.name(MarkdownElement::ListItem))?;
+                // Items of tight lists hold their text directly
+                if self.has_blocks() {
+                    w.container(|w| self.blocks(w))?;
+                } else {
+                    w.block(|w| self.inlines(w))?;
+                }
+            }
+            _ => self.blocks(w)?,
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Write inline events as text and items until the end of the current element or the next
+    /// block level event
+    fn inlines<W: Write>(&mut self, w: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        // Markdown splits text at special characters, every text call would start a new line
+        let mut text = String::new();
+        // Whitespace next to inline items is left out, renderers separate them from text anyway
+        let mut after_item = false;
+
+        while let Some(event) = self.events.get(self.pos) {
+            match event {
+                Event::Text(t) => text.push_str(t),
+                Event::SoftBreak => text.push('\n'),
+                Event::End(_) | Event::Rule => break,
+                Event::Start(tag) if is_block(tag) => break,
+                _ => {
+                    w.text(trim_around_items(&text, after_item, true))?;
+                    text.clear();
+                    self.inline(w)?;
+                    after_item = true;
+                    continue;
+                }
+            }
+            self.pos += 1;
+        }
+
+        w.text(trim_around_items(&text, after_item, false))?;
+        Ok(())
+    }
+
+    /// Write the inline event at the current position as an item
+    fn inline<W: Write>(&mut self, w: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        let event = self.events[self.pos].clone();
+        self.pos += 1;
+
+        match event {
+            Event::Code(code) => {
+                w.item(self.name(MarkdownElement::Code))?.block(|w| {
+                    w.text(code.as_bytes())?;
+                    Ok(())
+                })?;
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                w.item("html")?.block(|w| {
+                    w.text(html.as_bytes())?;
+                    Ok(())
+                })?;
+            }
+            Event::HardBreak => {
+                w.item(self.name(MarkdownElement::LineBreak))?.end()?;
+            }
+            Event::Start(Tag::Emphasis) => self.inline_block(MarkdownElement::Emphasis, w)?,
+            Event::Start(Tag::Strong) => self.inline_block(MarkdownElement::Strong, w)?,
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            }) => {
+                w.item("a")?;
+                match title.trim() {
+                    "" => w.params([dest_url.trim()])?,
+                    title => w.params([dest_url.trim(), title])?,
+                };
+                w.block(|w| self.inlines(w))?;
+                self.pos += 1;
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                let alt = self.raw_text();
+                self.pos += 1;
+                w.item("img")?
+                    .params([dest_url.trim(), alt.trim()])?
+                    .end()?;
+            }
+            Event::Start(_) => {
+                self.inlines(w)?;
+                self.pos += 1;
+            }
+            // Only produced with extensions which are not enabled
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Write the current inline element as an item with a block
+    fn inline_block<W: Write>(
+        &mut self,
+        element: MarkdownElement,
+        w: &mut AmarkWriter<W>,
+    ) -> AmarkResult<'static, ()> {
+        w.item(self.name(element))?.block(|w| self.inlines(w))?;
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Collect the text of all events until the end of the current element
+    fn raw_text(&mut self) -> String {
+        let mut text = String::new();
+        let mut depth = 0usize;
+
+        while let Some(event) = self.events.get(self.pos) {
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == 0 => break,
+                Event::End(_) => depth -= 1,
+                Event::Text(t) | Event::Code(t) | Event::Html(t) | Event::InlineHtml(t) => {
+                    text.push_str(t)
+                }
+                Event::SoftBreak | Event::HardBreak => text.push('\n'),
+                _ => (),
+            }
+            self.pos += 1;
+        }
+
+        text
+    }
+
+    /// Whether the current element contains block level elements
+    fn has_blocks(&self) -> bool {
+        let mut depth = 0usize;
+
+        for event in &self.events[self.pos..] {
+            match event {
+                Event::Start(tag) if depth == 0 && is_block(tag) => return true,
+                Event::Rule if depth == 0 => return true,
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == 0 => break,
+                Event::End(_) => depth -= 1,
+                _ => (),
+            }
+        }
+
+        false
+    }
+
+    /// The item name of the given element
+    fn name(&self, element: MarkdownElement) -> &[u8] {
+        self.names.get(&element).map_or(b"", Vec::as_slice)
+    }
+}
+
+/// Trim the whitespace of text after and before inline items
+fn trim_around_items(text: &str, after_item: bool, before_item: bool) -> &str {
+    let text = if after_item { text.trim_start() } else { text };
+    if before_item {
+        text.trim_end()
+    } else {
+        text
+    }
+}
+
+/// Whether the tag starts a block level element
+fn is_block(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::CodeBlock(_)
+            | Tag::HtmlBlock
+            | Tag::List(_)
+            | Tag::Item
+    )
+}
+
+/// The number of the heading level
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
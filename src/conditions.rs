@@ -0,0 +1,289 @@
+//! Conditional items which are kept or dropped by flags, evaluated on the token stream.
+//!
+//! `if(site=de) [ … ]`, or `@if(site=de) { … }` inside of blocks, is replaced by its content when
+//! the condition holds and dropped otherwise. An `else` item after it, with nothing but empty lines
+//! in between, is kept when the condition doesn't hold. Conditions are `key=value`, `key!=value` or `key`, which holds when the
+//! flag is set to anything but `false`. Every parameter has to hold.
+//!
+//! Flags which aren't set are errors unless the filter is told to collect them as warnings with
+//! [`ConditionFilter::strict`], then they count as not set.
+//!
+//! ```
+//! use amark::{conditions::ConditionFilter, AmarkFormatter, AmarkStream};
+//!
+//! let source = b"if(site=de) [\n    p { Hallo }\n]\nelse [\n    p { Hello }\n]";
+//! let mut filtered = Vec::new();
+//! let filter = ConditionFilter::new(AmarkStream::new(source.as_slice())).flag("site", "en");
+//! AmarkFormatter::new().format(filter, &mut filtered)?;
+//!
+//! assert_eq!(filtered, b"p {\n    Hello \n}\n");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    event::parse_params,
+    stream::{read_group, TokenStream},
+    AmarkError, AmarkResult, AmarkToken, ByteDisp, OwnedToken,
+};
+
+/// The name of the conditional item
+const IF: &[u8] = b"if";
+
+/// The name of the item kept when the condition before doesn't hold
+const ELSE: &[u8] = b"else";
+
+/// A flag which was used in a condition without being set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFlag {
+    /// The name of the flag
+    pub name: Vec<u8>,
+    /// The line the condition is on
+    pub line: usize,
+}
+
+impl Display for UnknownFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown flag {}", ByteDisp(&self.name))
+    }
+}
+
+/// A stream adapter which keeps or drops conditional items, see the [module docs](self)
+#[derive(Debug)]
+pub struct ConditionFilter<S> {
+    /// The stream of the document
+    stream: S,
+    /// The flags conditions are evaluated against
+    flags: HashMap<Vec<u8>, Vec<u8>>,
+    /// The open items, the innermost last
+    open: Vec<Open>,
+    /// Whether the condition of an `if` item which just ended held, `else` can follow it
+    ended: Option<bool>,
+    /// The empty lines read after an `if` item while looking for `else`
+    skipped: Vec<OwnedToken>,
+    /// The tokens to hand out before reading on, the skipped ones when no `else` followed
+    queued: VecDeque<OwnedToken>,
+    /// Whether unknown flags are errors
+    strict: bool,
+    /// The unknown flags used so far if they aren't errors
+    unknown: Vec<UnknownFlag>,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+/// An open block or container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Open {
+    /// A block which is handed out, it contains text
+    Block,
+    /// A container which is handed out, it contains items
+    Container,
+    /// A kept `if` item, its start and end are dropped
+    If,
+    /// A kept `else` item, its start and end are dropped
+    Else,
+}
+
+impl<S: TokenStream> ConditionFilter<S> {
+    /// Create a filter over the given stream without any flags
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            flags: HashMap::new(),
+            open: Vec::new(),
+            ended: None,
+            skipped: Vec::new(),
+            queued: VecDeque::new(),
+            strict: true,
+            unknown: Vec::new(),
+            current: OwnedToken::End,
+        }
+    }
+
+    /// Set a flag conditions are evaluated against
+    pub fn flag(mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.flags.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set whether unknown flags are errors, which is the default, or collected as warnings
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The unknown flags used so far, they are only collected if the filter isn't strict
+    pub fn unknown(&self) -> &[UnknownFlag] {
+        &self.unknown
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Whether the innermost handed out item is a block, kept conditional items are spliced into it
+    fn in_block(&self) -> bool {
+        self.open
+            .iter()
+            .rev()
+            .find(|&&open| matches!(open, Open::Block | Open::Container))
+            == Some(&Open::Block)
+    }
+
+    /// The next token of the stream
+    fn next_raw(&mut self) -> AmarkResult<'static, OwnedToken> {
+        Ok(self.stream.next_token()?.to_owned_token())
+    }
+
+    /// Read a conditional item after its name, `held` is `None` for `if` and whether the
+    /// condition before held for `else`
+    fn conditional(&mut self, held: Option<bool>) -> AmarkResult<'static, ()> {
+        let (name, open) = match held {
+            None => (IF, Open::If),
+            Some(_) => (ELSE, Open::Else),
+        };
+        let mut tok = self.next_raw()?;
+        let keep = match held {
+            None if tok == OwnedToken::ParamsStart => {
+                let conditions =
+                    parse_params(read_group(|| self.next_raw(), b"The end of the condition")?)?;
+                tok = self.next_raw()?;
+                self.evaluate(&conditions)?
+            }
+            Some(held) => !held,
+            None => return Err(unexpected(b"if(condition) with a block or container", IF)),
+        };
+        match tok {
+            OwnedToken::ContainerStart => (),
+            OwnedToken::BlockStart if self.in_block() => (),
+            OwnedToken::BlockStart => {
+                return Err(unexpected(
+                    b"A container after a condition outside of blocks",
+                    name,
+                ));
+            }
+            _ => return Err(unexpected(b"A block or container after a condition", name)),
+        }
+
+        if keep {
+            self.open.push(open);
+        } else {
+            read_group(|| self.next_raw(), b"The end of the conditional item")?;
+            self.ended = held.is_none().then_some(false);
+        }
+        Ok(())
+    }
+
+    /// Whether all conditions hold
+    fn evaluate(&mut self, conditions: &[Vec<u8>]) -> AmarkResult<'static, bool> {
+        if conditions.is_empty() {
+            return Err(unexpected(b"if(condition) with a condition", IF));
+        }
+
+        let mut holds = true;
+        for condition in conditions {
+            let (name, expected, equal) = match condition.iter().position(|&b| b == b'=') {
+                Some(pos) if pos > 0 && condition[pos - 1] == b'!' => {
+                    (&condition[..pos - 1], Some(&condition[pos + 1..]), false)
+                }
+                Some(pos) => (&condition[..pos], Some(&condition[pos + 1..]), true),
+                None => (condition.as_slice(), None, true),
+            };
+            let name = name.trim_ascii();
+            if name.is_empty() {
+                return Err(unexpected(b"key=value, key!=value or key", condition));
+            }
+
+            let value = self.flag_value(name)?;
+            holds &= match expected {
+                Some(expected) => (value == Some(expected.trim_ascii())) == equal,
+                None => value.is_some_and(|value| value != b"false"),
+            };
+        }
+        Ok(holds)
+    }
+
+    /// The value of a flag, unknown flags are errors or collected as warnings
+    fn flag_value(&mut self, name: &[u8]) -> AmarkResult<'static, Option<&[u8]>> {
+        if !self.flags.contains_key(name) {
+            if self.strict {
+                return Err(AmarkError::UnknownFlag {
+                    name: name.to_vec(),
+                });
+            }
+            self.unknown.push(UnknownFlag {
+                name: name.to_vec(),
+                line: self.stream.cur_line(),
+            });
+        }
+        Ok(self.flags.get(name).map(Vec::as_slice))
+    }
+}
+
+impl<S: TokenStream> TokenStream for ConditionFilter<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        loop {
+            let ended = self.ended.take();
+            let tok = match self.queued.pop_front() {
+                Some(tok) => tok,
+                None => self.next_raw()?,
+            };
+            match tok {
+                OwnedToken::EmptyLine if ended.is_some() => {
+                    self.skipped.push(tok);
+                    self.ended = ended;
+                }
+                OwnedToken::ItemName(ref name) if name == ELSE => match ended {
+                    Some(held) => {
+                        self.skipped.clear();
+                        self.conditional(Some(held))?;
+                    }
+                    None => return Err(unexpected(b"else right after if", ELSE)),
+                },
+                tok if !self.skipped.is_empty() => {
+                    self.queued.extend(self.skipped.drain(..));
+                    self.queued.push_back(tok);
+                }
+                OwnedToken::ItemName(ref name) if name == IF => self.conditional(None)?,
+                OwnedToken::BlockEnd | OwnedToken::ContainerEnd
+                    if self
+                        .open
+                        .last()
+                        .is_some_and(|&open| matches!(open, Open::If | Open::Else)) =>
+                {
+                    self.ended = (self.open.pop() == Some(Open::If)).then_some(true);
+                }
+                tok => {
+                    match tok {
+                        OwnedToken::BlockStart => self.open.push(Open::Block),
+                        OwnedToken::ContainerStart => self.open.push(Open::Container),
+                        OwnedToken::BlockEnd | OwnedToken::ContainerEnd => {
+                            self.open.pop();
+                        }
+                        _ => (),
+                    }
+                    self.current = tok;
+                    return Ok(self.current.as_token());
+                }
+            }
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
+
+/// Create an error for a conditional item which can't be evaluated
+fn unexpected(expected: &'static [u8], got: &[u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got.to_vec()),
+    }
+}
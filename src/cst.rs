@@ -0,0 +1,724 @@
+//! A lossless concrete syntax tree of Amark source.
+//!
+//! Every token keeps the exact bytes it was parsed from together with its trivia: the whitespace,
+//! line breaks and stray bytes the reader skips around it. Writing the tree back reproduces the
+//! source byte for byte, so tools can rename, insert and delete items without touching the layout
+//! of the rest of the file.
+//!
+//! Trivia up to and including the line break after a token is attached to that token, everything
+//! else to the token after it.
+
+use std::{
+    io::{self, Write},
+    iter::{self, Peekable},
+    mem, vec,
+};
+
+use crate::{validate_item_name, AmarkError, AmarkReader, AmarkResult, OwnedToken};
+
+/// A token with the source bytes it was parsed from and the trivia around it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstToken {
+    /// Skipped bytes in front of the token
+    leading: Vec<u8>,
+    /// The source of the token itself, e.g. `@name` or `\s`
+    raw: Vec<u8>,
+    /// Skipped bytes after the token up to and including the end of the line
+    trailing: Vec<u8>,
+    /// The parsed token
+    token: OwnedToken,
+}
+
+impl CstToken {
+    /// The skipped bytes in front of the token
+    pub fn leading(&self) -> &[u8] {
+        &self.leading
+    }
+
+    /// The source of the token
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// The skipped bytes after the token up to the end of its line
+    pub fn trailing(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    /// The parsed token
+    pub fn token(&self) -> &OwnedToken {
+        &self.token
+    }
+
+    /// Write the token with its trivia
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.leading)?;
+        writer.write_all(&self.raw)?;
+        writer.write_all(&self.trailing)
+    }
+
+    /// Whether the source continues on the next line after this token
+    fn ends_line(&self) -> bool {
+        self.token == OwnedToken::EmptyLine || self.trailing.contains(&b'\n')
+    }
+}
+
+/// A node of the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// An item
+    Item(ItemNode),
+    /// An escape sequence with its optional parameter list
+    Escape {
+        /// The escape sequence
+        seq: CstToken,
+        /// The parameter list, e.g. `(4)` in `\s(4)`
+        params: Option<Group>,
+    },
+    /// A line of text or an empty line
+    Token(CstToken),
+}
+
+impl Node {
+    /// The first token of this node
+    fn first_token(&self) -> &CstToken {
+        match *self {
+            Self::Item(ref item) => &item.name,
+            Self::Escape { ref seq, .. } => seq,
+            Self::Token(ref tok) => tok,
+        }
+    }
+
+    /// The first token of this node, its leading trivia is the one of the whole node
+    fn first_token_mut(&mut self) -> &mut CstToken {
+        match *self {
+            Self::Item(ref mut item) => &mut item.name,
+            Self::Escape { ref mut seq, .. } => seq,
+            Self::Token(ref mut tok) => tok,
+        }
+    }
+
+    /// The last token of this node, its trailing trivia is the one of the whole node
+    fn last_token_mut(&mut self) -> &mut CstToken {
+        match *self {
+            Self::Item(ItemNode {
+                body: Body::End(ref mut tok),
+                ..
+            }) => tok,
+            Self::Item(ItemNode {
+                body: Body::Block(ref mut group) | Body::Container(ref mut group),
+                ..
+            })
+            | Self::Escape {
+                params: Some(ref mut group),
+                ..
+            } => &mut group.close,
+            Self::Escape { ref mut seq, .. } => seq,
+            Self::Token(ref mut tok) => tok,
+        }
+    }
+
+    /// Whether this is an escape sequence without parameters
+    fn is_bare_escape(&self) -> bool {
+        matches!(*self, Self::Escape { params: None, .. })
+    }
+
+    /// Collect all tokens of this node in source order
+    fn collect_tokens<'a>(&'a self, out: &mut Vec<&'a CstToken>) {
+        match *self {
+            Self::Item(ref item) => {
+                out.push(&item.name);
+                for params in &item.params {
+                    params.collect_tokens(out);
+                }
+                match item.body {
+                    Body::End(ref tok) => out.push(tok),
+                    Body::Block(ref group) | Body::Container(ref group) => {
+                        group.collect_tokens(out);
+                    }
+                }
+            }
+            Self::Escape {
+                ref seq,
+                ref params,
+            } => {
+                out.push(seq);
+                if let Some(ref params) = *params {
+                    params.collect_tokens(out);
+                }
+            }
+            Self::Token(ref tok) => out.push(tok),
+        }
+    }
+}
+
+/// An item with its name, parameters and body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemNode {
+    /// The name, including the `@` of items inside of blocks
+    name: CstToken,
+    /// The parameter lists, the reader accepts more than one like in `a(1)(2);`
+    params: Vec<Group>,
+    /// What follows the name and parameters
+    body: Body,
+}
+
+impl ItemNode {
+    /// The name of the item
+    pub fn name(&self) -> &[u8] {
+        match self.name.token {
+            OwnedToken::ItemName(ref name) => name,
+            _ => unreachable!("The name of an item is always an item name token"),
+        }
+    }
+
+    /// The token of the name
+    pub fn name_token(&self) -> &CstToken {
+        &self.name
+    }
+
+    /// The parameter lists of the item, usually there is at most one
+    pub fn params(&self) -> &[Group] {
+        &self.params
+    }
+
+    /// The body of the item
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// The content of a block or container, `None` for items ended by `;`
+    pub fn children(&self) -> Option<&[Node]> {
+        match self.body {
+            Body::Block(ref group) | Body::Container(ref group) => Some(&group.children),
+            Body::End(_) => None,
+        }
+    }
+
+    /// Rename the item, everything around the name stays untouched
+    ///
+    /// # Errors
+    ///
+    /// This errors when the name is not a valid item name.
+    pub fn rename(&mut self, name: impl AsRef<[u8]>) -> AmarkResult<'static, ()> {
+        let name = name.as_ref();
+        validate_item_name(name)?;
+
+        let inline = self.name.raw.len() > self.name().len();
+        self.name.raw.clear();
+        if inline {
+            self.name.raw.push(b'@');
+        }
+        self.name.raw.extend_from_slice(name);
+        self.name.token = OwnedToken::ItemName(name.to_vec());
+
+        Ok(())
+    }
+}
+
+/// What follows the name and parameters of an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    /// The `;` of an item without content
+    End(CstToken),
+    /// A block `{ ... }`
+    Block(Group),
+    /// A container `[ ... ]`
+    Container(Group),
+}
+
+/// Nodes enclosed by an opening and a closing token, a block, container or parameter list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    /// The opening `{`, `[` or `(`
+    open: CstToken,
+    /// The enclosed nodes
+    children: Vec<Node>,
+    /// The closing `}`, `]` or `)`
+    close: CstToken,
+}
+
+impl Group {
+    /// The opening token
+    pub fn open(&self) -> &CstToken {
+        &self.open
+    }
+
+    /// The enclosed nodes
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// The closing token
+    pub fn close(&self) -> &CstToken {
+        &self.close
+    }
+
+    /// Collect all tokens of this group in source order
+    fn collect_tokens<'a>(&'a self, out: &mut Vec<&'a CstToken>) {
+        out.push(&self.open);
+        for child in &self.children {
+            child.collect_tokens(out);
+        }
+        out.push(&self.close);
+    }
+}
+
+/// A lossless syntax tree of a whole document
+///
+/// Nodes are addressed by paths of indices, `[2, 0]` is the first node in the block or container
+/// of the third node at the top level.
+///
+/// ```
+/// use amark::cst::Cst;
+///
+/// let mut cst = Cst::parse(b"gb {\n  A green box\n}\nl;  \n")?;
+/// cst.rename_all(b"gb", b"green_box")?;
+/// let path = cst.find_item(b"l").unwrap();
+/// cst.delete(&path);
+///
+/// assert_eq!(cst.to_bytes(), b"green_box {\n  A green box\n}\n");
+/// # Ok::<(), amark::AmarkError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst {
+    /// The nodes at the top level
+    children: Vec<Node>,
+    /// The end of the input with the trivia before it
+    end: CstToken,
+}
+
+impl Cst {
+    /// Parse the given source into a tree
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed.
+    pub fn parse(source: &[u8]) -> AmarkResult<'static, Self> {
+        let mut tokens = lex(source)?.into_iter().peekable();
+        let (children, end) = build_nodes(&mut tokens);
+        Ok(Self { children, end })
+    }
+
+    /// The nodes at the top level
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// All tokens of the document in source order, ending with [`OwnedToken::End`]
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            child.collect_tokens(&mut tokens);
+        }
+        tokens.push(&self.end);
+        tokens
+    }
+
+    /// Write the source of the tree
+    ///
+    /// # Errors
+    ///
+    /// This errors when writing fails.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for tok in self.tokens() {
+            tok.write_to(writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the source of the tree
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("Writing into a Vec doesn't fail");
+        out
+    }
+
+    /// Find the path of the first item with the given name, searching depth first
+    pub fn find_item(&self, name: &[u8]) -> Option<Vec<usize>> {
+        let mut path = Vec::new();
+        find_item_in(&self.children, name, &mut path).then_some(path)
+    }
+
+    /// Get the item at the given path
+    pub fn item(&self, path: &[usize]) -> Option<&ItemNode> {
+        let (&last, parents) = path.split_last()?;
+        let mut children = self.children.as_slice();
+        for &i in parents {
+            children = match *children.get(i)? {
+                Node::Item(ref item) => item.children()?,
+                _ => return None,
+            };
+        }
+
+        match *children.get(last)? {
+            Node::Item(ref item) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// Get the item at the given path mutably, e.g. to [`ItemNode::rename`] it
+    pub fn item_mut(&mut self, path: &[usize]) -> Option<&mut ItemNode> {
+        let (&last, parents) = path.split_last()?;
+        match *self.list_mut(parents)?.children.get_mut(last)? {
+            Node::Item(ref mut item) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// Rename every item called `from` to `to` and return how many were renamed
+    ///
+    /// # Errors
+    ///
+    /// This errors when `to` is not a valid item name.
+    pub fn rename_all(&mut self, from: &[u8], to: &[u8]) -> AmarkResult<'static, usize> {
+        validate_item_name(to)?;
+        rename_all_in(&mut self.children, from, to)
+    }
+
+    /// Parse `source` as content of the top level, block or container the path points into and
+    /// insert it at the last index of the path. The inserted source gets its own lines, indented
+    /// like its neighbours.
+    ///
+    /// # Errors
+    ///
+    /// This errors when the path doesn't point into the top level, a block or a container or
+    /// the source can't be parsed in that context.
+    pub fn insert(&mut self, path: &[usize], source: impl AsRef<[u8]>) -> AmarkResult<'static, ()> {
+        let (&index, parents) = path.split_last().ok_or_else(|| invalid_path(path))?;
+        let list = self
+            .list_mut(parents)
+            .filter(|list| index <= list.children.len())
+            .ok_or_else(|| invalid_path(path))?;
+        let mut nodes = parse_fragment(source.as_ref(), list.kind)?;
+        let Some(first) = nodes.first_mut() else {
+            return Ok(());
+        };
+
+        // Indent like the next sibling, the previous one or one level deeper than the end
+        let indent = match list
+            .children
+            .get(index)
+            .or_else(|| list.children.get(index.wrapping_sub(1)))
+        {
+            Some(sibling) => line_indent(&sibling.first_token().leading).to_vec(),
+            None if list.kind == ListKind::TopLevel => Vec::new(),
+            None => {
+                let mut indent = line_indent(&list.close.leading).to_vec();
+                let unit: &[u8] = if indent.contains(&b'\t') {
+                    b"\t"
+                } else {
+                    b"    "
+                };
+                indent.extend_from_slice(unit);
+                indent
+            }
+        };
+
+        // Start on a new line unless the content follows a bare escape, a line break after it
+        // would be an empty line
+        let prev = match index.checked_sub(1) {
+            Some(i) => list.children.get_mut(i).map(|prev| {
+                let bare = prev.is_bare_escape();
+                (prev.last_token_mut().ends_line(), bare)
+            }),
+            None => list.open.as_deref().map(|open| (open.ends_line(), false)),
+        };
+        let mut leading = Vec::new();
+        if let Some((false, false)) = prev {
+            leading.push(b'\n');
+        }
+        leading.extend_from_slice(&indent);
+        first.first_token_mut().leading = leading;
+
+        if let Some(last) = nodes.last_mut() {
+            let bare = last.is_bare_escape();
+            let tok = last.last_token_mut();
+            if !tok.ends_line() && !bare {
+                tok.trailing.push(b'\n');
+            }
+        }
+
+        list.children.splice(index..index, nodes);
+        Ok(())
+    }
+
+    /// Delete the node at the given path together with its trivia and return it
+    pub fn delete(&mut self, path: &[usize]) -> Option<Node> {
+        let (&index, parents) = path.split_last()?;
+        let list = self.list_mut(parents)?;
+        if index >= list.children.len() {
+            return None;
+        }
+
+        let mut node = list.children.remove(index);
+        // The indentation of the first node is kept for the one moving up
+        if index == 0 {
+            let next = match list.children.first_mut() {
+                Some(next) => next.first_token_mut(),
+                None => &mut *list.close,
+            };
+            mem::swap(&mut next.leading, &mut node.first_token_mut().leading);
+        }
+
+        Some(node)
+    }
+
+    /// Get the list of nodes the given path of items points into
+    fn list_mut(&mut self, parents: &[usize]) -> Option<List<'_>> {
+        let mut list = List {
+            kind: ListKind::TopLevel,
+            open: None,
+            children: &mut self.children,
+            close: &mut self.end,
+        };
+
+        for &i in parents {
+            let (kind, group) = match *list.children.get_mut(i)? {
+                Node::Item(ItemNode {
+                    body: Body::Block(ref mut group),
+                    ..
+                }) => (ListKind::Block, group),
+                Node::Item(ItemNode {
+                    body: Body::Container(ref mut group),
+                    ..
+                }) => (ListKind::Container, group),
+                _ => return None,
+            };
+            list = List {
+                kind,
+                open: Some(&mut group.open),
+                children: &mut group.children,
+                close: &mut group.close,
+            };
+        }
+
+        Some(list)
+    }
+}
+
+/// A list of nodes which can be edited
+struct List<'a> {
+    /// Where the list is
+    kind: ListKind,
+    /// The token opening the list, `None` at the top level
+    open: Option<&'a mut CstToken>,
+    /// The nodes
+    children: &'a mut Vec<Node>,
+    /// The token closing the list
+    close: &'a mut CstToken,
+}
+
+/// Where a list of nodes is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListKind {
+    /// At the top level
+    TopLevel,
+    /// Inside of a block
+    Block,
+    /// Inside of a container
+    Container,
+}
+
+/// The tokens of a document with their trivia
+type Tokens = Peekable<vec::IntoIter<CstToken>>;
+
+/// Parse the source into a list of tokens which together contain every byte of the source
+fn lex(source: &[u8]) -> AmarkResult<'static, Vec<CstToken>> {
+    let mut reader = AmarkReader::new();
+    let mut input = source;
+    let mut tokens: Vec<CstToken> = Vec::new();
+    // The end of the previous token
+    let mut pos = 0;
+
+    loop {
+        let token = reader
+            .parse_next(&mut input)
+            .map_err(AmarkError::to_owned)?
+            .to_owned_token();
+        // The reader is behind the token and the whitespace it skipped after it
+        let end = reader.offset();
+        let segment = source.get(pos..end).unwrap_or_default();
+        let skipped = segment
+            .iter()
+            .rev()
+            .take_while(|b| b.is_ascii_whitespace())
+            .count();
+
+        let (skipped, len) = match token {
+            OwnedToken::Text(ref text) => (usize::from(segment.last() == Some(&b'\n')), text.len()),
+            OwnedToken::EmptyLine | OwnedToken::ContainerEnd => (0, 1),
+            OwnedToken::EscapeSequence(_) => (0, 2),
+            OwnedToken::End => (0, 0),
+            OwnedToken::ItemName(ref name) => {
+                let at = segment
+                    .len()
+                    .checked_sub(skipped + name.len() + 1)
+                    .and_then(|i| segment.get(i));
+                (skipped, name.len() + usize::from(at == Some(&b'@')))
+            }
+            _ => (skipped, 1),
+        };
+        let raw_end = end.saturating_sub(skipped);
+        let raw_start = raw_end.saturating_sub(len).max(pos);
+
+        let mut leading = source.get(pos..raw_start).unwrap_or_default();
+        if let Some(prev) = tokens.last_mut() {
+            let line_end = leading
+                .iter()
+                .position(|&b| !b.is_ascii_whitespace() || b == b'\n')
+                .filter(|&i| leading.get(i) == Some(&b'\n'));
+            if let Some(i) = line_end {
+                prev.trailing = leading.get(..=i).unwrap_or_default().to_vec();
+                leading = leading.get(i + 1..).unwrap_or_default();
+            }
+        }
+
+        let is_end = token == OwnedToken::End;
+        tokens.push(CstToken {
+            leading: leading.to_vec(),
+            raw: source.get(raw_start..raw_end).unwrap_or_default().to_vec(),
+            trailing: Vec::new(),
+            token,
+        });
+        pos = raw_end;
+
+        if is_end {
+            return Ok(tokens);
+        }
+    }
+}
+
+/// Build nodes until the token that closes the current context and return both
+fn build_nodes(tokens: &mut Tokens) -> (Vec<Node>, CstToken) {
+    let mut nodes = Vec::new();
+
+    while let Some(tok) = tokens.next() {
+        match tok.token {
+            OwnedToken::ItemName(_) => {
+                let params = iter::from_fn(|| build_params(tokens)).collect();
+                let body = match tokens.next() {
+                    Some(open) if open.token == OwnedToken::BlockStart => {
+                        Body::Block(build_group(open, tokens))
+                    }
+                    Some(open) if open.token == OwnedToken::ContainerStart => {
+                        Body::Container(build_group(open, tokens))
+                    }
+                    Some(end) => Body::End(end),
+                    None => unreachable!("The reader always ends items"),
+                };
+                nodes.push(Node::Item(ItemNode {
+                    name: tok,
+                    params,
+                    body,
+                }));
+            }
+            OwnedToken::EscapeSequence(_) => {
+                let params = build_params(tokens);
+                nodes.push(Node::Escape { seq: tok, params });
+            }
+            OwnedToken::Text(_) | OwnedToken::EmptyLine => nodes.push(Node::Token(tok)),
+            _ => return (nodes, tok),
+        }
+    }
+
+    unreachable!("The reader always produces an end token")
+}
+
+/// Build a parameter list if one follows
+fn build_params(tokens: &mut Tokens) -> Option<Group> {
+    tokens
+        .next_if(|tok| tok.token == OwnedToken::ParamsStart)
+        .map(|open| build_group(open, tokens))
+}
+
+/// Build the group opened by the given token
+fn build_group(open: CstToken, tokens: &mut Tokens) -> Group {
+    let (children, close) = build_nodes(tokens);
+    Group {
+        open,
+        children,
+        close,
+    }
+}
+
+/// Parse a fragment of source as content of the given kind of list
+fn parse_fragment(source: &[u8], kind: ListKind) -> AmarkResult<'static, Vec<Node>> {
+    if kind != ListKind::Block {
+        return Ok(Cst::parse(source)?.children);
+    }
+
+    let mut wrapped = Vec::with_capacity(source.len().saturating_add(3));
+    wrapped.extend_from_slice(b"_{");
+    wrapped.extend_from_slice(source);
+    wrapped.push(b'}');
+
+    let mut children = Cst::parse(&wrapped)?.children.into_iter();
+    match (children.next(), children.next()) {
+        (
+            Some(Node::Item(ItemNode {
+                body: Body::Block(group),
+                ..
+            })),
+            None,
+        ) => Ok(group.children),
+        _ => Err(AmarkError::UnexpectedInput {
+            expected: b"Content of a block".as_ref().into(),
+            got: source.to_vec().into(),
+        }),
+    }
+}
+
+/// Search the first item with the given name depth first and record its path
+fn find_item_in(children: &[Node], name: &[u8], path: &mut Vec<usize>) -> bool {
+    for (i, child) in children.iter().enumerate() {
+        if let Node::Item(ref item) = *child {
+            path.push(i);
+            if item.name() == name || item.children().is_some_and(|c| find_item_in(c, name, path)) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+
+    false
+}
+
+/// Rename all items called `from` in the given nodes and their children
+fn rename_all_in(children: &mut [Node], from: &[u8], to: &[u8]) -> AmarkResult<'static, usize> {
+    let mut renamed = 0;
+    for child in children {
+        if let Node::Item(ref mut item) = *child {
+            if item.name() == from {
+                item.rename(to)?;
+                renamed += 1;
+            }
+            if let Body::Block(ref mut group) | Body::Container(ref mut group) = item.body {
+                renamed += rename_all_in(&mut group.children, from, to)?;
+            }
+        }
+    }
+
+    Ok(renamed)
+}
+
+/// The indentation at the end of the given leading trivia
+fn line_indent(leading: &[u8]) -> &[u8] {
+    let start = leading
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    leading.get(start..).unwrap_or_default()
+}
+
+/// Create an error for a path that doesn't point into a list of nodes
+fn invalid_path(path: &[usize]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: b"Path into the top level, a block or a container"
+            .as_ref()
+            .into(),
+        got: format!("{:?}", path).into_bytes().into(),
+    }
+}
@@ -0,0 +1,647 @@
+//! Deserialize Rust data structures from Amark with serde.
+//!
+//! The document is read into a [`Document`] first and then mapped onto the requested type:
+//!
+//! - The top level and containers are structs or maps keyed by item name, or sequences of their
+//!   items.
+//! - Parameters are tuples, sequences or structs by position, a single parameter is a plain value
+//!   like in `price(5);`.
+//! - Blocks are strings, or structs and maps keyed by the names of their inline items.
+//! - Items ended by `;` without parameters are units or `true`, an item that is present is `Some`.
+//! - Enum variants are named by items, `color [ Red; ]` or `color(Red);` as a value and
+//!   `Circle(5);` directly inside of a sequence.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Card {
+//!     title: String,
+//!     price: u32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let card: Card = amark::from_slice(b"title { Green box }\nprice(5);\ntags(new, sale);")?;
+//! assert_eq!(card.title, "Green box");
+//! assert_eq!(card.price, 5);
+//! assert_eq!(card.tags, ["new", "sale"]);
+//! # Ok::<(), amark::SerdeError>(())
+//! ```
+
+use std::{
+    any,
+    io::{BufReader, Read},
+    slice,
+    str::{self, FromStr},
+};
+
+use serde::{
+    de::{
+        DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+
+use crate::{
+    event::ItemKind,
+    tree::{Document, Element, Node},
+    AmarkStream, SerdeError, TokenStream,
+};
+
+/// Deserialize a value from the Amark document read from `reader`
+///
+/// # Errors
+///
+/// This errors when the input can't be read or parsed or doesn't fit the type.
+pub fn from_reader<T: DeserializeOwned>(reader: impl Read) -> Result<T, SerdeError> {
+    let mut stream = AmarkStream::new(BufReader::new(reader));
+    let document = Document::read(&mut stream).map_err(|error| SerdeError::Amark {
+        error,
+        line: stream.cur_line(),
+    })?;
+    from_document(&document)
+}
+
+/// Deserialize a value from the given Amark source
+///
+/// # Errors
+///
+/// This errors when the source can't be parsed or doesn't fit the type.
+pub fn from_slice<T: DeserializeOwned>(source: &[u8]) -> Result<T, SerdeError> {
+    from_reader(source)
+}
+
+/// Deserialize a value from an already read document
+///
+/// # Errors
+///
+/// This errors when the document doesn't fit the type.
+pub fn from_document<T: DeserializeOwned>(document: &Document) -> Result<T, SerdeError> {
+    T::deserialize(ListDeserializer {
+        nodes: &document.children,
+    })
+}
+
+/// Deserializes the items of the top level, a container or a block
+struct ListDeserializer<'a> {
+    /// The nodes of the list, everything but items is skipped
+    nodes: &'a [Node],
+}
+
+impl<'de> Deserializer<'de> for ListDeserializer<'_> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_map(ElementMap {
+            nodes: self.nodes.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(ElementSeq {
+            nodes: self.nodes.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let mut elements = self.nodes.iter().filter_map(element);
+        match (elements.next(), elements.next()) {
+            (Some(element), None) => visitor.visit_enum(VariantDeserializer { element }),
+            _ => Err(serde::de::Error::custom(
+                "expected exactly one item naming the enum variant",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        map struct identifier
+    }
+}
+
+/// Deserializes the value of an item from its parameters and content
+struct ItemDeserializer<'a> {
+    /// The item
+    element: &'a Element,
+    /// Whether the name of the item names the variant of an enum, true inside of sequences
+    named: bool,
+}
+
+impl<'a> ItemDeserializer<'a> {
+    /// The parameters of the item if it has any
+    fn params(&self) -> Option<&'a [Vec<u8>]> {
+        self.element.item.params.as_deref()
+    }
+
+    /// The items in the content of the item
+    fn list(&self) -> ListDeserializer<'a> {
+        ListDeserializer {
+            nodes: &self.element.children,
+        }
+    }
+
+    /// Whether the content of the item is a list of items instead of text
+    fn has_list(&self) -> bool {
+        match self.element.item.kind {
+            ItemKind::Container => true,
+            ItemKind::Block => self.element.elements().next().is_some(),
+            ItemKind::Bare => false,
+        }
+    }
+
+    /// The single value of the item, its only parameter or the text of its block
+    fn scalar(&self) -> Result<ScalarDeserializer, SerdeError> {
+        let text = match (self.params(), self.element.item.kind) {
+            (Some([param]), ItemKind::Bare) => param.clone(),
+            (None, ItemKind::Block) => self.element.text(),
+            (None, ItemKind::Bare) => Vec::new(),
+            _ => {
+                return Err(SerdeError::data(
+                    "expected a single parameter or a block of text",
+                    self.element.line,
+                ))
+            }
+        };
+
+        Ok(ScalarDeserializer {
+            text,
+            line: self.element.line,
+        })
+    }
+
+    /// A sequence over the parameters of the item
+    fn param_seq(&self) -> ParamSeq<'a> {
+        ParamSeq {
+            params: self.params().unwrap_or_default().iter(),
+            line: self.element.line,
+        }
+    }
+}
+
+/// Implement deserializer methods by deserializing the single value of an item
+macro_rules! deserialize_scalar {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                self.scalar()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ItemDeserializer<'_> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.has_list() {
+            return self.list().deserialize_any(visitor);
+        }
+
+        match (self.params(), self.element.item.kind) {
+            (None, ItemKind::Bare) => visitor.visit_unit(),
+            (Some(params), ItemKind::Bare) if params.len() != 1 => {
+                visitor.visit_seq(self.param_seq())
+            }
+            _ => self.scalar()?.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        // A flag like `draft;` is true
+        if self.params().is_none() && self.element.item.kind == ItemKind::Bare {
+            return visitor.visit_bool(true);
+        }
+        self.scalar()?.deserialize_bool(visitor)
+    }
+
+    deserialize_scalar! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.element.has_body() {
+            self.list().deserialize_seq(visitor)
+        } else {
+            visitor.visit_seq(self.param_seq())
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.list().deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        // The fields of a struct can also be given by position, `point(1, 2);`
+        if !self.element.has_body() && self.params().is_some() {
+            visitor.visit_seq(self.param_seq())
+        } else {
+            self.list().deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        if self.named {
+            visitor.visit_enum(VariantDeserializer {
+                element: self.element,
+            })
+        } else if self.has_list() {
+            self.list().deserialize_enum(name, variants, visitor)
+        } else {
+            self.scalar()?.deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+}
+
+/// Deserializes a single value given as text
+struct ScalarDeserializer {
+    /// The text of the value
+    text: Vec<u8>,
+    /// The line of the item the value belongs to
+    line: usize,
+}
+
+impl ScalarDeserializer {
+    /// The value as a string
+    fn into_string(self) -> Result<String, SerdeError> {
+        let line = self.line;
+        String::from_utf8(self.text).map_err(|e| SerdeError::data(e, line))
+    }
+
+    /// Parse the value as the given type
+    fn parse<T: FromStr>(&self) -> Result<T, SerdeError> {
+        str::from_utf8(&self.text)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or_else(|| {
+                SerdeError::data(
+                    format_args!(
+                        "invalid value {:?}, expected {}",
+                        String::from_utf8_lossy(&self.text),
+                        any::type_name::<T>()
+                    ),
+                    self.line,
+                )
+            })
+    }
+}
+
+/// Implement deserializer methods by parsing the text of a scalar
+macro_rules! parse_scalar {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ScalarDeserializer {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_string(self.into_string()?)
+    }
+
+    parse_scalar! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let line = self.line;
+        let s = self.into_string()?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(SerdeError::data(
+                format_args!("invalid value {:?}, expected a single character", s),
+                line,
+            )),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_byte_buf(self.text)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_byte_buf(self.text)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let line = self.line;
+        visitor
+            .visit_enum(self.into_string()?.into_deserializer())
+            .map_err(|e: SerdeError| e.at_line(line))
+    }
+
+    forward_to_deserialize_any! {
+        str string unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Gives the name of an item as the variant of an enum and its value as the content
+struct VariantDeserializer<'a> {
+    /// The item
+    element: &'a Element,
+}
+
+impl<'a, 'de> EnumAccess<'de> for VariantDeserializer<'a> {
+    type Error = SerdeError;
+    type Variant = ItemDeserializer<'a>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), SerdeError> {
+        let variant = seed.deserialize(ScalarDeserializer {
+            text: self.element.name().to_vec(),
+            line: self.element.line,
+        })?;
+        Ok((
+            variant,
+            ItemDeserializer {
+                element: self.element,
+                named: false,
+            },
+        ))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ItemDeserializer<'_> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, SerdeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_struct("", fields, visitor)
+    }
+}
+
+/// Gives the items of a list as entries keyed by their names
+struct ElementMap<'a> {
+    /// The remaining nodes of the list
+    nodes: slice::Iter<'a, Node>,
+    /// The item whose name was given last
+    value: Option<&'a Element>,
+}
+
+impl<'de> MapAccess<'de> for ElementMap<'_> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let Some(element) = self.nodes.by_ref().find_map(element) else {
+            return Ok(None);
+        };
+
+        self.value = Some(element);
+        seed.deserialize(ScalarDeserializer {
+            text: element.name().to_vec(),
+            line: element.line,
+        })
+        .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let element = self
+            .value
+            .take()
+            .ok_or_else(|| serde::de::Error::custom("value requested before its key"))?;
+        seed.deserialize(ItemDeserializer {
+            element,
+            named: false,
+        })
+        .map_err(|e| e.at_line(element.line))
+    }
+}
+
+/// Gives the items of a list as elements of a sequence
+struct ElementSeq<'a> {
+    /// The remaining nodes of the list
+    nodes: slice::Iter<'a, Node>,
+}
+
+impl<'de> SeqAccess<'de> for ElementSeq<'_> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        let Some(element) = self.nodes.by_ref().find_map(element) else {
+            return Ok(None);
+        };
+
+        seed.deserialize(ItemDeserializer {
+            element,
+            named: true,
+        })
+        .map(Some)
+        .map_err(|e| e.at_line(element.line))
+    }
+}
+
+/// Gives the parameters of an item as elements of a sequence
+struct ParamSeq<'a> {
+    /// The remaining parameters
+    params: slice::Iter<'a, Vec<u8>>,
+    /// The line of the item
+    line: usize,
+}
+
+impl<'de> SeqAccess<'de> for ParamSeq<'_> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        self.params
+            .next()
+            .map(|param| {
+                seed.deserialize(ScalarDeserializer {
+                    text: param.clone(),
+                    line: self.line,
+                })
+            })
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.params.len())
+    }
+}
+
+/// The element of a node if it is one
+fn element(node: &Node) -> Option<&Element> {
+    match *node {
+        Node::Element(ref element) => Some(element),
+        _ => None,
+    }
+}
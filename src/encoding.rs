@@ -0,0 +1,218 @@
+//! Detection of byte order marks and transcoding of UTF-16 input into UTF-8.
+//!
+//! The [`AmarkReader`](crate::AmarkReader) itself only understands UTF-8 and strips a UTF-8 byte
+//! order mark on its own. Input that may be UTF-16 encoded has to be wrapped in a
+//! [`DecodingReader`] which transcodes it on the fly.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, Read},
+};
+
+/// The UTF-8 byte order mark
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// The UTF-16 little endian byte order mark
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+/// The UTF-16 big endian byte order mark
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// The encoding of an input as indicated by its byte order mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, this is assumed when no byte order mark is present
+    Utf8,
+    /// UTF-16 little endian
+    Utf16Le,
+    /// UTF-16 big endian
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Detect the encoding of the given start of an input. Returns the encoding and the length
+    /// of the byte order mark which should be skipped.
+    pub fn detect(start: &[u8]) -> (Self, usize) {
+        if start.starts_with(UTF8_BOM) {
+            (Self::Utf8, UTF8_BOM.len())
+        } else if start.starts_with(UTF16_LE_BOM) {
+            (Self::Utf16Le, UTF16_LE_BOM.len())
+        } else if start.starts_with(UTF16_BE_BOM) {
+            (Self::Utf16Be, UTF16_BE_BOM.len())
+        } else {
+            (Self::Utf8, 0)
+        }
+    }
+
+    /// Combine two bytes to a UTF-16 code unit in the byte order of this encoding
+    fn code_unit(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::Utf16Le => u16::from_le_bytes(bytes),
+            Self::Utf8 | Self::Utf16Be => u16::from_be_bytes(bytes),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf16Le => write!(f, "UTF-16LE"),
+            Self::Utf16Be => write!(f, "UTF-16BE"),
+        }
+    }
+}
+
+/// A [`BufRead`] adapter which detects the encoding of the wrapped reader by its byte order mark
+/// and yields the content as UTF-8 without a byte order mark.
+///
+/// UTF-8 input is passed through unchanged, UTF-16 input is transcoded. Unpaired surrogates are
+/// replaced by `U+FFFD`.
+#[derive(Debug)]
+pub struct DecodingReader<R> {
+    /// The wrapped reader
+    inner: R,
+    /// The detected encoding, `None` until the first read
+    encoding: Option<Encoding>,
+    /// Transcoded UTF-8 output
+    out: Vec<u8>,
+    /// How many bytes of `out` have been consumed
+    consumed: usize,
+    /// A byte of an incomplete code unit left over from the last read
+    carry: Option<u8>,
+    /// A high surrogate waiting for its low surrogate
+    high_surrogate: Option<u16>,
+}
+
+impl<R: BufRead> DecodingReader<R> {
+    /// Wrap the given reader, the encoding is detected on the first read
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            encoding: None,
+            out: Vec::new(),
+            consumed: 0,
+            carry: None,
+            high_surrogate: None,
+        }
+    }
+
+    /// The detected encoding of the input
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when reading the start of the input fails.
+    pub fn encoding(&mut self) -> io::Result<Encoding> {
+        if let Some(encoding) = self.encoding {
+            return Ok(encoding);
+        }
+
+        // Make sure a split byte order mark is still detected
+        let mut start = Vec::with_capacity(UTF8_BOM.len());
+        while start.len() < UTF8_BOM.len() {
+            let available = self.inner.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            let n = available.len().min(UTF8_BOM.len() - start.len());
+            start.extend_from_slice(&available[..n]);
+            self.inner.consume(n);
+        }
+
+        let (encoding, bom_len) = Encoding::detect(&start);
+        let rest = &start[bom_len..];
+        match encoding {
+            Encoding::Utf8 => self.out.extend_from_slice(rest),
+            Encoding::Utf16Le | Encoding::Utf16Be => self.transcode(encoding, rest, false),
+        }
+        self.encoding = Some(encoding);
+
+        Ok(encoding)
+    }
+
+    /// Unwrap the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Transcode UTF-16 bytes of the given encoding into the output buffer
+    fn transcode(&mut self, encoding: Encoding, mut bytes: &[u8], eof: bool) {
+        let mut units = Vec::with_capacity(bytes.len() / 2 + 2);
+        units.extend(self.high_surrogate.take());
+
+        if let (Some(first), Some((&second, rest))) = (self.carry, bytes.split_first()) {
+            self.carry = None;
+            units.push(encoding.code_unit([first, second]));
+            bytes = rest;
+        }
+
+        let mut chunks = bytes.chunks_exact(2);
+        units.extend(chunks.by_ref().map(|c| encoding.code_unit([c[0], c[1]])));
+        if let [b] = *chunks.remainder() {
+            self.carry = Some(b);
+        }
+
+        // A high surrogate at the end may be completed by the next read
+        if !eof && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+            self.high_surrogate = units.pop();
+        }
+
+        let mut utf8 = [0; 4];
+        for c in char::decode_utf16(units) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.out
+                .extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        }
+    }
+}
+
+impl<R: BufRead> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let encoding = self.encoding()?;
+
+        if self.consumed >= self.out.len() {
+            self.out.clear();
+            self.consumed = 0;
+
+            match encoding {
+                // Pass through without copying
+                Encoding::Utf8 => return self.inner.fill_buf(),
+                Encoding::Utf16Le | Encoding::Utf16Be => {
+                    // Loop as a single byte or surrogate might not produce any output
+                    while self.out.is_empty() {
+                        let input = self.inner.fill_buf()?;
+                        let eof = input.is_empty();
+                        if eof && self.carry.is_none() && self.high_surrogate.is_none() {
+                            break;
+                        }
+                        let input = input.to_vec();
+                        self.inner.consume(input.len());
+                        self.transcode(encoding, &input, eof);
+                        if eof && self.carry.take().is_some() {
+                            // A dangling byte can't form a code unit
+                            self.out.extend_from_slice("\u{FFFD}".as_bytes());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(&self.out[self.consumed..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.consumed < self.out.len() {
+            self.consumed = self.consumed.saturating_add(amt).min(self.out.len());
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::{
     fmt::{self, Debug, Display, Formatter},
     io,
     ops::Deref,
+    path::PathBuf,
     str,
 };
 
@@ -30,6 +31,40 @@ pub enum AmarkError<'buf> {
         /// Description of what was expected before the end of the file
         expected: Cow<'buf, [u8]>,
     },
+    /// A macro couldn't be defined or expanded
+    Macro {
+        /// The name of the macro
+        name: Vec<u8>,
+        /// What went wrong
+        msg: Cow<'static, str>,
+        /// The line the macro is defined on
+        defined: usize,
+        /// The line the macro is used on, `None` for errors in the definition
+        used: Option<usize>,
+    },
+    /// An error in an included file or while including a file
+    Include {
+        /// The files with the line they are at, the file with the error first and the document
+        /// including everything last
+        chain: Vec<(PathBuf, usize)>,
+        /// What went wrong
+        error: Box<AmarkError<'static>>,
+    },
+    /// A variable was used without being set
+    UndefinedVariable {
+        /// The name of the variable
+        name: Vec<u8>,
+    },
+    /// A flag was used in a condition without being set
+    UnknownFlag {
+        /// The name of the flag
+        name: Vec<u8>,
+    },
+    /// Expanding the document would create more tokens than allowed
+    ExpansionLimit {
+        /// The maximum number of tokens expansions may create
+        limit: usize,
+    },
 }
 
 impl<'buf> AmarkError<'buf> {
@@ -47,6 +82,21 @@ impl<'buf> AmarkError<'buf> {
             Self::UnexpectedEol { expected } => AmarkError::UnexpectedEol {
                 expected: expected.into_owned().into(),
             },
+            Self::Macro {
+                name,
+                msg,
+                defined,
+                used,
+            } => AmarkError::Macro {
+                name,
+                msg,
+                defined,
+                used,
+            },
+            Self::Include { chain, error } => AmarkError::Include { chain, error },
+            Self::UndefinedVariable { name } => AmarkError::UndefinedVariable { name },
+            Self::UnknownFlag { name } => AmarkError::UnknownFlag { name },
+            Self::ExpansionLimit { limit } => AmarkError::ExpansionLimit { limit },
         }
     }
 }
@@ -86,6 +136,148 @@ impl<'buf> Display for AmarkError<'buf> {
                     ByteDisp(expected)
                 )
             }
+            Self::Macro {
+                ref name,
+                ref msg,
+                defined,
+                used,
+            } => {
+                write!(f, "Macro {} defined on line {}", ByteDisp(name), defined)?;
+                if let Some(used) = used {
+                    write!(f, " and used on line {}", used)?;
+                }
+                write!(f, ": {}", msg)
+            }
+            Self::Include {
+                ref chain,
+                ref error,
+            } => {
+                write!(f, "{}", error)?;
+                for (i, &(ref path, line)) in chain.iter().enumerate() {
+                    let context = if i == 0 { "in" } else { "included from" };
+                    write!(f, "\n{} {}:{}", context, path.display(), line)?;
+                }
+                Ok(())
+            }
+            Self::UndefinedVariable { ref name } => {
+                write!(f, "Undefined variable {}", ByteDisp(name))
+            }
+            Self::UnknownFlag { ref name } => write!(f, "Unknown flag {}", ByteDisp(name)),
+            Self::ExpansionLimit { limit } => write!(
+                f,
+                "Expanding the document creates more than {} tokens",
+                limit
+            ),
+        }
+    }
+}
+
+impl<'buf> std::error::Error for AmarkError<'buf> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Self::IoError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An error while converting between Amark and Rust data structures with serde
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum SerdeError {
+    /// The input couldn't be read or parsed
+    Amark {
+        /// The error of the reader
+        error: AmarkError<'static>,
+        /// The line the reader stopped at
+        line: usize,
+    },
+    /// The data doesn't fit the requested type
+    Data {
+        /// What went wrong
+        msg: String,
+        /// The line of the item the error belongs to, if known
+        line: Option<usize>,
+    },
+    /// The output couldn't be written
+    Write(AmarkError<'static>),
+}
+
+#[cfg(feature = "serde")]
+impl SerdeError {
+    /// The line the error belongs to, if known
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            Self::Amark { line, .. } => Some(line),
+            Self::Data { line, .. } => line,
+            Self::Write(_) => None,
+        }
+    }
+
+    /// Create an error for data on the given line
+    pub(crate) fn data(msg: impl Display, line: usize) -> Self {
+        Self::Data {
+            msg: msg.to_string(),
+            line: Some(line),
+        }
+    }
+
+    /// Attach the given line to the error unless it already has one
+    pub(crate) fn at_line(self, line: usize) -> Self {
+        match self {
+            Self::Data { msg, line: None } => Self::Data {
+                msg,
+                line: Some(line),
+            },
+            e => e,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Display for SerdeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Amark { ref error, line } => write!(f, "line {}: {}", line, error),
+            Self::Data {
+                ref msg,
+                line: Some(line),
+            } => write!(f, "line {}: {}", line, msg),
+            Self::Data {
+                ref msg,
+                line: None,
+            } => write!(f, "{}", msg),
+            Self::Write(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for SerdeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Self::Amark { ref error, .. } | Self::Write(ref error) => Some(error),
+            Self::Data { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Data {
+            msg: msg.to_string(),
+            line: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Data {
+            msg: msg.to_string(),
+            line: None,
         }
     }
 }
@@ -0,0 +1,349 @@
+//! A layer above the raw tokens which groups item names with their parameters and form and pairs
+//! every item start with its end. Most renderers are easier to write on top of these events than
+//! on top of the tokens directly.
+
+use std::{borrow::Cow, iter};
+
+use crate::{
+    stream::{Replay, TokenStream},
+    AmarkError, AmarkResult, AmarkToken, OwnedToken,
+};
+
+/// A semantic event of an Amark document
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// Start of a block or container item, the matching [`Event::End`] follows later
+    Start(&'a Item),
+    /// End of a block or container item
+    End(Item),
+    /// An item without content like `l;` or `br(3);`
+    Empty(Item),
+    /// A line of text
+    Text(&'a [u8]),
+    /// An escape sequence with its optional parameters, like `\n` or `\s(4)`
+    Escape(Escape),
+    /// An empty line
+    EmptyLine,
+}
+
+/// An item with its name, parameters and form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// The name of the item
+    pub name: Vec<u8>,
+    /// The parameters split at `,` or `None` if the item has no parameter list
+    pub params: Option<Vec<Vec<u8>>>,
+    /// The form of the item
+    pub kind: ItemKind,
+}
+
+impl Item {
+    /// All parameters of this item, empty if it has no parameter list
+    pub fn params(&self) -> &[Vec<u8>] {
+        self.params.as_deref().unwrap_or(&[])
+    }
+
+    /// Get the parameter at the given index
+    pub fn param(&self, idx: usize) -> Option<&[u8]> {
+        self.params().get(idx).map(Vec::as_slice)
+    }
+}
+
+/// The form of an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    /// An item without content ended by `;`
+    Bare,
+    /// A block item containing text `{ ... }`
+    Block,
+    /// A container item containing other items `[ ... ]`
+    Container,
+}
+
+/// An escape sequence with its optional parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Escape {
+    /// The escaped character
+    pub seq: u8,
+    /// The parameters, see [`Item::params`]
+    pub params: Option<Vec<Vec<u8>>>,
+}
+
+impl Escape {
+    /// The character this escape sequence stands for, see [`resolve_escape`]
+    pub fn resolved(&self) -> u8 {
+        resolve_escape(self.seq)
+    }
+
+    /// How often the escaped character should be repeated, given by the first parameter
+    pub fn count(&self) -> usize {
+        self.params
+            .as_deref()
+            .and_then(<[_]>::first)
+            .and_then(|p| parse_count(p))
+            .unwrap_or(1)
+    }
+}
+
+/// Resolve the character of an escape sequence. `\n`, `\s` and `\t` stand for a line break, a
+/// space and a tab, every other character stands for itself, e.g. `\}` or `\@`.
+pub fn resolve_escape(seq: u8) -> u8 {
+    match seq {
+        b'n' => b'\n',
+        b's' => b' ',
+        b't' => b'\t',
+        b => b,
+    }
+}
+
+/// Parse a parameter as a count like the `3` in `br(3);`
+pub fn parse_count(param: &[u8]) -> Option<usize> {
+    std::str::from_utf8(param).ok()?.trim().parse().ok()
+}
+
+/// Split the tokens of a parameter list without its delimiters like [`EventReader`] does
+pub(crate) fn parse_params(tokens: Vec<OwnedToken>) -> AmarkResult<'static, Vec<Vec<u8>>> {
+    let mut replayed = vec![OwnedToken::ItemName(b"_".to_vec()), OwnedToken::ParamsStart];
+    replayed.extend(tokens);
+    replayed.extend([OwnedToken::ParamsEnd, OwnedToken::ItemEnd]);
+
+    match EventReader::new(Replay::new(replayed)).next_event()? {
+        Some(Event::Empty(item)) => Ok(item.params.unwrap_or_default()),
+        _ => unreachable!("A bare item was replayed"),
+    }
+}
+
+/// Converts the tokens of a [`TokenStream`] to [`Event`]s
+#[derive(Debug)]
+pub struct EventReader<S> {
+    /// The stream of tokens
+    stream: S,
+    /// Currently open block and container items
+    stack: Vec<Item>,
+    /// The content of the last item name or text token
+    text: Vec<u8>,
+    /// A token which was read ahead after an escape sequence
+    peeked: Option<Kind>,
+    /// The line of the last item name
+    item_line: usize,
+}
+
+impl<S: TokenStream> EventReader<S> {
+    /// Create a new event reader over the given stream
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            stack: Vec::new(),
+            text: Vec::new(),
+            peeked: None,
+            item_line: 0,
+        }
+    }
+
+    /// Get the next event or `None` at the end of the input
+    ///
+    /// # Errors
+    ///
+    /// This errors when the underlying stream errors.
+    pub fn next_event(&mut self) -> AmarkResult<'static, Option<Event<'_>>> {
+        let event = match self.next_kind()? {
+            Kind::ItemName => return self.read_item().map(Some),
+            Kind::Text => Event::Text(&self.text),
+            Kind::EmptyLine => Event::EmptyLine,
+            Kind::EscapeSequence(seq) => {
+                let params = match self.next_kind()? {
+                    Kind::ParamsStart => Some(self.read_params()?),
+                    kind => {
+                        self.peeked = Some(kind);
+                        None
+                    }
+                };
+                Event::Escape(Escape { seq, params })
+            }
+            Kind::BlockEnd | Kind::ContainerEnd => match self.stack.pop() {
+                Some(item) => Event::End(item),
+                None => return Err(unexpected(b"Item or EOF", b"End of item")),
+            },
+            Kind::End => return Ok(None),
+            Kind::BlockStart
+            | Kind::ContainerStart
+            | Kind::ParamsStart
+            | Kind::ParamsEnd
+            | Kind::ItemEnd => return Err(unexpected(b"Item or text", b"Item delimiter")),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// The currently open block and container items, outermost first
+    pub fn open_items(&self) -> &[Item] {
+        &self.stack
+    }
+
+    /// The line of the input the stream is currently at
+    pub fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+
+    /// The line the name of the last read item is on
+    pub fn item_line(&self) -> usize {
+        self.item_line
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Get the kind of the next token, the content of text and item names is put into `text`
+    fn next_kind(&mut self) -> AmarkResult<'static, Kind> {
+        if let Some(kind) = self.peeked.take() {
+            return Ok(kind);
+        }
+
+        Ok(match self.stream.next_token()? {
+            AmarkToken::BlockStart => Kind::BlockStart,
+            AmarkToken::ParamsStart => Kind::ParamsStart,
+            AmarkToken::ContainerStart => Kind::ContainerStart,
+            AmarkToken::BlockEnd => Kind::BlockEnd,
+            AmarkToken::ParamsEnd => Kind::ParamsEnd,
+            AmarkToken::ContainerEnd => Kind::ContainerEnd,
+            AmarkToken::ItemEnd => Kind::ItemEnd,
+            AmarkToken::EmptyLine => Kind::EmptyLine,
+            AmarkToken::End => Kind::End,
+            AmarkToken::EscapeSequence(seq) => Kind::EscapeSequence(seq),
+            AmarkToken::ItemName(text) => {
+                self.text.clear();
+                self.text.extend_from_slice(text);
+                self.item_line = self.stream.cur_line();
+                Kind::ItemName
+            }
+            AmarkToken::Text(text) => {
+                self.text.clear();
+                self.text.extend_from_slice(text);
+                Kind::Text
+            }
+        })
+    }
+
+    /// Read the rest of an item after its name
+    fn read_item(&mut self) -> AmarkResult<'static, Event<'_>> {
+        let name = self.text.clone();
+        let mut params = None;
+
+        loop {
+            let kind = match self.next_kind()? {
+                Kind::ParamsStart if params.is_none() => {
+                    params = Some(self.read_params()?);
+                    continue;
+                }
+                Kind::ItemEnd => ItemKind::Bare,
+                Kind::BlockStart => ItemKind::Block,
+                Kind::ContainerStart => ItemKind::Container,
+                _ => return Err(unexpected(b"Item start or end", b"Other token")),
+            };
+
+            let item = Item { name, params, kind };
+            if kind == ItemKind::Bare {
+                return Ok(Event::Empty(item));
+            }
+
+            self.stack.push(item);
+            return Ok(Event::Start(
+                self.stack
+                    .last()
+                    .unwrap_or_else(|| unreachable!("An item was just pushed")),
+            ));
+        }
+    }
+
+    /// Read a parameter list after its start and split it at unescaped `,`
+    fn read_params(&mut self) -> AmarkResult<'static, Vec<Vec<u8>>> {
+        let mut params = Vec::new();
+        let mut cur: Option<Vec<u8>> = None;
+
+        loop {
+            match self.next_kind()? {
+                Kind::Text => {
+                    let mut parts = self.text.split(|&b| b == b',');
+                    if let Some(first) = parts.next() {
+                        cur.get_or_insert_with(Vec::new).extend_from_slice(first);
+                    }
+                    for part in parts {
+                        params.push(trim(cur.take().unwrap_or_default()));
+                        cur = Some(part.to_vec());
+                    }
+                }
+                Kind::EscapeSequence(seq) => {
+                    let next = self.next_kind()?;
+                    let count = if next == Kind::ParamsStart {
+                        let nested = self.read_params()?;
+                        nested.first().and_then(|p| parse_count(p)).unwrap_or(1)
+                    } else {
+                        self.peeked = Some(next);
+                        1
+                    };
+                    cur.get_or_insert_with(Vec::new)
+                        .extend(iter::repeat_n(resolve_escape(seq), count));
+                }
+                Kind::ParamsEnd => {
+                    params.extend(cur.map(trim));
+                    return Ok(params);
+                }
+                _ => return Err(unexpected(b"End of Parameter List: )", b"Other token")),
+            }
+        }
+    }
+}
+
+/// Remove leading and trailing whitespace of a parameter
+fn trim(mut param: Vec<u8>) -> Vec<u8> {
+    let end = param
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(0, |pos| pos + 1);
+    param.truncate(end);
+    let start = param
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(param.len());
+    param.drain(..start);
+    param
+}
+
+/// Create an error for an unexpected token
+fn unexpected(expected: &'static [u8], got: &'static [u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Borrowed(got),
+    }
+}
+
+/// The kind of a token without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// See [`AmarkToken::BlockStart`]
+    BlockStart,
+    /// See [`AmarkToken::ParamsStart`]
+    ParamsStart,
+    /// See [`AmarkToken::ContainerStart`]
+    ContainerStart,
+    /// See [`AmarkToken::BlockEnd`]
+    BlockEnd,
+    /// See [`AmarkToken::ParamsEnd`]
+    ParamsEnd,
+    /// See [`AmarkToken::ContainerEnd`]
+    ContainerEnd,
+    /// See [`AmarkToken::ItemEnd`]
+    ItemEnd,
+    /// See [`AmarkToken::EmptyLine`]
+    EmptyLine,
+    /// See [`AmarkToken::End`]
+    End,
+    /// See [`AmarkToken::ItemName`]
+    ItemName,
+    /// See [`AmarkToken::Text`]
+    Text,
+    /// See [`AmarkToken::EscapeSequence`]
+    EscapeSequence(u8),
+}
@@ -0,0 +1,306 @@
+//! A formatter which writes tokens back as canonical Amark source.
+//!
+//! Every item at the top level or in a container gets its own line, the content of blocks and
+//! containers is indented by one level per nesting and the spacing around `(`, `{`, `[` and `;` is
+//! normalized: `name(params) {`, `name [` and `name;`. Parsing the output gives exactly the tokens
+//! the formatter was fed with.
+
+use std::io::{self, Write};
+
+use crate::{metadata, stream::TokenStream, AmarkResult, AmarkToken};
+
+/// The indentation used per nesting level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// Indent with the given number of spaces
+    Spaces(usize),
+    /// Indent with one tab
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+/// Writes a stream of tokens as canonical Amark source
+///
+/// Tokens can be fed one by one with [`AmarkFormatter::write_token`] or a whole stream can be
+/// formatted with [`AmarkFormatter::format`].
+#[derive(Debug, Clone)]
+pub struct AmarkFormatter {
+    /// The indentation per nesting level
+    indent: Indent,
+    /// The currently open contexts
+    frames: Vec<Frame>,
+    /// What was written last, decides how the next token is separated
+    last: Last,
+}
+
+impl AmarkFormatter {
+    /// Create a new formatter indenting with 4 spaces
+    pub fn new() -> Self {
+        Self::with_indent(Indent::default())
+    }
+
+    /// Create a new formatter with the given indentation
+    pub fn with_indent(indent: Indent) -> Self {
+        Self {
+            indent,
+            frames: Vec::new(),
+            last: Last::LineStart,
+        }
+    }
+
+    /// Format all tokens of the given stream
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn format<S: TokenStream, W: Write>(
+        &mut self,
+        mut stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        self.reset();
+
+        loop {
+            let tok = stream.next_token()?;
+            self.write_token(&tok, writer)?;
+
+            if tok == AmarkToken::End {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Format the given source into a new buffer, front matter is kept as it is
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed.
+    pub fn format_bytes(&mut self, source: &[u8]) -> AmarkResult<'static, Vec<u8>> {
+        let mut out = Vec::with_capacity(source.len());
+        let mut stream = crate::AmarkStream::new(source);
+        stream.read_metadata()?;
+        out.extend_from_slice(metadata::front_matter(source)?);
+        self.format(stream, &mut out)?;
+        Ok(out)
+    }
+
+    /// Reset the state of the formatter to the start of a document
+    pub fn reset(&mut self) {
+        self.frames.clear();
+        self.last = Last::LineStart;
+    }
+
+    /// Write the next token. The tokens are expected in an order the [`AmarkReader`] could
+    /// produce.
+    ///
+    /// [`AmarkReader`]: crate::AmarkReader
+    ///
+    /// # Errors
+    ///
+    /// This errors when writing fails.
+    pub fn write_token<W: Write>(&mut self, tok: &AmarkToken, writer: &mut W) -> io::Result<()> {
+        if let Some(&Frame::Params { .. }) = self.frames.last() {
+            return self.write_param_token(tok, writer);
+        }
+
+        match *tok {
+            AmarkToken::ItemName(name) => {
+                if self.in_block() {
+                    self.separate(writer, &[Last::Escape])?;
+                    writer.write_all(b"@")?;
+                } else {
+                    self.start_line(writer)?;
+                }
+                writer.write_all(name)?;
+                self.last = Last::Name;
+            }
+            AmarkToken::ParamsStart => {
+                let escape = self.last == Last::Escape;
+                writer.write_all(b"(")?;
+                self.frames.push(Frame::Params { escape });
+                self.last = Last::Open;
+            }
+            AmarkToken::BlockStart | AmarkToken::ContainerStart => {
+                let (open, frame): (&[u8], _) = if *tok == AmarkToken::BlockStart {
+                    (b" {", Frame::Block)
+                } else {
+                    (b" [", Frame::Container)
+                };
+                writer.write_all(open)?;
+                self.frames.push(frame);
+                self.last = Last::Open;
+            }
+            AmarkToken::BlockEnd | AmarkToken::ContainerEnd => {
+                let close: &[u8] = if *tok == AmarkToken::BlockEnd {
+                    b"}"
+                } else {
+                    b"]"
+                };
+                self.frames.pop();
+                match self.last {
+                    // A line break after a bare escape would be an empty line
+                    Last::Open | Last::Escape => (),
+                    _ => self.start_line(writer)?,
+                }
+                writer.write_all(close)?;
+                writer.write_all(b"\n")?;
+                self.last = Last::LineStart;
+            }
+            AmarkToken::ItemEnd => {
+                writer.write_all(b";\n")?;
+                self.last = Last::LineStart;
+            }
+            AmarkToken::Text(text) => {
+                self.separate(writer, &[Last::Escape, Last::EscapeParams])?;
+                writer.write_all(text)?;
+                self.last = Last::Text;
+            }
+            AmarkToken::EscapeSequence(b) => {
+                self.separate(writer, &[Last::Text, Last::Escape, Last::EscapeParams])?;
+                writer.write_all(&[b'\\', b])?;
+                self.last = Last::Escape;
+            }
+            AmarkToken::EmptyLine => {
+                // The line break after a bare escape is the empty line itself
+                if self.last != Last::Escape && self.last != Last::LineStart {
+                    writer.write_all(b"\n")?;
+                }
+                writer.write_all(b"\n")?;
+                self.last = Last::LineStart;
+            }
+            AmarkToken::End => {
+                if self.last != Last::LineStart {
+                    writer.write_all(b"\n")?;
+                }
+                self.reset();
+            }
+            AmarkToken::ParamsEnd => {
+                writer.write_all(b")")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a token inside of a parameter list
+    fn write_param_token<W: Write>(&mut self, tok: &AmarkToken, writer: &mut W) -> io::Result<()> {
+        match *tok {
+            AmarkToken::Text(text) => {
+                // Every text token in a parameter list ends at a line break
+                if self.last == Last::Text {
+                    writer.write_all(b"\n")?;
+                }
+                writer.write_all(text)?;
+                self.last = Last::Text;
+            }
+            AmarkToken::EscapeSequence(b) => {
+                writer.write_all(&[b'\\', b])?;
+                self.last = Last::Escape;
+            }
+            AmarkToken::ParamsStart => {
+                writer.write_all(b"(")?;
+                self.frames.push(Frame::Params { escape: true });
+                self.last = Last::Open;
+            }
+            AmarkToken::ParamsEnd => {
+                writer.write_all(b")")?;
+                self.last = match self.frames.pop() {
+                    Some(Frame::Params { escape: true }) => Last::EscapeParams,
+                    _ => Last::Name,
+                };
+            }
+            // Not possible in a parameter list, end it and format the token as usual
+            _ => {
+                writer.write_all(b")")?;
+                self.frames.pop();
+                self.last = Last::Name;
+                return self.write_token(tok, writer);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the innermost context is a block
+    fn in_block(&self) -> bool {
+        self.frames.last() == Some(&Frame::Block)
+    }
+
+    /// Separate the next token of a block line from what was written before. It continues the
+    /// current line after any of `same_line`, otherwise it starts a new line.
+    fn separate<W: Write>(&mut self, writer: &mut W, same_line: &[Last]) -> io::Result<()> {
+        if same_line.contains(&self.last) {
+            Ok(())
+        } else {
+            self.start_line(writer)
+        }
+    }
+
+    /// End the current line if anything was written to it and indent the next one
+    fn start_line<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.last != Last::LineStart {
+            writer.write_all(b"\n")?;
+            self.last = Last::LineStart;
+        }
+        self.write_indent(writer)
+    }
+
+    /// Write the indentation for the current nesting level
+    fn write_indent<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let depth = self
+            .frames
+            .iter()
+            .filter(|f| matches!(f, Frame::Block | Frame::Container))
+            .count();
+        for _ in 0..depth {
+            match self.indent {
+                Indent::Spaces(n) => write!(writer, "{:n$}", "")?,
+                Indent::Tab => writer.write_all(b"\t")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for AmarkFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A context opened by a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    /// A block item
+    Block,
+    /// A container item
+    Container,
+    /// A parameter list of an item or an escape sequence
+    Params {
+        /// Whether the parameter list belongs to an escape sequence
+        escape: bool,
+    },
+}
+
+/// What was written last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing, the start of a line
+    LineStart,
+    /// An item name
+    Name,
+    /// A `(`, `{` or `[`
+    Open,
+    /// Text
+    Text,
+    /// An escape sequence without parameters
+    Escape,
+    /// The parameter list of an escape sequence
+    EscapeParams,
+}
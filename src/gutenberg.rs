@@ -0,0 +1,741 @@
+//! A renderer for the block markup of the WordPress block editor (Gutenberg). Items become
+//! blocks like `<!-- wp:paragraph {"align":"center"} -->` with their parameters as attributes
+//! or inline HTML inside of the text of a block. Items without a configured element become
+//! `group` blocks with their name and parameters as classes.
+//!
+//! Blocks either hold text or other blocks. Text directly inside of a block which holds other
+//! blocks is wrapped into `paragraph` blocks, an empty line starts a new one. An empty line inside
+//! of a paragraph splits it into two with the same attributes, inside of the text of other blocks
+//! like headings it is a `<br>`. Line breaks from empty lines, `\n` and `br` items are only
+//! written between text, `br` between blocks is left out since blocks are already apart.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    mem,
+};
+
+use crate::{
+    event::{parse_count, Event, EventReader, Item},
+    html::{self, HtmlElement},
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// A block of the block editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GutenbergBlock {
+    /// The block name, `core/` can be left out like the editor does
+    pub name: String,
+    /// The HTML element around the content or `None` for blocks which are rendered by the
+    /// server and are written without content
+    pub element: Option<HtmlElement>,
+    /// Attributes every block of this kind gets. Values which are JSON numbers or booleans are
+    /// written as such, everything else as strings.
+    pub attributes: Vec<(String, String)>,
+    /// The attribute names of the parameters in order, further parameters are left out
+    pub params: Vec<String>,
+    /// Whether the content are inner blocks instead of text
+    pub inner_blocks: bool,
+}
+
+impl GutenbergBlock {
+    /// A block with the given name which writes its text inside of the given element
+    pub fn new(name: impl Into<String>, element: HtmlElement) -> Self {
+        Self {
+            name: name.into(),
+            element: Some(element),
+            attributes: Vec::new(),
+            params: Vec::new(),
+            inner_blocks: false,
+        }
+    }
+
+    /// A block with the given name without any content, e.g. `latest-posts`
+    pub fn dynamic(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            element: None,
+            attributes: Vec::new(),
+            params: Vec::new(),
+            inner_blocks: false,
+        }
+    }
+
+    /// Let this block hold inner blocks instead of text
+    pub fn with_inner_blocks(mut self) -> Self {
+        self.inner_blocks = true;
+        self
+    }
+
+    /// Add an attribute every block of this kind gets
+    pub fn with_attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the attribute names of the parameters
+    pub fn with_params<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.params = names.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// What an item is rendered as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GutenbergElement {
+    /// A block, inside of the text of another block it becomes a `span`
+    Block(GutenbergBlock),
+    /// An HTML element inside of the text of a block, between blocks it starts a paragraph
+    Inline(HtmlElement),
+}
+
+/// Renders Amark documents as block editor markup
+#[derive(Debug, Clone)]
+pub struct GutenbergRenderer {
+    /// Elements configured for specific item names
+    elements: HashMap<Vec<u8>, GutenbergElement>,
+}
+
+impl GutenbergRenderer {
+    /// Create a new renderer with the core blocks `paragraph` for `p`, `heading` for `h1` to
+    /// `h6`, `list` for `ul` and `ol`, `list-item` for `li`, `quote` for `blockquote`,
+    /// `preformatted` for `pre` and `separator` for `hr` and the inline elements `em`, `strong`,
+    /// `code` and `br`. The first parameter of paragraphs and headings is their `align`
+    /// attribute.
+    pub fn new() -> Self {
+        let mut elements = HashMap::from([
+            (
+                b"p".to_vec(),
+                GutenbergBlock::new("paragraph", HtmlElement::new("p")).with_params(["align"]),
+            ),
+            (
+                b"ul".to_vec(),
+                GutenbergBlock::new("list", HtmlElement::new("ul").with_class("wp-block-list"))
+                    .with_inner_blocks(),
+            ),
+            (
+                b"ol".to_vec(),
+                GutenbergBlock::new("list", HtmlElement::new("ol").with_class("wp-block-list"))
+                    .with_inner_blocks()
+                    .with_attribute("ordered", "true"),
+            ),
+            (
+                b"li".to_vec(),
+                GutenbergBlock::new("list-item", HtmlElement::new("li")),
+            ),
+            (
+                b"blockquote".to_vec(),
+                GutenbergBlock::new(
+                    "quote",
+                    HtmlElement::new("blockquote").with_class("wp-block-quote"),
+                )
+                .with_inner_blocks(),
+            ),
+            (
+                b"pre".to_vec(),
+                GutenbergBlock::new(
+                    "preformatted",
+                    HtmlElement::new("pre").with_class("wp-block-preformatted"),
+                ),
+            ),
+            (
+                b"hr".to_vec(),
+                GutenbergBlock::new(
+                    "separator",
+                    HtmlElement::void("hr")
+                        .with_class("wp-block-separator has-alpha-channel-opacity"),
+                ),
+            ),
+        ])
+        .into_iter()
+        .map(|(name, block)| (name, GutenbergElement::Block(block)))
+        .collect::<HashMap<_, _>>();
+
+        for level in 1..=6 {
+            let mut block = GutenbergBlock::new(
+                "heading",
+                HtmlElement::new(format!("h{}", level)).with_class("wp-block-heading"),
+            )
+            .with_params(["align"]);
+            // Level 2 is the default which the editor leaves out
+            if level != 2 {
+                block = block.with_attribute("level", level.to_string());
+            }
+            elements.insert(
+                format!("h{}", level).into_bytes(),
+                GutenbergElement::Block(block),
+            );
+        }
+        for tag in ["em", "strong", "code"] {
+            elements.insert(
+                tag.as_bytes().to_vec(),
+                GutenbergElement::Inline(HtmlElement::new(tag)),
+            );
+        }
+        elements.insert(
+            b"br".to_vec(),
+            GutenbergElement::Inline(HtmlElement::void("br")),
+        );
+
+        Self { elements }
+    }
+
+    /// Render items with the given name as the given element
+    pub fn element(mut self, name: impl Into<Vec<u8>>, element: GutenbergElement) -> Self {
+        self.elements.insert(name.into(), element);
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        let mut output = Output::new(writer);
+
+        while let Some(event) = events.next_event()? {
+            if output.dropping > 0 {
+                match event {
+                    Event::Start(_) => output.dropping += 1,
+                    Event::End(_) => output.dropping -= 1,
+                    _ => (),
+                }
+                continue;
+            }
+
+            match event {
+                Event::Start(item) => {
+                    let element = self.element_for(item, &output);
+                    // Content of void and dynamic elements is left out
+                    if !output.start(item, &element)? {
+                        output.dropping = 1;
+                    }
+                }
+                Event::End(_) => output.end()?,
+                Event::Empty(item) => {
+                    let element = self.element_for(&item, &output);
+                    let count = match element {
+                        GutenbergElement::Inline(ref inline) if inline.void => {
+                            item.param(0).and_then(parse_count).unwrap_or(1)
+                        }
+                        _ => 1,
+                    };
+                    for _ in 0..count {
+                        if output.start(&item, &element)? {
+                            output.end()?;
+                        }
+                    }
+                }
+                Event::Text(text) => output.text(text)?,
+                Event::Escape(escape) if escape.seq == b'n' => {
+                    if output.in_text() {
+                        for _ in 0..escape.count() {
+                            output.break_line();
+                        }
+                    }
+                    output.last = Last::LineBreak;
+                }
+                Event::Escape(escape) => {
+                    let text: &[u8] = match escape.seq {
+                        b's' => b"&nbsp;",
+                        _ => &[],
+                    };
+                    for _ in 0..escape.count() {
+                        if text.is_empty() {
+                            output.escaped(&[escape.resolved()])?;
+                        } else {
+                            output.raw(text)?;
+                        }
+                    }
+                    output.last = Last::Escape;
+                }
+                Event::EmptyLine => output.empty_line()?,
+            }
+        }
+
+        output.finish()?;
+        Ok(())
+    }
+
+    /// The element an item is rendered as at the current position
+    fn element_for<W: Write>(&self, item: &Item, output: &Output<W>) -> GutenbergElement {
+        let name = classes(item);
+        match self.elements.get(&item.name) {
+            Some(GutenbergElement::Block(_)) | None if output.in_block_text() => {
+                GutenbergElement::Inline(HtmlElement::new("span").with_class(name))
+            }
+            Some(element) => element.clone(),
+            None => GutenbergElement::Block(
+                GutenbergBlock::new(
+                    "group",
+                    HtmlElement::new("div").with_class(format!("wp-block-group {}", name)),
+                )
+                .with_inner_blocks()
+                .with_attribute("className", name),
+            ),
+        }
+    }
+}
+
+impl Default for GutenbergRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An open element
+#[derive(Debug)]
+enum Frame {
+    /// A block holding other blocks, the top level has no name
+    Blocks {
+        /// The block name and the closing tag
+        close: Option<(String, String)>,
+        /// Whether no block was written into it yet
+        first: bool,
+    },
+    /// A block holding text, implicit paragraphs don't belong to an item
+    Text {
+        /// The block name
+        name: String,
+        /// The closing tag
+        tag: String,
+        /// Whether this is a paragraph around text between blocks
+        implicit: bool,
+        /// The block and parameters of a paragraph, an empty line starts it again
+        paragraph: Option<(GutenbergBlock, Vec<Vec<u8>>)>,
+    },
+    /// An inline element with its closing tag
+    Inline(String),
+}
+
+/// What the last output inside of text was, text and inline elements are separated by a space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing or something which needs no space
+    Start,
+    /// A line of text or an inline element
+    Text,
+    /// An escape sequence, text on the next line is separated by a space
+    Escape,
+    /// A line break escape sequence, text on the next line needs no space
+    LineBreak,
+}
+
+/// The state of the output while rendering
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The open elements, the first one is the top level
+    frames: Vec<Frame>,
+    /// What was written last inside of text
+    last: Last,
+    /// Trailing whitespace of the last line of text, only written before escape sequences
+    trailing: Vec<u8>,
+    /// How many items are left out together with their content
+    dropping: usize,
+    /// Line breaks inside of text, written once more text follows
+    breaks: usize,
+    /// Whether an empty line ended the text of a paragraph, it starts again once more text
+    /// follows
+    split: bool,
+}
+
+impl<'w, W: Write> Output<'w, W> {
+    /// Create the output with the top level open
+    fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            frames: vec![Frame::Blocks {
+                close: None,
+                first: true,
+            }],
+            last: Last::Start,
+            trailing: Vec::new(),
+            dropping: 0,
+            breaks: 0,
+            split: false,
+        }
+    }
+
+    /// Whether the output is inside of text
+    fn in_text(&self) -> bool {
+        matches!(
+            self.frames.last(),
+            Some(Frame::Text { .. } | Frame::Inline(_))
+        )
+    }
+
+    /// Whether the output is inside of the text of a block, other than a paragraph around text
+    /// between blocks
+    fn in_block_text(&self) -> bool {
+        matches!(
+            self.frames.last(),
+            Some(
+                Frame::Text {
+                    implicit: false,
+                    ..
+                } | Frame::Inline(_)
+            )
+        )
+    }
+
+    /// Start an item. Returns whether it was opened, void and dynamic elements are written
+    /// completely.
+    fn start(&mut self, item: &Item, element: &GutenbergElement) -> io::Result<bool> {
+        match element {
+            GutenbergElement::Inline(inline) if is_br(inline) => {
+                if self.in_text() {
+                    self.break_line();
+                }
+                Ok(false)
+            }
+            GutenbergElement::Inline(inline) => {
+                self.start_text()?;
+                self.write_breaks()?;
+                // Line breaks need no space around them
+                if inline.void {
+                    self.trailing.clear();
+                } else {
+                    self.separate()?;
+                }
+                write_tag(inline, self.writer)?;
+                self.writer.write_all(b">")?;
+                self.last = Last::Start;
+                if inline.void {
+                    return Ok(false);
+                }
+                self.frames.push(Frame::Inline(inline.tag.clone()));
+                Ok(true)
+            }
+            GutenbergElement::Block(block) => {
+                self.end_implicit()?;
+                self.start_block(block, item.params())
+            }
+        }
+    }
+
+    /// Write the start of a block and open it. Returns whether it was opened, see
+    /// [`Output::start`].
+    fn start_block(&mut self, block: &GutenbergBlock, params: &[Vec<u8>]) -> io::Result<bool> {
+        self.breaks = 0;
+        self.split = false;
+        if let Some(Frame::Blocks { first, .. }) = self.frames.last_mut() {
+            if !*first {
+                self.writer.write_all(b"\n\n")?;
+            }
+            *first = false;
+        }
+
+        let name = block.name.strip_prefix("core/").unwrap_or(&block.name);
+        write!(self.writer, "<!-- wp:{}", name)?;
+        let attributes = block
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .chain(
+                block
+                    .params
+                    .iter()
+                    .zip(params)
+                    .map(|(name, value)| (name.as_str(), value.as_slice())),
+            )
+            .collect::<Vec<_>>();
+        if !attributes.is_empty() {
+            self.writer.write_all(b" ")?;
+            write_attributes(&attributes, self.writer)?;
+        }
+
+        let Some(ref element) = block.element else {
+            self.writer.write_all(b" /-->")?;
+            return Ok(false);
+        };
+        self.writer.write_all(b" -->\n")?;
+        write_tag(element, self.writer)?;
+
+        if element.void {
+            write!(self.writer, "/>\n<!-- /wp:{} -->", name)?;
+            return Ok(false);
+        }
+        self.writer.write_all(b">")?;
+        if block.inner_blocks {
+            self.frames.push(Frame::Blocks {
+                close: Some((name.to_owned(), element.tag.clone())),
+                first: true,
+            });
+        } else {
+            self.frames.push(Frame::Text {
+                name: name.to_owned(),
+                tag: element.tag.clone(),
+                implicit: false,
+                paragraph: (name == "paragraph").then(|| (block.clone(), params.to_vec())),
+            });
+            self.last = Last::Start;
+        }
+
+        Ok(true)
+    }
+
+    /// End the innermost item
+    fn end(&mut self) -> io::Result<()> {
+        self.end_implicit()?;
+        self.close()
+    }
+
+    /// Close the innermost element
+    fn close(&mut self) -> io::Result<()> {
+        match self.frames.pop() {
+            Some(Frame::Blocks {
+                close: Some((name, tag)),
+                ..
+            }) => write!(self.writer, "</{}>\n<!-- /wp:{} -->", tag, name)?,
+            Some(Frame::Text { name, tag, .. }) => {
+                self.trailing.clear();
+                self.breaks = 0;
+                self.split = false;
+                write!(self.writer, "</{}>\n<!-- /wp:{} -->", tag, name)?;
+            }
+            Some(Frame::Inline(tag)) => {
+                self.trailing.clear();
+                write!(self.writer, "</{}>", tag)?;
+                self.last = Last::Text;
+            }
+            Some(Frame::Blocks { close: None, .. }) | None => (),
+        }
+
+        Ok(())
+    }
+
+    /// Write a line of text
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        self.start_text()?;
+        self.write_breaks()?;
+        let end = text
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(0, |i| i + 1);
+        self.separate()?;
+        html::write_escaped(&text[..end], self.writer)?;
+        self.trailing = text[end..].to_vec();
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Write escaped text which continues the current line
+    fn escaped(&mut self, text: &[u8]) -> io::Result<()> {
+        self.start_text()?;
+        self.write_breaks()?;
+        self.writer.write_all(&self.trailing)?;
+        self.trailing.clear();
+        html::write_escaped(text, self.writer)?;
+        self.last = Last::Start;
+        Ok(())
+    }
+
+    /// Write HTML which continues the current line
+    fn raw(&mut self, html: &[u8]) -> io::Result<()> {
+        self.start_text()?;
+        self.write_breaks()?;
+        self.writer.write_all(&self.trailing)?;
+        self.trailing.clear();
+        self.writer.write_all(html)?;
+        self.last = Last::Start;
+        Ok(())
+    }
+
+    /// End the paragraph around text between blocks, split a paragraph or break the line in the
+    /// text of another block, an empty line after an escape sequence is only the end of its line
+    fn empty_line(&mut self) -> io::Result<()> {
+        match self.last {
+            Last::Escape => {
+                self.last = Last::Text;
+                return Ok(());
+            }
+            Last::LineBreak => {
+                self.last = Last::Start;
+                return Ok(());
+            }
+            Last::Start | Last::Text => (),
+        }
+        match self.frames.last() {
+            Some(Frame::Text { implicit: true, .. }) => return self.end_implicit(),
+            Some(Frame::Text {
+                paragraph: Some(_), ..
+            }) => self.split = true,
+            Some(Frame::Text { .. } | Frame::Inline(_)) => self.break_line(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Break the line once more text follows, it needs no space around it
+    fn break_line(&mut self) {
+        self.breaks += 1;
+        self.trailing.clear();
+        self.last = Last::Start;
+    }
+
+    /// Write the line breaks owed before more text or start the paragraph again
+    fn write_breaks(&mut self) -> io::Result<()> {
+        let breaks = mem::take(&mut self.breaks);
+        if mem::take(&mut self.split) {
+            if let Some(Frame::Text {
+                paragraph: Some((block, params)),
+                ..
+            }) = self.frames.last()
+            {
+                let (block, params) = (block.clone(), params.clone());
+                self.close()?;
+                self.start_block(&block, &params)?;
+                return Ok(());
+            }
+        }
+        for _ in 0..breaks {
+            self.writer.write_all(b"<br>")?;
+        }
+        Ok(())
+    }
+
+    /// Separate a line of text or an inline element from the text before it
+    fn separate(&mut self) -> io::Result<()> {
+        self.trailing.clear();
+        if mem::replace(&mut self.last, Last::Start) == Last::Text {
+            self.writer.write_all(b" ")?;
+        }
+        Ok(())
+    }
+
+    /// Open a paragraph if text starts between blocks
+    fn start_text(&mut self) -> io::Result<()> {
+        if !self.in_text() {
+            let paragraph = GutenbergBlock::new("paragraph", HtmlElement::new("p"));
+            self.start_block(&paragraph, &[])?;
+            if let Some(Frame::Text { implicit, .. }) = self.frames.last_mut() {
+                *implicit = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the paragraph around text between blocks if one is open
+    fn end_implicit(&mut self) -> io::Result<()> {
+        if let Some(Frame::Text { implicit: true, .. }) = self.frames.last() {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Close the last paragraph and end the output with a line break
+    fn finish(&mut self) -> io::Result<()> {
+        self.end_implicit()?;
+        if let Some(Frame::Blocks { first: false, .. }) = self.frames.first() {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()
+    }
+}
+
+/// Whether the element is a plain line break
+fn is_br(element: &HtmlElement) -> bool {
+    element.void && element.tag == "br" && element.class.is_none()
+}
+
+/// The classes of an item without a configured element, its name followed by its parameters
+fn classes(item: &Item) -> String {
+    let mut classes = String::from_utf8_lossy(&item.name).into_owned();
+    for param in item.params() {
+        classes.push(' ');
+        classes.push_str(&String::from_utf8_lossy(param));
+    }
+    classes
+}
+
+/// Write the opening tag of an element without the closing `>`
+fn write_tag<W: Write>(element: &HtmlElement, writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"<")?;
+    writer.write_all(element.tag.as_bytes())?;
+    if let Some(ref class) = element.class {
+        writer.write_all(b" class=\"")?;
+        html::write_escaped(class.as_bytes(), writer)?;
+        writer.write_all(b"\"")?;
+    }
+    Ok(())
+}
+
+/// Write the attributes as a JSON object which can't end the surrounding HTML comment
+fn write_attributes<W: Write>(attributes: &[(&str, &[u8])], writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"{")?;
+    for (i, &(name, value)) in attributes.iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+        write_attribute_str(name.as_bytes(), writer)?;
+        writer.write_all(b":")?;
+        if value == b"true" || value == b"false" || is_json_number(value) {
+            writer.write_all(value)?;
+        } else {
+            write_attribute_str(value, writer)?;
+        }
+    }
+    writer.write_all(b"}")
+}
+
+/// Write a JSON string with the characters escaped which the block editor escapes in comments
+fn write_attribute_str<W: Write>(value: &[u8], writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in String::from_utf8_lossy(value).chars() {
+        match c {
+            '\\' => writer.write_all(b"\\\\")?,
+            '"' | '-' | '<' | '>' | '&' | '\0'..='\x1F' => {
+                write!(writer, "\\u{:04x}", u32::from(c))?
+            }
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+/// Whether the value is a number in JSON syntax
+fn is_json_number(value: &[u8]) -> bool {
+    /// The number of ASCII digits at the start
+    fn digits(s: &[u8]) -> usize {
+        s.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    let mut rest = value.strip_prefix(b"-").unwrap_or(value);
+    let int = digits(rest);
+    if int == 0 || (int > 1 && rest[0] == b'0') {
+        return false;
+    }
+    rest = &rest[int..];
+    if let Some(frac) = rest.strip_prefix(b".") {
+        let n = digits(frac);
+        if n == 0 {
+            return false;
+        }
+        rest = &frac[n..];
+    }
+    if let Some(exp) = rest.strip_prefix(b"e").or_else(|| rest.strip_prefix(b"E")) {
+        let exp = exp
+            .strip_prefix(b"+")
+            .or_else(|| exp.strip_prefix(b"-"))
+            .unwrap_or(exp);
+        let n = digits(exp);
+        if n == 0 {
+            return false;
+        }
+        rest = &exp[n..];
+    }
+
+    rest.is_empty()
+}
@@ -0,0 +1,265 @@
+//! A simple HTML renderer. Every item becomes an element, by default a `div` with the item name
+//! as its class. Elements can be configured per item name.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::{
+    event::{parse_count, Escape, Event, EventReader, Item},
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// The HTML element an item is rendered as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlElement {
+    /// The tag name of the element
+    pub tag: String,
+    /// The class attribute of the element
+    pub class: Option<String>,
+    /// Whether this is a void element like `br` which has no content and no closing tag. Void
+    /// elements are repeated as often as their first parameter says.
+    pub void: bool,
+}
+
+impl HtmlElement {
+    /// An element with the given tag and no class
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            class: None,
+            void: false,
+        }
+    }
+
+    /// A void element with the given tag
+    pub fn void(tag: impl Into<String>) -> Self {
+        Self {
+            void: true,
+            ..Self::new(tag)
+        }
+    }
+
+    /// Set the class of this element
+    pub fn with_class(mut self, class: impl Into<String>) -> Self {
+        self.class = Some(class.into());
+        self
+    }
+}
+
+/// Renders Amark documents as HTML
+#[derive(Debug, Clone)]
+pub struct HtmlRenderer {
+    /// Elements configured for specific item names
+    elements: HashMap<Vec<u8>, HtmlElement>,
+}
+
+impl HtmlRenderer {
+    /// Create a new renderer which renders `br` items as `<br>` and every other item as `div`
+    pub fn new() -> Self {
+        Self {
+            elements: HashMap::from([(b"br".to_vec(), HtmlElement::void("br"))]),
+        }
+    }
+
+    /// Render items with the given name as the given element
+    pub fn element(mut self, name: impl Into<Vec<u8>>, element: HtmlElement) -> Self {
+        self.elements.insert(name.into(), element);
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        let mut text = HtmlText::default();
+
+        while let Some(event) = events.next_event()? {
+            match event {
+                Event::Start(item) => {
+                    text.end_line(writer)?;
+                    self.write_start(item, writer)?;
+                }
+                Event::End(item) => {
+                    text.end_line(writer)?;
+                    self.write_end(&item, writer)?;
+                }
+                Event::Empty(item) => {
+                    text.end_line(writer)?;
+                    let element = self.element_for(&item);
+                    if element.void {
+                        let count = item.param(0).and_then(parse_count).unwrap_or(1);
+                        for _ in 0..count {
+                            self.write_start(&item, writer)?;
+                        }
+                    } else {
+                        self.write_start(&item, writer)?;
+                        self.write_end(&item, writer)?;
+                    }
+                }
+                Event::Text(line) => text.text(line, writer)?,
+                Event::Escape(escape) => text.escape(&escape, writer)?,
+                Event::EmptyLine => text.empty_line(writer)?,
+            }
+        }
+        text.end_line(writer)?;
+
+        Ok(())
+    }
+
+    /// Get the element configured for an item
+    fn element_for(&self, item: &Item) -> HtmlElement {
+        self.elements.get(&item.name).cloned().unwrap_or_else(|| {
+            HtmlElement::new("div").with_class(String::from_utf8_lossy(&item.name))
+        })
+    }
+
+    /// Write the opening tag of an item
+    fn write_start<W: Write>(&self, item: &Item, writer: &mut W) -> AmarkResult<'static, ()> {
+        let element = self.element_for(item);
+        writer.write_all(b"<")?;
+        writer.write_all(element.tag.as_bytes())?;
+        if let Some(ref class) = element.class {
+            writer.write_all(b" class=\"")?;
+            write_escaped(class.as_bytes(), writer)?;
+            writer.write_all(b"\"")?;
+        }
+        if !element.void && !item.params().is_empty() {
+            writer.write_all(b" data-params=\"")?;
+            for (i, param) in item.params().iter().enumerate() {
+                if i != 0 {
+                    writer.write_all(b",")?;
+                }
+                write_escaped(param, writer)?;
+            }
+            writer.write_all(b"\"")?;
+        }
+        writer.write_all(b">\n")?;
+
+        Ok(())
+    }
+
+    /// Write the closing tag of an item
+    fn write_end<W: Write>(&self, item: &Item, writer: &mut W) -> AmarkResult<'static, ()> {
+        let element = self.element_for(item);
+        if !element.void {
+            writer.write_all(b"</")?;
+            writer.write_all(element.tag.as_bytes())?;
+            writer.write_all(b">\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the text and escape sequences of blocks as HTML, shared with the
+/// [`TemplateRenderer`](crate::template::TemplateRenderer). A line ends with a line break once
+/// something other than an escape sequence follows it.
+#[derive(Debug, Default)]
+pub(crate) struct HtmlText {
+    /// What was written last
+    last: Last,
+}
+
+impl HtmlText {
+    /// Write a line of text
+    pub(crate) fn text<W: Write>(&mut self, text: &[u8], writer: &mut W) -> io::Result<()> {
+        if self.last == Last::Text {
+            writer.write_all(b"\n")?;
+        }
+        write_escaped(text, writer)?;
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Write an escape sequence as often as it is repeated
+    pub(crate) fn escape<W: Write>(&mut self, escape: &Escape, writer: &mut W) -> io::Result<()> {
+        for _ in 0..escape.count() {
+            match escape.seq {
+                b'n' => writer.write_all(b"<br>\n")?,
+                b's' => writer.write_all(b"&nbsp;")?,
+                _ => write_escaped(&[escape.resolved()], writer)?,
+            }
+        }
+        self.last = match escape.seq {
+            b'n' => Last::LineBreak,
+            _ => Last::Escape,
+        };
+        Ok(())
+    }
+
+    /// Write an empty line as `<br>`. The reader also gives an empty line after an escape
+    /// sequence at the end of a line, which only ends that line.
+    pub(crate) fn empty_line<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        match self.last {
+            Last::Escape => self.end_line(writer),
+            Last::LineBreak => {
+                self.last = Last::Start;
+                Ok(())
+            }
+            Last::Start | Last::Text => {
+                self.end_line(writer)?;
+                writer.write_all(b"<br>\n")
+            }
+        }
+    }
+
+    /// End the current line before anything other than text is written
+    pub(crate) fn end_line<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        if matches!(self.last, Last::Text | Last::Escape) {
+            writer.write_all(b"\n")?;
+        }
+        self.last = Last::Start;
+        Ok(())
+    }
+}
+
+/// What [`HtmlText`] wrote last
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing or a complete line
+    #[default]
+    Start,
+    /// Text
+    Text,
+    /// An escape sequence other than a line break
+    Escape,
+    /// An escaped line break
+    LineBreak,
+}
+
+/// Write text with the HTML special characters escaped
+pub(crate) fn write_escaped<W: Write>(text: &[u8], writer: &mut W) -> io::Result<()> {
+    let mut rest = text;
+    while let Some(pos) = rest
+        .iter()
+        .position(|b| matches!(b, b'<' | b'>' | b'&' | b'"' | b'\''))
+    {
+        writer.write_all(&rest[..pos])?;
+        writer.write_all(match rest[pos] {
+            b'<' => b"&lt;",
+            b'>' => b"&gt;",
+            b'&' => b"&amp;",
+            b'"' => b"&quot;",
+            _ => b"&#39;",
+        })?;
+        rest = &rest[pos + 1..];
+    }
+
+    writer.write_all(rest)
+}
@@ -0,0 +1,198 @@
+//! Splicing other files into a document with `include(path);` items.
+//!
+//! `include(path);`, or `@include(path);` inside of blocks, is replaced by the tokens of the file
+//! at the given path, relative to the directory of the including file. Included files can include
+//! further files, but not a file which is already being included. Errors in included files carry
+//! the whole chain of includes with the line in every file, see [`AmarkError::Include`].
+//!
+//! ```no_run
+//! use std::{fs::File, io::BufReader};
+//!
+//! use amark::{html::HtmlRenderer, include::IncludeExpander, AmarkStream};
+//!
+//! let stream = AmarkStream::new(BufReader::new(File::open("docs/handbook.amark")?));
+//! let mut html = Vec::new();
+//! HtmlRenderer::new().render(IncludeExpander::new(stream, "docs/handbook.amark"), &mut html)?;
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    event::parse_params,
+    stream::{read_group, TokenStream},
+    AmarkError, AmarkResult, AmarkStream, AmarkToken, DecodingReader, OwnedToken,
+};
+
+/// The name of the item including a file
+const INCLUDE: &[u8] = b"include";
+
+/// The stream of an included file
+type FileStream = AmarkStream<DecodingReader<BufReader<File>>>;
+
+/// A stream adapter which splices included files into the stream, see the [module docs](self)
+#[derive(Debug)]
+pub struct IncludeExpander<S> {
+    /// The stream of the document
+    stream: S,
+    /// The path of the document
+    path: PathBuf,
+    /// The files being included, the innermost last
+    files: Vec<Included>,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+/// A file being included
+#[derive(Debug)]
+struct Included {
+    /// The stream of the file
+    stream: FileStream,
+    /// The path of the file as it was resolved
+    path: PathBuf,
+    /// The line of the including file the include is on
+    line: usize,
+}
+
+impl<S: TokenStream> IncludeExpander<S> {
+    /// Create an expander over the stream of the document at the given path. Includes are
+    /// resolved relative to its directory.
+    pub fn new(stream: S, path: impl Into<PathBuf>) -> Self {
+        Self {
+            stream,
+            path: path.into(),
+            files: Vec::new(),
+            current: OwnedToken::End,
+        }
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// The next token of the innermost file, the end of included files is skipped
+    fn next_raw(&mut self) -> AmarkResult<'static, OwnedToken> {
+        loop {
+            let tok = match self.files.last_mut() {
+                Some(file) => file.stream.next_token()?.to_owned_token(),
+                None => return Ok(self.stream.next_token()?.to_owned_token()),
+            };
+            if tok != OwnedToken::End {
+                return Ok(tok);
+            }
+            self.files.pop();
+        }
+    }
+
+    /// The line the innermost file is at
+    fn line(&self) -> usize {
+        self.files
+            .last()
+            .map_or_else(|| self.stream.cur_line(), |file| file.stream.cur_line())
+    }
+
+    /// Read an include after its name and open the included file
+    fn include(&mut self) -> AmarkResult<'static, ()> {
+        let params = match self.next_raw()? {
+            OwnedToken::ParamsStart => {
+                parse_params(read_group(|| self.next_raw(), b"The end of the path")?)?
+            }
+            _ => Vec::new(),
+        };
+        let ([ref path], OwnedToken::ItemEnd) = (params.as_slice(), self.next_raw()?) else {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"include(path);"),
+                got: Cow::Borrowed(INCLUDE),
+            });
+        };
+
+        let path = String::from_utf8_lossy(path);
+        let including = self.files.last().map_or(&self.path, |file| &file.path);
+        let path = including
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&*path);
+
+        let canonical = fs::canonicalize(&path).map_err(|e| open_error(&path, e))?;
+        let including = self
+            .files
+            .iter()
+            .map(|file| &file.path)
+            .chain([&self.path])
+            .filter_map(|path| fs::canonicalize(path).ok());
+        for including in including {
+            if including == canonical {
+                return Err(AmarkError::UnexpectedInput {
+                    expected: Cow::Borrowed(b"A file which is not already being included"),
+                    got: Cow::Owned(path.display().to_string().into_bytes()),
+                });
+            }
+        }
+
+        let file = File::open(&path).map_err(|e| open_error(&path, e))?;
+        let line = self.line();
+        self.files.push(Included {
+            stream: AmarkStream::new(DecodingReader::new(BufReader::new(file))),
+            path,
+            line,
+        });
+        Ok(())
+    }
+
+    /// Attach the chain of includes to an error of an include or while reading an included file
+    fn in_chain(&self, error: AmarkError<'static>, including: bool) -> AmarkError<'static> {
+        if (self.files.is_empty() && !including) || matches!(error, AmarkError::Include { .. }) {
+            return error;
+        }
+
+        // Every file is paired with the line it is at, the includes are at the line of the
+        // include in the file after them
+        let mut chain = Vec::with_capacity(self.files.len() + 1);
+        let mut line = self.line();
+        for file in self.files.iter().rev() {
+            chain.push((file.path.clone(), line));
+            line = file.line;
+        }
+        chain.push((self.path.clone(), line));
+
+        AmarkError::Include {
+            chain,
+            error: Box::new(error),
+        }
+    }
+}
+
+impl<S: TokenStream> TokenStream for IncludeExpander<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        loop {
+            match self.next_raw() {
+                Ok(OwnedToken::ItemName(ref name)) if name == INCLUDE => {
+                    self.include().map_err(|e| self.in_chain(e, true))?;
+                }
+                Ok(tok) => {
+                    self.current = tok;
+                    return Ok(self.current.as_token());
+                }
+                Err(e) => return Err(self.in_chain(e, false)),
+            }
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
+
+/// Add the path to an error opening an included file
+fn open_error(path: &Path, error: io::Error) -> AmarkError<'static> {
+    AmarkError::IoError(io::Error::new(
+        error.kind(),
+        format!("{}: {}", path.display(), error),
+    ))
+}
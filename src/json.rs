@@ -0,0 +1,739 @@
+//! JSON export and import of Amark documents.
+//!
+//! Documents are exported either as their tokens with [`write_tokens`] or as a tree of nodes
+//! with [`write_tree`]. In the tree text is a string per line, items are objects like
+//! `{"name":"rb","line":3,"kind":"Block","params":["a"],"children":[…]}` where `params` is left
+//! out without a parameter list and `children` for items ended by `;`. Escape sequences are
+//! objects like `{"escape":"s","params":["4"]}` and empty lines are `{"empty_line":true}`.
+//!
+//! Positions are line-only: `line` is the line the item name is on, token streams don't track
+//! columns. The [XML export](crate::xml) reads the source itself and also has columns.
+//!
+//! [`import`] reads both formats and writes them as Amark source again. Arrays and objects can
+//! be nested at most 128 levels deep, deeper input is rejected.
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    str,
+};
+
+use crate::{
+    event::{Escape, Item, ItemKind},
+    stream::TokenStream,
+    tree::{Document, Element, Node},
+    AmarkError, AmarkFormatter, AmarkResult, AmarkToken, AmarkWriter, OwnedToken,
+};
+
+/// How deep arrays and objects can be nested, the parser recurses into them
+const MAX_DEPTH: usize = 128;
+
+/// Write all tokens of the stream as a JSON array, one token per line. Every token is an object
+/// with its name as `t` and its content, if any, as `v`, e.g. `{"t":"ItemName","v":"p"}`.
+///
+/// # Errors
+///
+/// This errors when the stream errors or writing fails.
+pub fn write_tokens<S: TokenStream, W: Write>(
+    mut stream: S,
+    writer: &mut W,
+) -> AmarkResult<'static, ()> {
+    writer.write_all(b"[")?;
+
+    let mut first = true;
+    loop {
+        let tok = stream.next_token()?;
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        first = false;
+        write_token(&tok, writer)?;
+
+        if tok == AmarkToken::End {
+            break;
+        }
+    }
+
+    writer.write_all(b"\n]\n")?;
+    Ok(())
+}
+
+/// Write a single token as a JSON object
+///
+/// # Errors
+///
+/// This errors when writing fails.
+pub fn write_token<W: Write>(tok: &AmarkToken, writer: &mut W) -> io::Result<()> {
+    let (name, value): (&[u8], Option<&[u8]>) = match *tok {
+        AmarkToken::BlockStart => (b"BlockStart", None),
+        AmarkToken::ParamsStart => (b"ParamsStart", None),
+        AmarkToken::ContainerStart => (b"ContainerStart", None),
+        AmarkToken::BlockEnd => (b"BlockEnd", None),
+        AmarkToken::ParamsEnd => (b"ParamsEnd", None),
+        AmarkToken::ContainerEnd => (b"ContainerEnd", None),
+        AmarkToken::ItemEnd => (b"ItemEnd", None),
+        AmarkToken::EmptyLine => (b"EmptyLine", None),
+        AmarkToken::End => (b"End", None),
+        AmarkToken::ItemName(name) => (b"ItemName", Some(name)),
+        AmarkToken::Text(text) => (b"Text", Some(text)),
+        AmarkToken::EscapeSequence(ref b) => (b"EscapeSequence", Some(std::slice::from_ref(b))),
+    };
+
+    writer.write_all(b"{\"t\":\"")?;
+    writer.write_all(name)?;
+    writer.write_all(b"\"")?;
+    if let Some(value) = value {
+        writer.write_all(b",\"v\":")?;
+        write_str(value, writer)?;
+    }
+    writer.write_all(b"}")
+}
+
+/// Write the bytes as a JSON string, invalid UTF-8 is replaced by `U+FFFD`
+///
+/// # Errors
+///
+/// This errors when writing fails.
+pub fn write_str<W: Write>(value: &[u8], writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+
+    for chunk in value.utf8_chunks() {
+        let valid = chunk.valid().as_bytes();
+        let mut start = 0;
+        for (i, &b) in valid.iter().enumerate() {
+            let escaped: &[u8] = match b {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0..=0x1F => b"",
+                _ => continue,
+            };
+            writer.write_all(&valid[start..i])?;
+            if escaped.is_empty() {
+                write!(writer, "\\u{:04x}", b)?;
+            } else {
+                writer.write_all(escaped)?;
+            }
+            start = i + 1;
+        }
+        writer.write_all(&valid[start..])?;
+
+        if !chunk.invalid().is_empty() {
+            writer.write_all("\u{FFFD}".as_bytes())?;
+        }
+    }
+
+    writer.write_all(b"\"")
+}
+
+/// Read the document from the stream and write it as a JSON tree with the line of every item,
+/// see the [module docs](self)
+///
+/// # Errors
+///
+/// This errors when the stream errors or writing fails.
+pub fn write_tree<S: TokenStream, W: Write>(stream: S, writer: &mut W) -> AmarkResult<'static, ()> {
+    let document = Document::read(stream)?;
+    write_nodes(&document.children, writer)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Write the nodes as a JSON array
+///
+/// # Errors
+///
+/// This errors when writing fails.
+pub fn write_nodes<W: Write>(nodes: &[Node], writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"[")?;
+    for (i, node) in nodes.iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+        match *node {
+            Node::Element(ref element) => write_element(element, writer)?,
+            Node::Text(ref text) => write_str(text, writer)?,
+            Node::Escape(ref escape) => {
+                writer.write_all(b"{\"escape\":")?;
+                write_str(&[escape.seq], writer)?;
+                write_params(escape.params.as_deref(), writer)?;
+                writer.write_all(b"}")?;
+            }
+            Node::EmptyLine => writer.write_all(b"{\"empty_line\":true}")?,
+        }
+    }
+    writer.write_all(b"]")
+}
+
+/// Write an element as a JSON object
+fn write_element<W: Write>(element: &Element, writer: &mut W) -> io::Result<()> {
+    let kind = match element.item.kind {
+        ItemKind::Bare => "Bare",
+        ItemKind::Block => "Block",
+        ItemKind::Container => "Container",
+    };
+
+    writer.write_all(b"{\"name\":")?;
+    write_str(element.name(), writer)?;
+    write!(writer, ",\"line\":{},\"kind\":\"{}\"", element.line, kind)?;
+    write_params(element.item.params.as_deref(), writer)?;
+    if element.has_body() {
+        writer.write_all(b",\"children\":")?;
+        write_nodes(&element.children, writer)?;
+    }
+    writer.write_all(b"}")
+}
+
+/// Write the parameters as a `params` member if there are any
+fn write_params<W: Write>(params: Option<&[Vec<u8>]>, writer: &mut W) -> io::Result<()> {
+    let Some(params) = params else {
+        return Ok(());
+    };
+
+    writer.write_all(b",\"params\":[")?;
+    for (i, param) in params.iter().enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+        write_str(param, writer)?;
+    }
+    writer.write_all(b"]")
+}
+
+/// Write JSON exported with [`write_tokens`] or [`write_tree`] as Amark source
+///
+/// # Errors
+///
+/// This errors when the input is not valid JSON in one of the formats, the document can't be
+/// written as Amark or writing fails.
+pub fn import<W: Write>(json: &[u8], writer: &mut W) -> AmarkResult<'static, ()> {
+    let value = JsonParser::new(json).parse()?;
+    let Value::Array(values) = value else {
+        return Err(unexpected(b"A JSON array", value.describe()));
+    };
+
+    let is_tokens = values
+        .first()
+        .is_some_and(|value| value.member(b"t").is_some());
+    if is_tokens {
+        let mut formatter = AmarkFormatter::new();
+        for value in &values {
+            formatter.write_token(&read_token(value)?.as_token(), writer)?;
+        }
+    } else {
+        let document = Document {
+            children: read_nodes(&values)?,
+        };
+        let mut amark = AmarkWriter::new(&mut *writer);
+        document.write(&mut amark)?;
+        amark.finish()?;
+    }
+
+    Ok(())
+}
+
+/// Read the tokens exported with [`write_tokens`]
+///
+/// # Errors
+///
+/// This errors when the input is not a valid JSON array of tokens.
+pub fn read_tokens(json: &[u8]) -> AmarkResult<'static, Vec<OwnedToken>> {
+    match JsonParser::new(json).parse()? {
+        Value::Array(values) => values.iter().map(read_token).collect(),
+        value => Err(unexpected(b"A JSON array of tokens", value.describe())),
+    }
+}
+
+/// Read the document exported with [`write_tree`], missing lines are 0
+///
+/// # Errors
+///
+/// This errors when the input is not a valid JSON tree.
+pub fn read_tree(json: &[u8]) -> AmarkResult<'static, Document> {
+    match JsonParser::new(json).parse()? {
+        Value::Array(values) => Ok(Document {
+            children: read_nodes(&values)?,
+        }),
+        value => Err(unexpected(b"A JSON array of nodes", value.describe())),
+    }
+}
+
+/// Read a token object
+fn read_token(value: &Value) -> AmarkResult<'static, OwnedToken> {
+    let name = value
+        .member(b"t")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let content = || {
+        value
+            .member(b"v")
+            .and_then(Value::as_str)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| unexpected(b"A token with a string as v", value.describe()))
+    };
+
+    Ok(match name {
+        b"BlockStart" => OwnedToken::BlockStart,
+        b"ParamsStart" => OwnedToken::ParamsStart,
+        b"ContainerStart" => OwnedToken::ContainerStart,
+        b"BlockEnd" => OwnedToken::BlockEnd,
+        b"ParamsEnd" => OwnedToken::ParamsEnd,
+        b"ContainerEnd" => OwnedToken::ContainerEnd,
+        b"ItemEnd" => OwnedToken::ItemEnd,
+        b"EmptyLine" => OwnedToken::EmptyLine,
+        b"End" => OwnedToken::End,
+        b"ItemName" => OwnedToken::ItemName(content()?),
+        b"Text" => OwnedToken::Text(content()?),
+        b"EscapeSequence" => OwnedToken::EscapeSequence(single_byte(&content()?)?),
+        name => return Err(unexpected(b"The name of a token", name.to_vec())),
+    })
+}
+
+/// Read the nodes of a JSON array
+fn read_nodes(values: &[Value]) -> AmarkResult<'static, Vec<Node>> {
+    values.iter().map(read_node).collect()
+}
+
+/// Read a single node
+fn read_node(value: &Value) -> AmarkResult<'static, Node> {
+    if let Some(text) = value.as_str() {
+        return Ok(Node::Text(text.to_vec()));
+    }
+    if let Some(seq) = value.member(b"escape") {
+        let seq = seq
+            .as_str()
+            .ok_or_else(|| unexpected(b"An escaped character", seq.describe()))?;
+        return Ok(Node::Escape(Escape {
+            seq: single_byte(seq)?,
+            params: read_params(value)?,
+        }));
+    }
+    if value.member(b"empty_line").is_some() {
+        return Ok(Node::EmptyLine);
+    }
+
+    let name = value
+        .member(b"name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            unexpected(
+                b"A text, escape sequence, empty line or item",
+                value.describe(),
+            )
+        })?;
+    let kind = match value.member(b"kind").and_then(Value::as_str) {
+        Some(b"Block") => ItemKind::Block,
+        Some(b"Container") => ItemKind::Container,
+        Some(b"Bare") | None => ItemKind::Bare,
+        Some(kind) => return Err(unexpected(b"Bare, Block or Container", kind.to_vec())),
+    };
+    let line = match value.member(b"line") {
+        Some(Value::Number(number)) => str::from_utf8(number)
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| unexpected(b"A line number", number.clone()))?,
+        Some(other) => return Err(unexpected(b"A line number", other.describe())),
+        None => 0,
+    };
+    let children = match value.member(b"children") {
+        Some(Value::Array(children)) => read_nodes(children)?,
+        Some(other) => return Err(unexpected(b"An array of children", other.describe())),
+        None => Vec::new(),
+    };
+
+    Ok(Node::Element(Element {
+        item: Item {
+            name: name.to_vec(),
+            params: read_params(value)?,
+            kind,
+        },
+        line,
+        children,
+    }))
+}
+
+/// Read the `params` member of an item or escape sequence
+fn read_params(value: &Value) -> AmarkResult<'static, Option<Vec<Vec<u8>>>> {
+    let Some(params) = value.member(b"params") else {
+        return Ok(None);
+    };
+
+    match *params {
+        Value::Array(ref params) => params
+            .iter()
+            .map(|param| {
+                param
+                    .as_str()
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| unexpected(b"A string parameter", param.describe()))
+            })
+            .collect::<AmarkResult<_>>()
+            .map(Some),
+        ref other => Err(unexpected(b"An array of parameters", other.describe())),
+    }
+}
+
+/// The only byte of an escape sequence
+fn single_byte(seq: &[u8]) -> AmarkResult<'static, u8> {
+    match *seq {
+        [b] => Ok(b),
+        _ => Err(unexpected(b"A single escaped byte", seq.to_vec())),
+    }
+}
+
+/// Create an error for unexpected input
+fn unexpected(expected: &'static [u8], got: Vec<u8>) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got),
+    }
+}
+
+/// Parse a single JSON value
+pub(crate) fn parse(json: &[u8]) -> AmarkResult<'static, Value> {
+    JsonParser::new(json).parse()
+}
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number as written in the input
+    Number(Vec<u8>),
+    /// A string as UTF-8
+    String(Vec<u8>),
+    /// An array
+    Array(Vec<Value>),
+    /// An object with its members in order
+    Object(Vec<(Vec<u8>, Value)>),
+}
+
+impl Value {
+    /// The member of an object with the given name
+    pub(crate) fn member(&self, name: &[u8]) -> Option<&Value> {
+        match *self {
+            Self::Object(ref members) => members
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// The content of a string
+    pub(crate) fn as_str(&self) -> Option<&[u8]> {
+        match *self {
+            Self::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value of a non-negative integer
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Self::Number(ref n) => str::from_utf8(n).ok()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Write the value as compact JSON
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match *self {
+            Self::Null => writer.write_all(b"null"),
+            Self::Bool(b) => write!(writer, "{}", b),
+            Self::Number(ref n) => writer.write_all(n),
+            Self::String(ref s) => write_str(s, writer),
+            Self::Array(ref values) => {
+                writer.write_all(b"[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    value.write(writer)?;
+                }
+                writer.write_all(b"]")
+            }
+            Self::Object(ref members) => {
+                writer.write_all(b"{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    write_str(name, writer)?;
+                    writer.write_all(b":")?;
+                    value.write(writer)?;
+                }
+                writer.write_all(b"}")
+            }
+        }
+    }
+
+    /// A short description of the value for errors
+    fn describe(&self) -> Vec<u8> {
+        let desc: &[u8] = match *self {
+            Self::Null => b"null",
+            Self::Bool(true) => b"true",
+            Self::Bool(false) => b"false",
+            Self::Number(ref n) => n,
+            Self::String(ref s) => return [b"\"", s.as_slice(), b"\""].concat(),
+            Self::Array(_) => b"An array",
+            Self::Object(_) => b"An object",
+        };
+        desc.to_vec()
+    }
+}
+
+/// A recursive descent parser for JSON
+struct JsonParser<'a> {
+    /// The input
+    json: &'a [u8],
+    /// The position of the next byte
+    pos: usize,
+    /// How many arrays and objects are open
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    /// Create a parser for the input
+    fn new(json: &'a [u8]) -> Self {
+        Self {
+            json,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parse the input as a single value
+    fn parse(mut self) -> AmarkResult<'static, Value> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.json.len() {
+            return Err(self.unexpected(b"The end of the input"));
+        }
+        Ok(value)
+    }
+
+    /// Parse a value
+    fn value(&mut self) -> AmarkResult<'static, Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b @ (b'{' | b'[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.unexpected(b"At most 128 nested arrays and objects"));
+                }
+                self.depth += 1;
+                let value = if b == b'{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.literal(b"true", Value::Bool(true)),
+            Some(b'f') => self.literal(b"false", Value::Bool(false)),
+            Some(b'n') => self.literal(b"null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.unexpected(b"A JSON value")),
+        }
+    }
+
+    /// Parse an object
+    fn object(&mut self) -> AmarkResult<'static, Value> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.unexpected(b"The name of a member"));
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.unexpected(b":"));
+            }
+            members.push((name, self.value()?));
+
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Value::Object(members));
+            }
+            if !self.eat(b',') {
+                return Err(self.unexpected(b", or }"));
+            }
+        }
+    }
+
+    /// Parse an array
+    fn array(&mut self) -> AmarkResult<'static, Value> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Ok(Value::Array(values));
+            }
+            if !self.eat(b',') {
+                return Err(self.unexpected(b", or ]"));
+            }
+        }
+    }
+
+    /// Parse a string starting at its opening quote
+    fn string(&mut self) -> AmarkResult<'static, Vec<u8>> {
+        self.pos += 1;
+        let mut s = Vec::new();
+
+        loop {
+            let Some(b) = self.next() else {
+                return Err(AmarkError::UnexpectedEof {
+                    expected: Cow::Borrowed(b"The end of the string"),
+                });
+            };
+            match b {
+                b'"' => return Ok(s),
+                b'\\' => {
+                    let escaped = match self.next() {
+                        Some(b @ (b'"' | b'\\' | b'/')) => b,
+                        Some(b'b') => 0x08,
+                        Some(b'f') => 0x0C,
+                        Some(b'n') => b'\n',
+                        Some(b'r') => b'\r',
+                        Some(b't') => b'\t',
+                        Some(b'u') => {
+                            let c = self.unicode_escape()?;
+                            s.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                            continue;
+                        }
+                        _ => return Err(self.unexpected(b"An escape sequence")),
+                    };
+                    s.push(escaped);
+                }
+                b => s.push(b),
+            }
+        }
+    }
+
+    /// Parse the rest of a `\u` escape sequence, including a second one for surrogate pairs
+    fn unicode_escape(&mut self) -> AmarkResult<'static, char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !(self.eat(b'\\') && self.eat(b'u')) {
+                return Err(self.unexpected(b"The second half of a surrogate pair"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.unexpected(b"The second half of a surrogate pair"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.unexpected(b"A unicode scalar value"))
+    }
+
+    /// Parse 4 hexadecimal digits
+    fn hex4(&mut self) -> AmarkResult<'static, u32> {
+        let digits = self
+            .json
+            .get(self.pos..self.pos + 4)
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.unexpected(b"4 hexadecimal digits"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    /// Parse a number, it is kept as written
+    fn number(&mut self) -> AmarkResult<'static, Value> {
+        let start = self.pos;
+        self.eat(b'-');
+        // Integers have no leading zeros
+        if !self.eat(b'0') && self.digits() == 0 {
+            return Err(self.unexpected(b"The digits of a number"));
+        }
+        if self.eat(b'.') && self.digits() == 0 {
+            return Err(self.unexpected(b"The digits of a fraction"));
+        }
+        if self.eat(b'e') || self.eat(b'E') {
+            let _ = self.eat(b'+') || self.eat(b'-');
+            if self.digits() == 0 {
+                return Err(self.unexpected(b"The digits of an exponent"));
+            }
+        }
+        Ok(Value::Number(self.json[start..self.pos].to_vec()))
+    }
+
+    /// Skip digits and return how many there were
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// Parse the given literal
+    fn literal(&mut self, literal: &'static [u8], value: Value) -> AmarkResult<'static, Value> {
+        if self.json[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.unexpected(literal))
+        }
+    }
+
+    /// Skip whitespace between tokens
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b" \t\r\n".contains(&b)) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume the given byte if it is next
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// The next byte without consuming it
+    fn peek(&self) -> Option<u8> {
+        self.json.get(self.pos).copied()
+    }
+
+    /// Consume the next byte
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    /// Create an error for the input at the current position
+    fn unexpected(&self, expected: &'static [u8]) -> AmarkError<'static> {
+        if self.pos >= self.json.len() {
+            return AmarkError::UnexpectedEof {
+                expected: Cow::Borrowed(expected),
+            };
+        }
+
+        let end = self.json.len().min(self.pos + 16);
+        unexpected(expected, self.json[self.pos..end].to_vec())
+    }
+}
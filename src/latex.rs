@@ -0,0 +1,490 @@
+//! A LaTeX renderer for print output. Items are rendered as the environment or command configured
+//! for their name, items without one only render their content. Items between other items are
+//! separated as paragraphs.
+//!
+//! Lines of text are written as lines, an empty line starts a new paragraph, `\n` breaks the line
+//! and `\s` is a non-breaking space. The parameters of environments and commands are their
+//! arguments.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    mem,
+};
+
+use crate::{
+    event::{parse_count, Event, EventReader, Item, ItemKind},
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// The form of a [`LatexElement`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatexKind {
+    /// An environment like `\begin{quote}...\end{quote}`
+    Environment,
+    /// A command with the content as its last argument like `\emph{...}`
+    Command,
+    /// An entry of the surrounding list, `\item ...`
+    ListItem,
+    /// Vertical space of as many lines as the first parameter says, the content is left out
+    VerticalSpace,
+}
+
+/// The LaTeX construct an item is rendered as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatexElement {
+    /// The form of the element
+    pub kind: LatexKind,
+    /// The name of the environment or command
+    pub name: String,
+    /// Optional arguments written in brackets, like `colback=red!5`
+    pub options: Option<String>,
+}
+
+impl LatexElement {
+    /// An environment with the given name
+    pub fn environment(name: impl Into<String>) -> Self {
+        Self {
+            kind: LatexKind::Environment,
+            name: name.into(),
+            options: None,
+        }
+    }
+
+    /// A command with the given name without the backslash
+    pub fn command(name: impl Into<String>) -> Self {
+        Self {
+            kind: LatexKind::Command,
+            name: name.into(),
+            options: None,
+        }
+    }
+
+    /// An entry of a list
+    pub fn list_item() -> Self {
+        Self {
+            kind: LatexKind::ListItem,
+            name: String::from("item"),
+            options: None,
+        }
+    }
+
+    /// Vertical space
+    pub fn vertical_space() -> Self {
+        Self {
+            kind: LatexKind::VerticalSpace,
+            name: String::from("vspace"),
+            options: None,
+        }
+    }
+
+    /// Set the optional arguments of this element
+    pub fn with_options(mut self, options: impl Into<String>) -> Self {
+        self.options = Some(options.into());
+        self
+    }
+}
+
+/// Renders Amark documents as LaTeX
+#[derive(Debug, Clone)]
+pub struct LatexRenderer {
+    /// Elements configured for specific item names
+    elements: HashMap<Vec<u8>, LatexElement>,
+    /// Written before `\begin{document}` if the output is a whole document
+    preamble: Option<String>,
+}
+
+impl LatexRenderer {
+    /// A preamble for an `article` with the packages the default elements need
+    pub const DEFAULT_PREAMBLE: &'static str =
+        "\\documentclass{article}\n\\usepackage[T1]{fontenc}\n\\usepackage{tcolorbox}\n";
+
+    /// Create a new renderer with sections for `h1` to `h3`, `emph` for `em`, `textbf` for
+    /// `strong`, `texttt` for `code`, `itemize` for `ul`, `enumerate` for `ol`, `quote` for
+    /// `blockquote`, colored `tcolorbox`es for `gb`, `bb`, `rb` and `yb` and vertical space for
+    /// `br`. `li` are list entries.
+    pub fn new() -> Self {
+        let mut elements = HashMap::from([
+            (b"h1".to_vec(), LatexElement::command("section")),
+            (b"h2".to_vec(), LatexElement::command("subsection")),
+            (b"h3".to_vec(), LatexElement::command("subsubsection")),
+            (b"em".to_vec(), LatexElement::command("emph")),
+            (b"strong".to_vec(), LatexElement::command("textbf")),
+            (b"code".to_vec(), LatexElement::command("texttt")),
+            (b"ul".to_vec(), LatexElement::environment("itemize")),
+            (b"ol".to_vec(), LatexElement::environment("enumerate")),
+            (b"li".to_vec(), LatexElement::list_item()),
+            (b"blockquote".to_vec(), LatexElement::environment("quote")),
+            (b"br".to_vec(), LatexElement::vertical_space()),
+        ]);
+        for (name, color) in [
+            ("gb", "green"),
+            ("bb", "blue"),
+            ("rb", "red"),
+            ("yb", "yellow"),
+        ] {
+            elements.insert(
+                name.as_bytes().to_vec(),
+                LatexElement::environment("tcolorbox").with_options(format!(
+                    "colback={}!5!white,colframe={}!75!black",
+                    color, color
+                )),
+            );
+        }
+
+        Self {
+            elements,
+            preamble: None,
+        }
+    }
+
+    /// Render items with the given name as the given element
+    pub fn element(mut self, name: impl Into<Vec<u8>>, element: LatexElement) -> Self {
+        self.elements.insert(name.into(), element);
+        self
+    }
+
+    /// Render a whole document with the given preamble, like
+    /// [`LatexRenderer::DEFAULT_PREAMBLE`], instead of only its body
+    pub fn preamble(mut self, preamble: impl Into<String>) -> Self {
+        self.preamble = Some(preamble.into());
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        if let Some(ref preamble) = self.preamble {
+            writer.write_all(preamble.as_bytes())?;
+            if !preamble.is_empty() && !preamble.ends_with('\n') {
+                writer.write_all(b"\n")?;
+            }
+            writer.write_all(b"\\begin{document}\n")?;
+        }
+
+        let mut output = Output {
+            writer,
+            frames: Vec::new(),
+            sep: Sep::None,
+            at_line_start: true,
+            blank: true,
+            trailing: Vec::new(),
+            last: Last::Start,
+            dropping: 0,
+        };
+        while let Some(event) = events.next_event()? {
+            if output.dropping > 0 {
+                match event {
+                    Event::Start(_) => output.dropping += 1,
+                    Event::End(_) => output.dropping -= 1,
+                    _ => (),
+                }
+                continue;
+            }
+
+            match event {
+                Event::Start(item) => {
+                    // The content of vertical space is left out
+                    if !output.start(item, self.elements.get(&item.name))? {
+                        output.dropping = 1;
+                    }
+                }
+                Event::End(_) => output.end()?,
+                Event::Empty(item) => {
+                    if output.start(&item, self.elements.get(&item.name))? {
+                        output.end()?;
+                    }
+                }
+                Event::Text(text) => output.text(text)?,
+                Event::Escape(escape) => {
+                    for _ in 0..escape.count() {
+                        match escape.seq {
+                            b'n' => output.inline(b"\\\\\n")?,
+                            b's' => output.inline(b"~")?,
+                            _ => output.escaped(&[escape.resolved()])?,
+                        }
+                    }
+                }
+                Event::EmptyLine => output.empty_line(),
+            }
+        }
+
+        // Nothing follows which would need a paragraph break
+        output.sep = Sep::Line;
+        output.write_sep()?;
+        if self.preamble.is_some() {
+            output.writer.write_all(b"\\end{document}\n")?;
+        }
+        output.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for LatexRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whitespace owed between the last and the next output, larger ones replace smaller ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    /// Nothing
+    None,
+    /// A line break, which LaTeX reads as a space inside of text
+    Line,
+    /// An empty line between paragraphs
+    Paragraph,
+}
+
+/// What was written last inside of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing or the start of an item
+    Start,
+    /// A line of text or an inline command, the next text starts on a new line
+    Text,
+    /// An escape sequence, text continues on the same line after it
+    Escape,
+}
+
+/// An item that is currently rendered
+#[derive(Debug)]
+struct Frame {
+    /// Written at the end of the item
+    end: Vec<u8>,
+    /// Whether the item is an environment which ends on a line of its own
+    environment: bool,
+    /// Whether the content of the item is text
+    text: bool,
+    /// Whether the item is separated from others as a paragraph
+    paragraph: bool,
+}
+
+/// The state of the output while rendering
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The items being rendered
+    frames: Vec<Frame>,
+    /// Whitespace owed before the next output
+    sep: Sep,
+    /// Whether the next output starts a line
+    at_line_start: bool,
+    /// Whether a paragraph starts here anyway, like at the start of the output or an
+    /// environment
+    blank: bool,
+    /// Trailing whitespace of the last text, only written before escape sequences
+    trailing: Vec<u8>,
+    /// What was written last inside of text
+    last: Last,
+    /// How deep the renderer is inside of items whose content is left out
+    dropping: usize,
+}
+
+impl<W: Write> Output<'_, W> {
+    /// Whether the current content is text
+    fn in_text(&self) -> bool {
+        self.frames.last().is_some_and(|frame| frame.text)
+    }
+
+    /// Start rendering an item. Returns whether it was opened, vertical space is written
+    /// completely.
+    fn start(&mut self, item: &Item, element: Option<&LatexElement>) -> io::Result<bool> {
+        let in_text = self.in_text();
+        let mut frame = Frame {
+            end: Vec::new(),
+            environment: false,
+            text: item.kind == ItemKind::Block,
+            paragraph: false,
+        };
+        if !in_text && element.is_none_or(|element| element.kind == LatexKind::Command) {
+            frame.paragraph = true;
+            self.separate(Sep::Paragraph);
+        }
+
+        match element {
+            Some(element) => match element.kind {
+                LatexKind::Environment => {
+                    self.separate(Sep::Line);
+                    self.write(format!("\\begin{{{}}}", element.name).as_bytes())?;
+                    self.write_arguments(element, item)?;
+                    self.separate(Sep::Line);
+                    self.blank = true;
+                    frame.end = format!("\\end{{{}}}", element.name).into_bytes();
+                    frame.environment = true;
+                }
+                LatexKind::Command => {
+                    self.continue_text();
+                    self.write(format!("\\{}", element.name).as_bytes())?;
+                    self.write_arguments(element, item)?;
+                    self.write(b"{")?;
+                    frame.end = b"}".to_vec();
+                }
+                LatexKind::ListItem => {
+                    self.separate(Sep::Line);
+                    self.write(b"\\item ")?;
+                }
+                LatexKind::VerticalSpace => {
+                    let count = item.param(0).and_then(parse_count).unwrap_or(1);
+                    self.separate(Sep::Line);
+                    self.write(format!("\\vspace{{{}\\baselineskip}}", count).as_bytes())?;
+                    self.separate(Sep::Line);
+                    return Ok(false);
+                }
+            },
+            None => frame.text = frame.text || in_text,
+        }
+
+        self.last = Last::Start;
+        self.frames.push(frame);
+        Ok(true)
+    }
+
+    /// Finish rendering the current item
+    fn end(&mut self) -> io::Result<()> {
+        let Some(frame) = self.frames.pop() else {
+            return Ok(());
+        };
+
+        self.trailing.clear();
+        if frame.environment {
+            self.sep = Sep::Line;
+        }
+        self.write(&frame.end)?;
+
+        if frame.paragraph {
+            self.separate(Sep::Paragraph);
+        } else if frame.environment {
+            self.separate(Sep::Line);
+        } else {
+            self.last = Last::Text;
+        }
+        Ok(())
+    }
+
+    /// Write the options and parameters of an environment or command
+    fn write_arguments(&mut self, element: &LatexElement, item: &Item) -> io::Result<()> {
+        if let Some(ref options) = element.options {
+            self.write(format!("[{}]", options).as_bytes())?;
+        }
+        for param in item.params() {
+            self.write(b"{")?;
+            self.write_escaped(param)?;
+            self.write(b"}")?;
+        }
+        Ok(())
+    }
+
+    /// Write a line of text
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        self.continue_text();
+        let content = text.trim_ascii_end();
+        self.write_escaped(content)?;
+        self.trailing = text[content.len()..].to_vec();
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Write a LaTeX sequence which continues the current line of text
+    fn inline(&mut self, latex: &[u8]) -> io::Result<()> {
+        let trailing = mem::take(&mut self.trailing);
+        self.write(&trailing)?;
+        self.write(latex)?;
+        if latex.ends_with(b"\n") {
+            self.at_line_start = true;
+        }
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Write escaped text which continues the current line of text
+    fn escaped(&mut self, text: &[u8]) -> io::Result<()> {
+        let trailing = mem::take(&mut self.trailing);
+        self.write(&trailing)?;
+        self.write_escaped(text)?;
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Start a new paragraph, the empty line after an escape sequence only ends its line
+    fn empty_line(&mut self) {
+        if self.last == Last::Escape {
+            self.separate(Sep::Line);
+        } else {
+            self.separate(Sep::Paragraph);
+        }
+    }
+
+    /// Start text or an inline command on a new line after text
+    fn continue_text(&mut self) {
+        if self.last == Last::Text {
+            self.separate(Sep::Line);
+        }
+        self.trailing.clear();
+    }
+
+    /// Owe at least the given whitespace before the next output
+    fn separate(&mut self, sep: Sep) {
+        self.sep = self.sep.max(sep);
+        self.last = Last::Start;
+    }
+
+    /// Write text with the LaTeX special characters escaped
+    fn write_escaped(&mut self, text: &[u8]) -> io::Result<()> {
+        let mut rest = text;
+        while let Some(pos) = rest.iter().position(|b| b"\\{}$&#^_%~".contains(b)) {
+            self.write(&rest[..pos])?;
+            let escaped: &[u8] = match rest[pos] {
+                b'\\' => b"\\textbackslash{}",
+                b'^' => b"\\textasciicircum{}",
+                b'~' => b"\\textasciitilde{}",
+                b'{' => b"\\{",
+                b'}' => b"\\}",
+                b'$' => b"\\$",
+                b'&' => b"\\&",
+                b'#' => b"\\#",
+                b'_' => b"\\_",
+                _ => b"\\%",
+            };
+            self.write(escaped)?;
+            rest = &rest[pos + 1..];
+        }
+        self.write(rest)
+    }
+
+    /// Write output after the owed whitespace
+    fn write(&mut self, output: &[u8]) -> io::Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+        self.write_sep()?;
+        self.writer.write_all(output)?;
+        self.at_line_start = false;
+        self.blank = false;
+        Ok(())
+    }
+
+    /// Write the owed whitespace, nothing at the start of the output
+    fn write_sep(&mut self) -> io::Result<()> {
+        let sep = mem::replace(&mut self.sep, Sep::None);
+        if sep >= Sep::Line && !self.at_line_start {
+            self.writer.write_all(b"\n")?;
+            self.at_line_start = true;
+        }
+        if sep == Sep::Paragraph && !self.blank {
+            self.writer.write_all(b"\n")?;
+            self.blank = true;
+        }
+        Ok(())
+    }
+}
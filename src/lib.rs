@@ -2,11 +2,54 @@
 //! structure like an AST but some context aware tokens with semantic meaning. This is usually
 //! enough to generate some other text based on the input like another markup language e.g. HTML.
 
+pub mod ansi;
 mod buf;
+#[cfg(feature = "markdown-import")]
+pub mod commonmark;
+pub mod conditions;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod encoding;
 mod error;
-
+pub mod event;
+pub mod format;
+pub mod gutenberg;
+pub mod html;
+pub mod include;
+pub mod json;
+pub mod latex;
+pub mod lint;
+pub mod lsp;
+pub mod macros;
+pub mod markdown;
+pub mod metadata;
+pub mod repeat;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod stream;
+pub mod template;
+pub mod text;
+pub mod tree;
+pub mod variables;
+pub mod writer;
+pub mod xml;
+
+#[cfg(feature = "serde")]
+pub use de::{from_reader, from_slice};
+pub use encoding::DecodingReader;
 pub use error::AmarkError;
 use error::ByteDisp;
+#[cfg(feature = "serde")]
+pub use error::SerdeError;
+pub use event::{Event, EventReader};
+pub use format::AmarkFormatter;
+pub use metadata::Metadata;
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_writer};
+pub use stream::{AmarkStream, TokenStream};
+pub use writer::AmarkWriter;
 
 use std::{
     fmt::Debug,
@@ -50,7 +93,7 @@ impl AmarkReader {
     pub fn parse_next<'buf, B: BufRead>(
         &'buf mut self,
         reader: &mut B,
-    ) -> AmarkResult<'buf, AmarkToken> {
+    ) -> AmarkResult<'buf, AmarkToken<'buf>> {
         self.inner.parse_next_inner(&mut self.cur_line, reader)
     }
 
@@ -60,13 +103,27 @@ impl AmarkReader {
     pub fn parse_next_get_cur_line<'buf, B: BufRead>(
         &'buf mut self,
         reader: &mut B,
-    ) -> (AmarkResult<'buf, AmarkToken>, usize) {
+    ) -> (AmarkResult<'buf, AmarkToken<'buf>>, usize) {
         (
             AmarkReaderInner::parse_next_inner(&mut self.inner, &mut self.cur_line, reader),
             self.cur_line,
         )
     }
 
+    /// Read the front matter at the start of the input, see [`metadata`]. This has to be called
+    /// before the first token is parsed, `None` is returned when the input has no front matter.
+    ///
+    /// # Errors
+    ///
+    /// This errors when reading from `reader` fails, the front matter is malformed or tokens were
+    /// parsed already.
+    pub fn read_metadata<B: BufRead>(
+        &mut self,
+        reader: &mut B,
+    ) -> AmarkResult<'static, Option<Metadata>> {
+        metadata::read(self, reader)
+    }
+
     /// Take the inner buffer for later reuse
     pub fn take_buf(self) -> Vec<u8> {
         self.inner.buf.take_storage()
@@ -76,6 +133,12 @@ impl AmarkReader {
     pub fn cur_line(&self) -> usize {
         self.cur_line
     }
+
+    /// Get the number of bytes of the input processed so far. After a token was parsed this is
+    /// the offset right behind it and the whitespace the reader skipped after it.
+    pub fn offset(&self) -> usize {
+        self.inner.buf.offset()
+    }
 }
 
 impl Default for AmarkReader {
@@ -189,15 +252,14 @@ impl AmarkReaderInner {
                             }
                             b'}' => {
                                 self.context_stack.pop();
-                                // Skip whitespace ahead
-                                loop {
-                                    if self
-                                        .buf
-                                        .next_byte()
-                                        .map_or(true, |b| b.is_ascii_whitespace())
-                                    {
-                                        break;
-                                    }
+                                // Skip the line break after the block, an empty line
+                                // following it is still reported
+                                if self
+                                    .buf
+                                    .next_byte()
+                                    .is_some_and(|b| !b.is_ascii_whitespace())
+                                {
+                                    self.buf.rewind(1);
                                 }
                                 return Ok(AmarkToken::BlockEnd);
                             }
@@ -293,7 +355,7 @@ impl AmarkReaderInner {
     /// # Errors
     ///
     /// Returns an error when EOF is encountered instead of another character
-    fn parse_escape_sequence(&mut self) -> AmarkResult<AmarkToken> {
+    fn parse_escape_sequence(&mut self) -> AmarkResult<'_, AmarkToken<'_>> {
         self.context_stack.push(Context::EscapeSequence);
         if let Some(b) = self.buf.next_byte() {
             Ok(AmarkToken::EscapeSequence(b))
@@ -309,7 +371,7 @@ impl AmarkReaderInner {
     /// # Errors
     ///
     /// Returns an error when `EOF` is encountered instead of an item name
-    fn read_item_name(buf: &mut Buf) -> AmarkResult<&[u8]> {
+    fn read_item_name(buf: &mut Buf) -> AmarkResult<'_, &[u8]> {
         let name = buf
             .take_until_rewind(
                 |b| {
@@ -431,6 +493,73 @@ impl<'buf> AmarkToken<'buf> {
     pub fn is_context_end(&self) -> bool {
         matches!(*self, Self::ParamsEnd | Self::BlockEnd | Self::ContainerEnd)
     }
+
+    /// Copy this token into an [`OwnedToken`] that doesn't borrow the reader
+    pub fn to_owned_token(&self) -> OwnedToken {
+        match *self {
+            Self::BlockStart => OwnedToken::BlockStart,
+            Self::ParamsStart => OwnedToken::ParamsStart,
+            Self::ContainerStart => OwnedToken::ContainerStart,
+            Self::BlockEnd => OwnedToken::BlockEnd,
+            Self::ParamsEnd => OwnedToken::ParamsEnd,
+            Self::ContainerEnd => OwnedToken::ContainerEnd,
+            Self::ItemEnd => OwnedToken::ItemEnd,
+            Self::EmptyLine => OwnedToken::EmptyLine,
+            Self::End => OwnedToken::End,
+            Self::ItemName(name) => OwnedToken::ItemName(name.to_vec()),
+            Self::Text(text) => OwnedToken::Text(text.to_vec()),
+            Self::EscapeSequence(b) => OwnedToken::EscapeSequence(b),
+        }
+    }
+}
+
+/// An owned version of [`AmarkToken`], used to buffer tokens beyond the next call to the reader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedToken {
+    /// See [`AmarkToken::BlockStart`]
+    BlockStart,
+    /// See [`AmarkToken::ParamsStart`]
+    ParamsStart,
+    /// See [`AmarkToken::ContainerStart`]
+    ContainerStart,
+    /// See [`AmarkToken::BlockEnd`]
+    BlockEnd,
+    /// See [`AmarkToken::ParamsEnd`]
+    ParamsEnd,
+    /// See [`AmarkToken::ContainerEnd`]
+    ContainerEnd,
+    /// See [`AmarkToken::ItemEnd`]
+    ItemEnd,
+    /// See [`AmarkToken::EmptyLine`]
+    EmptyLine,
+    /// See [`AmarkToken::End`]
+    End,
+    /// See [`AmarkToken::ItemName`]
+    ItemName(Vec<u8>),
+    /// See [`AmarkToken::Text`]
+    Text(Vec<u8>),
+    /// See [`AmarkToken::EscapeSequence`]
+    EscapeSequence(u8),
+}
+
+impl OwnedToken {
+    /// Borrow this token as an [`AmarkToken`]
+    pub fn as_token(&self) -> AmarkToken<'_> {
+        match *self {
+            Self::BlockStart => AmarkToken::BlockStart,
+            Self::ParamsStart => AmarkToken::ParamsStart,
+            Self::ContainerStart => AmarkToken::ContainerStart,
+            Self::BlockEnd => AmarkToken::BlockEnd,
+            Self::ParamsEnd => AmarkToken::ParamsEnd,
+            Self::ContainerEnd => AmarkToken::ContainerEnd,
+            Self::ItemEnd => AmarkToken::ItemEnd,
+            Self::EmptyLine => AmarkToken::EmptyLine,
+            Self::End => AmarkToken::End,
+            Self::ItemName(ref name) => AmarkToken::ItemName(name),
+            Self::Text(ref text) => AmarkToken::Text(text),
+            Self::EscapeSequence(b) => AmarkToken::EscapeSequence(b),
+        }
+    }
 }
 
 /// A stack of [`Context`] items showing where in an Amark file the parser currently is.
@@ -505,6 +634,24 @@ fn is_ascii_ident_char(byte: u8) -> bool {
     !byte.is_ascii_whitespace() && !is_ascii_context_char(byte) && byte != b';'
 }
 
+/// Check that the given name can be written as an item name
+///
+/// # Errors
+///
+/// Returns an error when the name is empty or contains a character that isn't valid in names.
+fn validate_item_name(name: &[u8]) -> AmarkResult<'static, ()> {
+    if name.is_empty() || !name.iter().all(|&b| is_ascii_ident_char(b)) {
+        return Err(AmarkError::UnexpectedInput {
+            expected: b"Item name without whitespace, ;, (, ), [, ], { or }"
+                .as_ref()
+                .into(),
+            got: name.to_vec().into(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Wether the given character will trigger a switch into an "item context" (block, container, params)
 fn is_ascii_context_char(byte: u8) -> bool {
     [b'{', b'}', b'(', b')', b'[', b']'].contains(&byte)
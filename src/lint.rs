@@ -0,0 +1,736 @@
+//! A linter for style and quality problems of documents which parse fine.
+//!
+//! Every problem is found by a [`Rule`] and reported as a [`Lint`] with the severity configured
+//! for the rule. These rules are built in:
+//!
+//! - `empty-block`: blocks and containers without any content
+//! - `deep-nesting`: blocks and containers nested deeper than 4 levels
+//! - `trailing-whitespace`: lines ending with spaces or tabs, fixable
+//! - `repeated-empty-lines`: more than one empty line in a row inside of a block, fixable
+//! - `unused-macro`: `define(name, …)` macros which are never used
+//! - `br-zero`: `br(0);` which adds no line break
+//! - `mixed-indentation`: lines indented with tabs and others with spaces, fixable
+//!
+//! The configuration is Amark source with an item per rule, `rule(severity, options…);`. The
+//! severity is `off`, `info`, `warning` or `error`, `deep-nesting` takes the maximum depth as
+//! option. [`Linter::fix`] formats a document with the fixes of all enabled rules applied.
+//!
+//! ```
+//! use amark::lint::{Linter, Severity};
+//!
+//! let mut linter = Linter::load(b"deep-nesting(error, 1);\nempty-block(off);")?;
+//! let source = b"c [\n    p {\n        Text  \n    }\n    l {}\n]\nbr(0);";
+//! let lints = linter.lint(source)?;
+//!
+//! assert_eq!(lints[0].to_string(), "Item \"p\" is nested 2 levels deep, more than 1 [deep-nesting]");
+//! assert_eq!(lints[0].severity, Severity::Error);
+//! assert_eq!((lints[1].rule, lints[1].line), ("trailing-whitespace", 3));
+//! assert_eq!(lints[3].to_string(), "br(0) adds no line break [br-zero]");
+//! assert_eq!(linter.fix(source)?, b"c [\n    p {\n        Text\n    }\n    l {}\n]\nbr(0);\n");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt::{self, Debug, Display, Formatter},
+};
+
+use crate::{
+    event::parse_count, metadata, stream::TokenStream, tree::Document, AmarkError, AmarkFormatter,
+    AmarkResult, AmarkStream, AmarkToken, ByteDisp, OwnedToken,
+};
+
+/// The name of the item defining macros, see [`macros`](crate::macros)
+const DEFINE: &[u8] = b"define";
+
+/// How serious a lint is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The rule is disabled
+    Off,
+    /// A hint
+    Info,
+    /// A problem which should be fixed
+    Warning,
+    /// A problem which has to be fixed
+    Error,
+}
+
+impl Severity {
+    /// Parse a severity of the configuration
+    fn parse(severity: &[u8]) -> AmarkResult<'static, Self> {
+        Ok(match severity {
+            b"off" => Self::Off,
+            b"info" => Self::Info,
+            b"warning" => Self::Warning,
+            b"error" => Self::Error,
+            _ => return Err(unexpected(b"off, info, warning or error", severity)),
+        })
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Self::Off => "off",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A problem found by a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// The name of the rule
+    pub rule: &'static str,
+    /// The severity configured for the rule
+    pub severity: Severity,
+    /// The line of the problem
+    pub line: usize,
+    /// What is wrong
+    pub msg: String,
+    /// Whether [`Linter::fix`] fixes the problem
+    pub fixable: bool,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.msg, self.rule)
+    }
+}
+
+/// Where in the document a rule is called and where it reports lints
+#[derive(Debug)]
+pub struct Context<'a> {
+    /// The state of the document
+    state: &'a State,
+    /// The rule which is called
+    rule: &'static str,
+    /// The severity of the rule
+    severity: Severity,
+    /// Whether the rule can fix its lints
+    fixable: bool,
+    /// The lints found so far
+    lints: &'a mut Vec<Lint>,
+}
+
+impl<'a> Context<'a> {
+    /// The line the token is on or the number of the source line
+    pub fn line(&self) -> usize {
+        self.state.line
+    }
+
+    /// The number of open blocks and containers, a started one included
+    pub fn depth(&self) -> usize {
+        self.state.depth
+    }
+
+    /// Whether the token is part of a parameter list
+    pub fn in_params(&self) -> bool {
+        self.state.params > 0
+    }
+
+    /// The name of the last item
+    pub fn item(&self) -> &[u8] {
+        &self.state.item
+    }
+
+    /// Report a lint on the current line
+    pub fn report(&mut self, msg: impl Into<String>) {
+        self.report_at(self.state.line, msg);
+    }
+
+    /// Report a lint on the given line
+    pub fn report_at(&mut self, line: usize, msg: impl Into<String>) {
+        self.lints.push(Lint {
+            rule: self.rule,
+            severity: self.severity,
+            line,
+            msg: msg.into(),
+            fixable: self.fixable,
+        });
+    }
+}
+
+/// A check of the linter. Rules see every token and every source line of a document, they are
+/// reset before each document.
+pub trait Rule: Debug {
+    /// The name of the rule in the configuration and in lints
+    fn name(&self) -> &'static str;
+
+    /// The severity unless the configuration sets one
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Whether [`Rule::fix`] fixes the lints of this rule. Rules whose lints are fixed by
+    /// formatting alone don't need to change any token.
+    fn fixable(&self) -> bool {
+        false
+    }
+
+    /// Apply the options of the configuration after the severity
+    ///
+    /// # Errors
+    ///
+    /// This errors when the options are invalid, by default for any option.
+    fn configure(&mut self, options: &[Vec<u8>]) -> AmarkResult<'static, ()> {
+        match options.first() {
+            Some(option) => Err(unexpected(b"No options for the rule", option)),
+            None => Ok(()),
+        }
+    }
+
+    /// Forget everything about the last document
+    fn reset(&mut self) {}
+
+    /// Check a token of the document
+    fn check_token(&mut self, _tok: &AmarkToken<'_>, _cx: &mut Context<'_>) {}
+
+    /// Check a line of the source without its line break
+    fn check_line(&mut self, _line: &[u8], _cx: &mut Context<'_>) {}
+
+    /// Report what is only known at the end of the document
+    fn finish(&mut self, _cx: &mut Context<'_>) {}
+
+    /// Fix a token before it is formatted, `None` drops it
+    fn fix(&mut self, tok: OwnedToken, _cx: &Context<'_>) -> Option<OwnedToken> {
+        Some(tok)
+    }
+}
+
+/// The state of the document rules are called in
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// See [`Context::line`]
+    line: usize,
+    /// See [`Context::depth`]
+    depth: usize,
+    /// The number of open parameter lists
+    params: usize,
+    /// See [`Context::item`]
+    item: Vec<u8>,
+    /// Whether the last token ended a block or container
+    closed: bool,
+}
+
+impl State {
+    /// Update the state for the next token on the given line
+    fn advance(&mut self, tok: &OwnedToken, line: usize) {
+        self.line = line;
+        if self.closed {
+            self.depth -= 1;
+            self.closed = false;
+        }
+        match *tok {
+            OwnedToken::ItemName(ref name) => name.clone_into(&mut self.item),
+            OwnedToken::BlockStart | OwnedToken::ContainerStart => self.depth += 1,
+            OwnedToken::BlockEnd | OwnedToken::ContainerEnd => self.closed = true,
+            OwnedToken::ParamsStart => self.params += 1,
+            OwnedToken::ParamsEnd => self.params -= 1,
+            _ => (),
+        }
+    }
+}
+
+/// A rule with its configured severity
+#[derive(Debug)]
+struct Entry {
+    /// The rule
+    rule: Box<dyn Rule>,
+    /// The configured severity
+    severity: Severity,
+}
+
+impl Entry {
+    /// The context for calling the rule
+    fn context<'a>(&self, state: &'a State, lints: &'a mut Vec<Lint>) -> Context<'a> {
+        Context {
+            state,
+            rule: self.rule.name(),
+            severity: self.severity,
+            fixable: self.rule.fixable(),
+            lints,
+        }
+    }
+}
+
+/// Lints documents with a set of rules, see the [module docs](self)
+#[derive(Debug)]
+pub struct Linter {
+    /// The rules in the order they are called
+    rules: Vec<Entry>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::empty()
+            .rule(EmptyBlock::default())
+            .rule(DeepNesting::default())
+            .rule(TrailingWhitespace)
+            .rule(RepeatedEmptyLines::default())
+            .rule(UnusedMacro::default())
+            .rule(BrZero::default())
+            .rule(MixedIndentation::default())
+    }
+}
+
+impl Linter {
+    /// Create a new linter with the built in rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new linter without any rules
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Load the configuration in Amark source for the built in rules, see the
+    /// [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed or names an unknown rule, a severity or
+    /// options are invalid.
+    pub fn load(config: &[u8]) -> AmarkResult<'static, Self> {
+        let mut linter = Self::new();
+        for element in Document::parse(config)?.elements() {
+            let entry = linter
+                .rules
+                .iter_mut()
+                .find(|entry| entry.rule.name().as_bytes() == element.name());
+            let (Some(entry), false, [severity, options @ ..]) =
+                (entry, element.has_body(), element.item.params())
+            else {
+                return Err(unexpected(
+                    b"rule(severity, options...); for a known rule",
+                    element.name(),
+                ));
+            };
+            entry.severity = Severity::parse(severity)?;
+            entry.rule.configure(options)?;
+        }
+        Ok(linter)
+    }
+
+    /// Add a rule with its default severity
+    pub fn rule(mut self, rule: impl Rule + 'static) -> Self {
+        self.rules.push(Entry {
+            severity: rule.default_severity(),
+            rule: Box::new(rule),
+        });
+        self
+    }
+
+    /// Set the severity of the rule with the given name, unknown names are ignored
+    pub fn severity(mut self, name: &str, severity: Severity) -> Self {
+        for entry in self.rules.iter_mut().filter(|e| e.rule.name() == name) {
+            entry.severity = severity;
+        }
+        self
+    }
+
+    /// Lint the given source and return the lints in the order of their lines, front matter is
+    /// not linted
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed.
+    pub fn lint(&mut self, source: &[u8]) -> AmarkResult<'static, Vec<Lint>> {
+        let mut lints = Vec::new();
+        let mut state = State::default();
+        self.reset();
+
+        let mut stream = AmarkStream::new(source);
+        stream.read_metadata()?;
+        loop {
+            let tok = stream.next_token()?.to_owned_token();
+            state.advance(&tok, stream.cur_line());
+            for entry in self.enabled() {
+                let mut cx = entry.context(&state, &mut lints);
+                entry.rule.check_token(&tok.as_token(), &mut cx);
+            }
+            if tok == OwnedToken::End {
+                break;
+            }
+        }
+
+        let front_lines = metadata::front_matter(source)?
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        for (i, line) in source.split(|&b| b == b'\n').enumerate().skip(front_lines) {
+            state.line = i + 1;
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            for entry in self.enabled() {
+                entry
+                    .rule
+                    .check_line(line, &mut entry.context(&state, &mut lints));
+            }
+        }
+
+        for entry in self.enabled() {
+            entry.rule.finish(&mut entry.context(&state, &mut lints));
+        }
+        lints.sort_by_key(|lint| lint.line);
+        Ok(lints)
+    }
+
+    /// Format the given source with the fixes of all enabled rules applied, front matter is kept
+    /// as it is
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed.
+    pub fn fix(&mut self, source: &[u8]) -> AmarkResult<'static, Vec<u8>> {
+        self.reset();
+        let mut stream = AmarkStream::new(source);
+        stream.read_metadata()?;
+        let fixer = Fixer {
+            stream,
+            rules: &mut self.rules,
+            state: State::default(),
+            current: OwnedToken::End,
+        };
+        let mut out = Vec::with_capacity(source.len());
+        out.extend_from_slice(metadata::front_matter(source)?);
+        AmarkFormatter::new().format(fixer, &mut out)?;
+        Ok(out)
+    }
+
+    /// Reset all rules for the next document
+    fn reset(&mut self) {
+        for entry in &mut self.rules {
+            entry.rule.reset();
+        }
+    }
+
+    /// The rules which aren't turned off
+    fn enabled(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.rules
+            .iter_mut()
+            .filter(|entry| entry.severity != Severity::Off)
+    }
+}
+
+/// A stream adapter passing every token through the fixes of the enabled rules
+#[derive(Debug)]
+struct Fixer<'r, S> {
+    /// The stream of the document
+    stream: S,
+    /// The rules of the linter
+    rules: &'r mut [Entry],
+    /// The state of the document
+    state: State,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+impl<'r, S: TokenStream> TokenStream for Fixer<'r, S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        'tokens: loop {
+            let mut tok = self.stream.next_token()?.to_owned_token();
+            self.state.advance(&tok, self.stream.cur_line());
+
+            let mut lints = Vec::new();
+            for entry in self.rules.iter_mut() {
+                if entry.severity == Severity::Off || !entry.rule.fixable() {
+                    continue;
+                }
+                let cx = entry.context(&self.state, &mut lints);
+                match entry.rule.fix(tok, &cx) {
+                    Some(fixed) => tok = fixed,
+                    None => continue 'tokens,
+                }
+            }
+            self.current = tok;
+            return Ok(self.current.as_token());
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
+
+/// Reports blocks and containers without any content
+#[derive(Debug, Clone, Default)]
+pub struct EmptyBlock {
+    /// Whether the last token started a block or container
+    started: bool,
+}
+
+impl Rule for EmptyBlock {
+    fn name(&self) -> &'static str {
+        "empty-block"
+    }
+
+    fn reset(&mut self) {
+        self.started = false;
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        if self.started && matches!(tok, AmarkToken::BlockEnd | AmarkToken::ContainerEnd) {
+            cx.report(format!("Item {} is empty", ByteDisp(&cx.item())));
+        }
+        self.started = matches!(tok, AmarkToken::BlockStart | AmarkToken::ContainerStart);
+    }
+}
+
+/// Reports blocks and containers nested deeper than the maximum
+#[derive(Debug, Clone)]
+pub struct DeepNesting {
+    /// The maximum depth
+    max: usize,
+}
+
+impl DeepNesting {
+    /// The maximum depth unless it is configured
+    pub const DEFAULT_MAX: usize = 4;
+
+    /// Create the rule with the given maximum depth
+    pub fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl Default for DeepNesting {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX)
+    }
+}
+
+impl Rule for DeepNesting {
+    fn name(&self) -> &'static str {
+        "deep-nesting"
+    }
+
+    fn configure(&mut self, options: &[Vec<u8>]) -> AmarkResult<'static, ()> {
+        match *options {
+            [] => Ok(()),
+            [ref max] => {
+                self.max = parse_count(max).ok_or_else(|| unexpected(b"A maximum depth", max))?;
+                Ok(())
+            }
+            [_, ref option, ..] => Err(unexpected(b"Only the maximum depth", option)),
+        }
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        let started = matches!(tok, AmarkToken::BlockStart | AmarkToken::ContainerStart);
+        if started && cx.depth() > self.max {
+            let msg = format!(
+                "Item {} is nested {} levels deep, more than {}",
+                ByteDisp(&cx.item()),
+                cx.depth(),
+                self.max
+            );
+            cx.report(msg);
+        }
+    }
+}
+
+/// Reports lines ending with whitespace and trims the text of blocks
+#[derive(Debug, Clone, Default)]
+pub struct TrailingWhitespace;
+
+impl Rule for TrailingWhitespace {
+    fn name(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+
+    fn fixable(&self) -> bool {
+        true
+    }
+
+    fn check_line(&mut self, line: &[u8], cx: &mut Context<'_>) {
+        if line.ends_with(b" ") || line.ends_with(b"\t") {
+            cx.report("Trailing whitespace");
+        }
+    }
+
+    fn fix(&mut self, tok: OwnedToken, cx: &Context<'_>) -> Option<OwnedToken> {
+        match tok {
+            OwnedToken::Text(mut text) if !cx.in_params() => {
+                text.truncate(text.trim_ascii_end().len());
+                (!text.is_empty()).then_some(OwnedToken::Text(text))
+            }
+            tok => Some(tok),
+        }
+    }
+}
+
+/// Reports empty lines following another one and drops them
+#[derive(Debug, Clone, Default)]
+pub struct RepeatedEmptyLines {
+    /// Whether the last token was an empty line
+    empty: bool,
+}
+
+impl Rule for RepeatedEmptyLines {
+    fn name(&self) -> &'static str {
+        "repeated-empty-lines"
+    }
+
+    fn fixable(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.empty = false;
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        let empty = *tok == AmarkToken::EmptyLine;
+        if empty && self.empty {
+            cx.report("More than one empty line in a row");
+        }
+        self.empty = empty;
+    }
+
+    fn fix(&mut self, tok: OwnedToken, _cx: &Context<'_>) -> Option<OwnedToken> {
+        let repeated = tok == OwnedToken::EmptyLine && self.empty;
+        self.empty = tok == OwnedToken::EmptyLine;
+        (!repeated).then_some(tok)
+    }
+}
+
+/// Reports macros which are defined but never used
+#[derive(Debug, Clone, Default)]
+pub struct UnusedMacro {
+    /// Whether the name of a definition is expected next
+    defining: bool,
+    /// The names of the defined macros with the lines of their definitions
+    defined: Vec<(Vec<u8>, usize)>,
+    /// The names of all items
+    used: HashSet<Vec<u8>>,
+}
+
+impl Rule for UnusedMacro {
+    fn name(&self) -> &'static str {
+        "unused-macro"
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        match *tok {
+            AmarkToken::ItemName(name) => {
+                self.defining = name == DEFINE;
+                self.used.insert(name.to_vec());
+            }
+            AmarkToken::Text(text) if self.defining && cx.in_params() => {
+                let name = text.split(|&b| b == b',').next().unwrap_or_default();
+                self.defined.push((name.trim_ascii().to_vec(), cx.line()));
+                self.defining = false;
+            }
+            AmarkToken::ParamsStart => (),
+            _ => self.defining = false,
+        }
+    }
+
+    fn finish(&mut self, cx: &mut Context<'_>) {
+        for (name, line) in &self.defined {
+            if !self.used.contains(name) {
+                cx.report_at(*line, format!("Macro {} is never used", ByteDisp(name)));
+            }
+        }
+    }
+}
+
+/// Reports `br(0);`, which adds no line break
+#[derive(Debug, Clone, Default)]
+pub struct BrZero {
+    /// The line of a `br` item and its parameters so far while they are read
+    br: Option<(usize, Vec<u8>)>,
+}
+
+impl Rule for BrZero {
+    fn name(&self) -> &'static str {
+        "br-zero"
+    }
+
+    fn reset(&mut self) {
+        self.br = None;
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        match (tok, self.br.as_mut()) {
+            (&AmarkToken::ItemName(b"br"), _) => self.br = Some((cx.line(), Vec::new())),
+            (&AmarkToken::ParamsStart, Some(_)) => (),
+            (&AmarkToken::Text(text), Some((_, params))) => params.extend_from_slice(text),
+            (&AmarkToken::ParamsEnd, Some(&mut (line, ref params))) => {
+                if parse_count(params) == Some(0) {
+                    cx.report_at(line, "br(0) adds no line break");
+                }
+                self.br = None;
+            }
+            _ => self.br = None,
+        }
+    }
+}
+
+/// Reports lines indented differently than the first indented line or with tabs and spaces
+#[derive(Debug, Clone, Default)]
+pub struct MixedIndentation {
+    /// Whether the first indented line uses tabs and its number
+    first: Option<(bool, usize)>,
+}
+
+impl Rule for MixedIndentation {
+    fn name(&self) -> &'static str {
+        "mixed-indentation"
+    }
+
+    fn fixable(&self) -> bool {
+        // The formatter indents every line the same way
+        true
+    }
+
+    fn reset(&mut self) {
+        self.first = None;
+    }
+
+    fn check_line(&mut self, line: &[u8], cx: &mut Context<'_>) {
+        let indent = line.len() - line.trim_ascii_start().len();
+        if indent == 0 || indent == line.len() {
+            return;
+        }
+
+        let indent = &line[..indent];
+        let tabs = indent.contains(&b'\t');
+        if tabs && indent.contains(&b' ') {
+            cx.report("Indented with tabs and spaces");
+            return;
+        }
+        match self.first {
+            None => self.first = Some((tabs, cx.line())),
+            Some((first_tabs, first)) if first_tabs != tabs => {
+                let (used, other) = if tabs {
+                    ("tabs", "spaces")
+                } else {
+                    ("spaces", "tabs")
+                };
+                cx.report(format!(
+                    "Indented with {}, line {} is indented with {}",
+                    used, first, other
+                ));
+            }
+            Some(_) => (),
+        }
+    }
+}
+
+/// Create an error for an invalid configuration
+fn unexpected(expected: &'static [u8], got: &[u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got.to_vec()),
+    }
+}
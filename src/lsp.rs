@@ -0,0 +1,846 @@
+//! A Language Server Protocol server for Amark documents, run by the `amark-lsp` binary.
+//!
+//! The server speaks JSON-RPC with `Content-Length` headers over any reader and writer, usually
+//! stdin and stdout. Documents are synced as a whole and the server provides:
+//!
+//! - diagnostics for errors of the reader and items the schema doesn't allow
+//! - document symbols with the tree of items
+//! - folding ranges for blocks and containers spanning several lines
+//! - go to definition of macros and of files in `include(path);`
+//! - formatting with the [`AmarkFormatter`]
+//! - completion of the item names the schema declares
+//!
+//! The schema is set with [`Server::schema`] or as a path in the `schema` member of the
+//! `initializationOptions`.
+//!
+//! ```
+//! use amark::lsp::Server;
+//!
+//! let body = br#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#;
+//! let mut input = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+//! input.extend_from_slice(body);
+//! input.extend_from_slice(b"Content-Length: 33\r\n\r\n{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}");
+//!
+//! let mut output = Vec::new();
+//! let clean = Server::new().run(input.as_slice(), &mut output)?;
+//!
+//! assert!(clean);
+//! assert_eq!(output, b"Content-Length: 38\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    io::{BufRead, Read, Write},
+    path::{Path, PathBuf},
+    str,
+};
+
+use crate::{
+    cst::{self, Body, Cst, CstToken, Group},
+    event::{parse_params, ItemKind},
+    format::Indent,
+    json::{self, Value},
+    metadata,
+    schema::{Schema, Validator},
+    stream::TokenStream,
+    AmarkError, AmarkFormatter, AmarkResult, AmarkStream, AmarkToken, OwnedToken,
+};
+
+/// The name of the item defining macros, see [`macros`](crate::macros)
+const DEFINE: &[u8] = b"define";
+
+/// The name of the item including files, see [`include`](crate::include)
+const INCLUDE: &[u8] = b"include";
+
+/// The JSON-RPC error code for messages which aren't JSON
+const PARSE_ERROR: i64 = -32700;
+
+/// The JSON-RPC error code for requests after the shutdown
+const INVALID_REQUEST: i64 = -32600;
+
+/// The JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The JSON-RPC error code for requests about unknown documents
+const INVALID_PARAMS: i64 = -32602;
+
+/// The diagnostic severity of errors, which make the document invalid
+const SEVERITY_ERROR: usize = 1;
+
+/// The diagnostic severity of warnings, like items the schema doesn't allow
+const SEVERITY_WARNING: usize = 2;
+
+/// The type of error messages shown to the user
+const MESSAGE_ERROR: usize = 1;
+
+/// The document sync kind sending the whole document on every change
+const SYNC_FULL: usize = 1;
+
+/// The completion item kind of item names
+const COMPLETION_KEYWORD: usize = 14;
+
+/// The symbol kind of containers
+const SYMBOL_NAMESPACE: usize = 3;
+
+/// The symbol kind of bare items
+const SYMBOL_CONSTANT: usize = 14;
+
+/// The symbol kind of blocks
+const SYMBOL_STRING: usize = 15;
+
+/// An error response with its code and message
+type RequestError = (i64, String);
+
+/// A language server for Amark documents, see the [module docs](self)
+#[derive(Debug, Default)]
+pub struct Server {
+    /// The source of the open documents by URI
+    documents: HashMap<String, Vec<u8>>,
+    /// The schema for diagnostics and completion
+    schema: Option<Schema>,
+    /// Whether the client asked the server to shut down
+    shutdown: bool,
+}
+
+impl Server {
+    /// Create a new server without a schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check documents against the given schema and complete its item names
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Answer the messages of the reader until the client exits or the input ends. Returns
+    /// whether the client asked to shut down before, which is a clean exit.
+    ///
+    /// # Errors
+    ///
+    /// This errors when reading or writing fails or a message has no valid header.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        mut reader: R,
+        mut writer: W,
+    ) -> AmarkResult<'static, bool> {
+        while let Some(body) = read_message(&mut reader)? {
+            let mut replies = Vec::new();
+            let exit = match json::parse(&body) {
+                Ok(message) => self.handle(&message, &mut replies),
+                Err(e) => {
+                    replies.push(response(&Value::Null, Err((PARSE_ERROR, e.to_string()))));
+                    false
+                }
+            };
+
+            for reply in replies {
+                let mut body = Vec::new();
+                reply.write(&mut body)?;
+                write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+                writer.write_all(&body)?;
+            }
+            writer.flush()?;
+
+            if exit {
+                return Ok(self.shutdown);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Handle a message and collect the messages to send back. Returns whether the client exits.
+    fn handle(&mut self, message: &Value, replies: &mut Vec<Value>) -> bool {
+        let method = message
+            .member(b"method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.member(b"params").unwrap_or(&Value::Null);
+
+        match message.member(b"id") {
+            _ if method == b"exit" => return true,
+            Some(id) => {
+                let result = if self.shutdown {
+                    Err((INVALID_REQUEST, String::from("The server is shut down")))
+                } else {
+                    self.request(method, params, replies)
+                };
+                replies.push(response(id, result));
+            }
+            None => self.notification(method, params, replies),
+        }
+        false
+    }
+
+    /// Answer a request
+    fn request(
+        &mut self,
+        method: &[u8],
+        params: &Value,
+        replies: &mut Vec<Value>,
+    ) -> Result<Value, RequestError> {
+        Ok(match method {
+            b"initialize" => self.initialize(params, replies),
+            b"shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            b"textDocument/documentSymbol" => {
+                let (_, source) = self.document(params)?;
+                let lines = Lines::new(source);
+                let items = outline(source).unwrap_or_default();
+                Value::Array(items.iter().map(|item| symbol(item, &lines)).collect())
+            }
+            b"textDocument/foldingRange" => {
+                let (_, source) = self.document(params)?;
+                let items = outline(source);
+                let mut ranges = Vec::new();
+                folding_ranges(&items.unwrap_or_default(), &Lines::new(source), &mut ranges);
+                Value::Array(ranges)
+            }
+            b"textDocument/definition" => {
+                let (uri, source) = self.document(params)?;
+                definition(uri, source, params).unwrap_or(Value::Null)
+            }
+            b"textDocument/formatting" => {
+                let (_, source) = self.document(params)?;
+                formatting(source, params)
+            }
+            b"textDocument/completion" => {
+                let mut names: Vec<_> = self.schema.iter().flat_map(Schema::names).collect();
+                names.sort_unstable();
+                let items = names.into_iter().map(|name| {
+                    object([
+                        ("label", string(name)),
+                        ("kind", number(COMPLETION_KEYWORD)),
+                    ])
+                });
+                Value::Array(items.collect())
+            }
+            _ => {
+                let method = String::from_utf8_lossy(method);
+                return Err((METHOD_NOT_FOUND, format!("Unknown method {}", method)));
+            }
+        })
+    }
+
+    /// Handle a notification
+    fn notification(&mut self, method: &[u8], params: &Value, replies: &mut Vec<Value>) {
+        let document = params.member(b"textDocument");
+        let Some(uri) = document
+            .and_then(|d| d.member(b"uri"))
+            .and_then(Value::as_str)
+        else {
+            return;
+        };
+        let uri = String::from_utf8_lossy(uri).into_owned();
+
+        let text = match method {
+            b"textDocument/didOpen" => document.and_then(|d| d.member(b"text")),
+            // The whole document is synced, the last change has all of it
+            b"textDocument/didChange" => match params.member(b"contentChanges") {
+                Some(Value::Array(changes)) => changes.last().and_then(|c| c.member(b"text")),
+                _ => None,
+            },
+            b"textDocument/didClose" => {
+                self.documents.remove(&uri);
+                replies.push(publish_diagnostics(&uri, Vec::new()));
+                return;
+            }
+            _ => return,
+        };
+        if let Some(text) = text.and_then(Value::as_str) {
+            replies.push(publish_diagnostics(&uri, self.diagnostics(text)));
+            self.documents.insert(uri, text.to_vec());
+        }
+    }
+
+    /// Answer the initialize request and load the schema of the initialization options
+    fn initialize(&mut self, params: &Value, replies: &mut Vec<Value>) -> Value {
+        let path = params
+            .member(b"initializationOptions")
+            .and_then(|options| options.member(b"schema"))
+            .and_then(Value::as_str);
+        if let Some(path) = path {
+            let path = String::from_utf8_lossy(path).into_owned();
+            match fs::read(&path)
+                .map_err(AmarkError::from)
+                .and_then(|s| Schema::load(&s))
+            {
+                Ok(schema) => self.schema = Some(schema),
+                Err(e) => {
+                    let message = format!("The schema {} can't be loaded: {}", path, e);
+                    replies.push(notification(
+                        "window/showMessage",
+                        object([
+                            ("type", number(MESSAGE_ERROR)),
+                            ("message", string(message)),
+                        ]),
+                    ));
+                }
+            }
+        }
+
+        let capabilities = object([
+            ("textDocumentSync", number(SYNC_FULL)),
+            ("documentSymbolProvider", Value::Bool(true)),
+            ("foldingRangeProvider", Value::Bool(true)),
+            ("definitionProvider", Value::Bool(true)),
+            ("documentFormattingProvider", Value::Bool(true)),
+            (
+                "completionProvider",
+                object([("triggerCharacters", Value::Array(vec![string("@")]))]),
+            ),
+        ]);
+        let info = object([
+            ("name", string("amark-lsp")),
+            ("version", string(env!("CARGO_PKG_VERSION"))),
+        ]);
+        object([("capabilities", capabilities), ("serverInfo", info)])
+    }
+
+    /// The URI and source of the document a request is about
+    fn document(&self, params: &Value) -> Result<(&str, &[u8]), RequestError> {
+        let uri = params
+            .member(b"textDocument")
+            .and_then(|document| document.member(b"uri"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let uri = String::from_utf8_lossy(uri);
+        match self.documents.get_key_value(uri.as_ref()) {
+            Some((uri, source)) => Ok((uri, source)),
+            None => Err((INVALID_PARAMS, format!("Unknown document {}", uri))),
+        }
+    }
+
+    /// The diagnostics of a document, the first error of the reader or the validator or the
+    /// schema violations
+    fn diagnostics(&self, source: &[u8]) -> Vec<Value> {
+        let lines = Lines::new(source);
+        let error = |line: usize, error: AmarkError| {
+            // Errors at the end of the input are on the last line
+            let line = line.saturating_sub(1).min(lines.starts.len() - 1);
+            vec![diagnostic(&lines, line, SEVERITY_ERROR, error.to_string())]
+        };
+
+        let mut stream = AmarkStream::new(source);
+        let read_error = stream.read_metadata().err().or_else(|| loop {
+            match stream.next_token() {
+                Ok(AmarkToken::End) => break None,
+                Ok(_) => (),
+                Err(e) => break Some(e),
+            }
+        });
+        if let Some(e) = read_error {
+            return error(stream.cur_line(), e);
+        }
+
+        let Some(ref schema) = self.schema else {
+            return Vec::new();
+        };
+        // Items can still be malformed for the validator, like `p(a)(b);`
+        let mut stream = AmarkStream::new(source);
+        let validated = stream
+            .read_metadata()
+            .and_then(|_| Validator::new(schema).validate(&mut stream));
+        match validated {
+            Ok(violations) => violations
+                .iter()
+                .map(|v| {
+                    let line = v.line.saturating_sub(1);
+                    diagnostic(&lines, line, SEVERITY_WARNING, v.to_string())
+                })
+                .collect(),
+            Err(e) => error(stream.cur_line(), e),
+        }
+    }
+}
+
+/// Read the body of the next message, `None` at the end of the input
+fn read_message<R: BufRead>(reader: &mut R) -> AmarkResult<'static, Option<Vec<u8>>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(AmarkError::UnexpectedEof {
+                    expected: Cow::Borrowed(b"The body of a message"),
+                }),
+            };
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(AmarkError::UnexpectedInput {
+            expected: Cow::Borrowed(b"A Content-Length header"),
+            got: Cow::Owned(line.into_bytes()),
+        });
+    };
+    // The length is up to the client, the body only grows as far as the input goes
+    let mut body = Vec::new();
+    reader.take(length as u64).read_to_end(&mut body)?;
+    if body.len() < length {
+        return Err(AmarkError::UnexpectedEof {
+            expected: Cow::Borrowed(b"The body of a message"),
+        });
+    }
+    Ok(Some(body))
+}
+
+/// An item of a document with the offsets of its source
+#[derive(Debug)]
+struct Outline {
+    /// The name of the item
+    name: Vec<u8>,
+    /// The parameters of the item
+    params: Vec<Vec<u8>>,
+    /// The form of the item
+    kind: ItemKind,
+    /// The start and end of the name
+    name_span: (usize, usize),
+    /// The end of the name and parameters
+    head_end: usize,
+    /// The end of the whole item
+    end: usize,
+    /// The items inside of a block or container
+    children: Vec<Outline>,
+}
+
+/// The items of a document with their offsets, front matter is parsed as blank lines
+fn outline(source: &[u8]) -> AmarkResult<'static, Vec<Outline>> {
+    let source = metadata::blank_front_matter(source)?;
+    let cst = Cst::parse(&source)?;
+    let mut walker = Walker {
+        offset: 0,
+        len: source.len(),
+    };
+    Ok(walker.nodes(cst.children()))
+}
+
+/// Walks the tokens of a tree in source order and keeps track of their offsets
+struct Walker {
+    /// The offset after the last token
+    offset: usize,
+    /// The length of the source
+    len: usize,
+}
+
+impl Walker {
+    /// Pass a token and return the start and end of its source without trivia
+    fn token(&mut self, tok: &CstToken) -> (usize, usize) {
+        let start = self.offset + tok.leading().len();
+        let end = start + tok.raw().len();
+        self.offset = end + tok.trailing().len();
+        (start.min(self.len), end.min(self.len))
+    }
+
+    /// Pass a group and return the tokens between its delimiters
+    fn group(&mut self, group: &Group) -> Vec<OwnedToken> {
+        self.token(group.open());
+        let mut tokens = Vec::new();
+        for child in group.children() {
+            match *child {
+                cst::Node::Token(ref tok) => {
+                    self.token(tok);
+                    tokens.push(tok.token().clone());
+                }
+                cst::Node::Escape {
+                    ref seq,
+                    ref params,
+                } => {
+                    self.token(seq);
+                    tokens.push(seq.token().clone());
+                    if let Some(ref params) = *params {
+                        tokens.push(params.open().token().clone());
+                        tokens.extend(self.group(params));
+                        tokens.push(params.close().token().clone());
+                    }
+                }
+                cst::Node::Item(_) => unreachable!("Groups of parameters hold no items"),
+            }
+        }
+        self.token(group.close());
+        tokens
+    }
+
+    /// Pass the nodes and return their items
+    fn nodes(&mut self, nodes: &[cst::Node]) -> Vec<Outline> {
+        let mut items = Vec::new();
+        for node in nodes {
+            match *node {
+                cst::Node::Item(ref node) => {
+                    let mut item = Outline {
+                        name: node.name().to_vec(),
+                        params: Vec::new(),
+                        kind: ItemKind::Bare,
+                        name_span: self.token(node.name_token()),
+                        head_end: 0,
+                        end: 0,
+                        children: Vec::new(),
+                    };
+                    for (i, group) in node.params().iter().enumerate() {
+                        let tokens = self.group(group);
+                        if i == 0 {
+                            item.params = parse_params(tokens).unwrap_or_default();
+                        }
+                    }
+                    item.head_end = self.offset;
+                    match *node.body() {
+                        Body::End(ref tok) => item.end = self.token(tok).1,
+                        Body::Block(ref group) | Body::Container(ref group) => {
+                            item.kind = match *node.body() {
+                                Body::Block(_) => ItemKind::Block,
+                                _ => ItemKind::Container,
+                            };
+                            self.token(group.open());
+                            item.children = self.nodes(group.children());
+                            item.end = self.token(group.close()).1;
+                        }
+                    }
+                    items.push(item);
+                }
+                cst::Node::Escape {
+                    ref seq,
+                    ref params,
+                } => {
+                    self.token(seq);
+                    if let Some(ref params) = *params {
+                        self.group(params);
+                    }
+                }
+                cst::Node::Token(ref tok) => {
+                    self.token(tok);
+                }
+            }
+        }
+        items
+    }
+}
+
+/// The start offsets of the lines of a source to convert offsets to positions and back
+struct Lines<'a> {
+    /// The source
+    source: &'a [u8],
+    /// The offset every line starts at
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    /// Index the lines of the source
+    fn new(source: &'a [u8]) -> Self {
+        let breaks = source.iter().enumerate().filter(|&(_, &b)| b == b'\n');
+        let starts = [0].into_iter().chain(breaks.map(|(i, _)| i + 1)).collect();
+        Self { source, starts }
+    }
+
+    /// The source of a line without its line break
+    fn line(&self, line: usize) -> &'a [u8] {
+        let Some(&start) = self.starts.get(line) else {
+            return &[];
+        };
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(self.source.len(), |&e| e - 1);
+        let line = &self.source[start..end];
+        line.strip_suffix(b"\r").unwrap_or(line)
+    }
+
+    /// The position of an offset, characters are counted in UTF-16 code units
+    fn position(&self, offset: usize) -> Value {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let before = &self.source[self.starts[line]..offset];
+        position(line, utf16_len(before))
+    }
+
+    /// The offset of a position, positions beyond a line are at its end
+    fn offset(&self, line: usize, character: usize) -> usize {
+        let Some(&start) = self.starts.get(line) else {
+            return self.source.len();
+        };
+        let text = String::from_utf8_lossy(self.line(line));
+        let mut units = 0;
+        for (i, c) in text.char_indices() {
+            if units >= character {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        start + text.len()
+    }
+
+    /// The range of the given offsets
+    fn range(&self, start: usize, end: usize) -> Value {
+        object([("start", self.position(start)), ("end", self.position(end))])
+    }
+}
+
+/// The number of UTF-16 code units of the source
+fn utf16_len(source: &[u8]) -> usize {
+    String::from_utf8_lossy(source).encode_utf16().count()
+}
+
+/// The document symbol of an item
+fn symbol(item: &Outline, lines: &Lines<'_>) -> Value {
+    let kind = match item.kind {
+        ItemKind::Bare => SYMBOL_CONSTANT,
+        ItemKind::Block => SYMBOL_STRING,
+        ItemKind::Container => SYMBOL_NAMESPACE,
+    };
+    let detail = item
+        .params
+        .iter()
+        .map(|param| String::from_utf8_lossy(param))
+        .collect::<Vec<_>>()
+        .join(", ");
+    object([
+        ("name", string(&item.name)),
+        ("detail", string(detail)),
+        ("kind", number(kind)),
+        ("range", lines.range(item.name_span.0, item.end)),
+        (
+            "selectionRange",
+            lines.range(item.name_span.0, item.name_span.1),
+        ),
+        (
+            "children",
+            Value::Array(item.children.iter().map(|c| symbol(c, lines)).collect()),
+        ),
+    ])
+}
+
+/// Collect the folding ranges of blocks and containers spanning several lines
+fn folding_ranges(items: &[Outline], lines: &Lines<'_>, ranges: &mut Vec<Value>) {
+    for item in items {
+        let start = lines.starts.partition_point(|&s| s <= item.name_span.0) - 1;
+        let end = lines.starts.partition_point(|&s| s < item.end) - 1;
+        if item.kind != ItemKind::Bare && end > start {
+            ranges.push(object([
+                ("startLine", number(start)),
+                ("endLine", number(end)),
+            ]));
+        }
+        folding_ranges(&item.children, lines, ranges);
+    }
+}
+
+/// The location of the macro or included file of the item at the position of a request
+fn definition(uri: &str, source: &[u8], params: &Value) -> Option<Value> {
+    let lines = Lines::new(source);
+    let cursor = params.member(b"position")?;
+    let offset = lines.offset(
+        cursor.member(b"line")?.as_usize()?,
+        cursor.member(b"character")?.as_usize()?,
+    );
+    let items = outline(source).ok()?;
+    let item = find_item(&items, &|item| {
+        (item.name_span.0..=item.head_end).contains(&offset)
+    })?;
+
+    if item.name == INCLUDE {
+        let path = str::from_utf8(item.params.first()?).ok()?;
+        let dir = uri_to_path(uri)?.parent()?.to_path_buf();
+        let start = position(0, 0);
+        let range = object([("start", start.clone()), ("end", start)]);
+        return Some(object([
+            ("uri", string(path_to_uri(&dir.join(path)))),
+            ("range", range),
+        ]));
+    }
+
+    let define = macro_definition(&items, item)?;
+    Some(object([
+        ("uri", string(uri)),
+        ("range", lines.range(define.name_span.0, define.end)),
+    ]))
+}
+
+/// The definition of the macro an item uses. Like the [`MacroExpander`] reads them, macros are
+/// defined in document order for the rest of the document, definitions inside of other
+/// definitions only once those are used.
+///
+/// [`MacroExpander`]: crate::macros::MacroExpander
+fn macro_definition<'a>(items: &'a [Outline], item: &Outline) -> Option<&'a Outline> {
+    items
+        .iter()
+        .take_while(|define| define.name_span.0 < item.name_span.0)
+        .find_map(|define| {
+            if define.name != DEFINE {
+                return macro_definition(&define.children, item);
+            }
+            let defines = define.params.first() == Some(&item.name);
+            (defines && define.end <= item.name_span.0).then_some(define)
+        })
+}
+
+/// Find the first item matching the predicate, searching depth first
+fn find_item<'a>(items: &'a [Outline], matches: &dyn Fn(&Outline) -> bool) -> Option<&'a Outline> {
+    items.iter().find_map(|item| {
+        if matches(item) {
+            Some(item)
+        } else {
+            find_item(&item.children, matches)
+        }
+    })
+}
+
+/// The edits formatting a whole document, `null` if it can't be parsed
+fn formatting(source: &[u8], params: &Value) -> Value {
+    let options = params.member(b"options");
+    let spaces = options
+        .and_then(|o| o.member(b"insertSpaces"))
+        .is_none_or(|spaces| *spaces == Value::Bool(true));
+    let size = options.and_then(|o| o.member(b"tabSize")?.as_usize());
+    let indent = match size {
+        _ if !spaces => Indent::Tab,
+        Some(size) => Indent::Spaces(size),
+        None => Indent::default(),
+    };
+
+    let Ok(formatted) = AmarkFormatter::with_indent(indent).format_bytes(source) else {
+        return Value::Null;
+    };
+    if formatted == source {
+        return Value::Array(Vec::new());
+    }
+    let lines = Lines::new(source);
+    Value::Array(vec![object([
+        ("range", lines.range(0, source.len())),
+        ("newText", string(formatted)),
+    ])])
+}
+
+/// A diagnostic spanning a whole line
+fn diagnostic(lines: &Lines<'_>, line: usize, severity: usize, message: String) -> Value {
+    let end = position(line, utf16_len(lines.line(line)));
+    object([
+        (
+            "range",
+            object([("start", position(line, 0)), ("end", end)]),
+        ),
+        ("severity", number(severity)),
+        ("source", string("amark")),
+        ("message", string(message)),
+    ])
+}
+
+/// The notification publishing the diagnostics of a document
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    notification(
+        "textDocument/publishDiagnostics",
+        object([
+            ("uri", string(uri)),
+            ("diagnostics", Value::Array(diagnostics)),
+        ]),
+    )
+}
+
+/// A notification to the client
+fn notification(method: &str, params: Value) -> Value {
+    object([
+        ("jsonrpc", string("2.0")),
+        ("method", string(method)),
+        ("params", params),
+    ])
+}
+
+/// The response to a request
+fn response(id: &Value, result: Result<Value, RequestError>) -> Value {
+    let result = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            object([
+                ("code", Value::Number(code.to_string().into_bytes())),
+                ("message", string(message)),
+            ]),
+        ),
+    };
+    object([("jsonrpc", string("2.0")), ("id", id.clone()), result])
+}
+
+/// The path of a `file` URI, without the slash in front of a Windows drive like `/C:/`
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut i = 0;
+    while i < path.len() {
+        let escaped = path
+            .get(i + 1..i + 3)
+            .filter(|_| path[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(path[i]);
+                i += 1;
+            }
+        }
+    }
+    let drive =
+        matches!(decoded.as_slice(), [b'/', letter, b':', ..] if letter.is_ascii_alphabetic());
+    if drive {
+        decoded.remove(0);
+    }
+    Some(PathBuf::from(String::from_utf8(decoded).ok()?))
+}
+
+/// The `file` URI of a path, with slashes for the separators of Windows paths
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for &b in path.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            uri.push(char::from(b));
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+/// A position of a line and a character
+fn position(line: usize, character: usize) -> Value {
+    object([("line", number(line)), ("character", number(character))])
+}
+
+/// A JSON object with the given members
+fn object<const N: usize>(members: [(&str, Value); N]) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(name, value)| (name.as_bytes().to_vec(), value))
+            .collect(),
+    )
+}
+
+/// A JSON string
+fn string(s: impl AsRef<[u8]>) -> Value {
+    Value::String(s.as_ref().to_vec())
+}
+
+/// A JSON number
+fn number(n: usize) -> Value {
+    Value::Number(n.to_string().into_bytes())
+}
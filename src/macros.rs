@@ -0,0 +1,311 @@
+//! Macros defined inside of documents and expanded on the token stream.
+//!
+//! `define(name, args…) { … }` or `define(name, args…) [ … ]` defines a macro, the definition
+//! itself is removed from the stream. Using it like `name(values…);` replaces the item by the
+//! content of the definition where every `$arg` in text and parameters is replaced by the value
+//! given for it. Macros defined with a block hold text and can only be used inside of blocks,
+//! macros defined with a container hold items. A `$` which is not followed by an argument stays
+//! as it is, so names like the index of a [`repeat`](crate::repeat) item are left for later
+//! adapters. `\$` is always a literal `$`.
+//!
+//! Macros can use other macros, but not themselves. Errors point at the line of the definition
+//! and the line the macro is used on.
+//!
+//! ```
+//! use amark::{html::HtmlRenderer, macros::MacroExpander, AmarkStream};
+//!
+//! let source = b"define(card, title) [\n    box {\n        $title\n    }\n]\ncard(Hi);";
+//! let mut html = Vec::new();
+//! HtmlRenderer::new().render(MacroExpander::new(AmarkStream::new(source.as_slice())), &mut html)?;
+//!
+//! assert_eq!(html, b"<div class=\"box\">\nHi\n</div>\n");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{borrow::Cow, collections::HashMap, vec};
+
+use crate::{
+    event::parse_params,
+    stream::{read_group, TokenStream},
+    validate_item_name, AmarkError, AmarkResult, AmarkToken, OwnedToken,
+};
+
+/// The name of the item defining macros
+const DEFINE: &[u8] = b"define";
+
+/// A stream adapter which expands macros, see the [module docs](self)
+#[derive(Debug)]
+pub struct MacroExpander<S> {
+    /// The stream of the document
+    stream: S,
+    /// The macros defined so far
+    macros: HashMap<Vec<u8>, Macro>,
+    /// The expansions being read, the innermost last
+    frames: Vec<Frame>,
+    /// Whether each open item is a block, the top level is not
+    blocks: Vec<bool>,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+/// A defined macro
+#[derive(Debug)]
+struct Macro {
+    /// The names of the arguments
+    args: Vec<Vec<u8>>,
+    /// The content of the definition
+    body: Vec<OwnedToken>,
+    /// Whether the definition is a block holding text
+    block: bool,
+    /// The line the macro is defined on
+    line: usize,
+}
+
+/// An expansion being read
+#[derive(Debug)]
+struct Frame {
+    /// The name of the expanded macro
+    name: Vec<u8>,
+    /// The rest of the expanded tokens
+    tokens: vec::IntoIter<OwnedToken>,
+    /// The line the outermost macro was used on
+    line: usize,
+}
+
+impl<S: TokenStream> MacroExpander<S> {
+    /// Create an expander over the given stream without any macros
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            macros: HashMap::new(),
+            frames: Vec::new(),
+            blocks: Vec::new(),
+            current: OwnedToken::End,
+        }
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// The next token of the innermost expansion or the stream
+    fn next_raw(&mut self) -> AmarkResult<'static, OwnedToken> {
+        while let Some(frame) = self.frames.last_mut() {
+            match frame.tokens.next() {
+                Some(tok) => return Ok(tok),
+                None => {
+                    self.frames.pop();
+                }
+            }
+        }
+        Ok(self.stream.next_token()?.to_owned_token())
+    }
+
+    /// The line of the token read last, inside of expansions the line they were used on
+    fn line(&self) -> usize {
+        self.frames
+            .first()
+            .map_or_else(|| self.stream.cur_line(), |frame| frame.line)
+    }
+
+    /// Read a definition after the name of the `define` item
+    fn define(&mut self) -> AmarkResult<'static, ()> {
+        let line = self.line();
+        let params = match self.next_raw()? {
+            OwnedToken::ParamsStart => parse_params(self.read_group()?)?,
+            _ => Vec::new(),
+        };
+        let Some((name, args)) = params.split_first() else {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"define(name, args...)"),
+                got: Cow::Borrowed(DEFINE),
+            });
+        };
+        validate_item_name(name)?;
+        let error = |msg: String| AmarkError::Macro {
+            name: name.clone(),
+            msg: msg.into(),
+            defined: line,
+            used: None,
+        };
+
+        if let Some(defined) = self.macros.get(name) {
+            return Err(error(format!(
+                "It is already defined on line {}",
+                defined.line
+            )));
+        }
+        if let Some(arg) = args.iter().find(|arg| !is_arg_name(arg)) {
+            return Err(error(format!(
+                "The argument {:?} is not a name of letters, digits and _",
+                String::from_utf8_lossy(arg)
+            )));
+        }
+
+        let (body, block) = match self.next_raw()? {
+            OwnedToken::BlockStart => (self.read_group()?, true),
+            OwnedToken::ContainerStart => (self.read_group()?, false),
+            OwnedToken::ItemEnd => (Vec::new(), false),
+            _ => return Err(error(String::from("It is defined by a block or container"))),
+        };
+
+        self.macros.insert(
+            name.clone(),
+            Macro {
+                args: args.to_vec(),
+                body,
+                block,
+                line,
+            },
+        );
+        Ok(())
+    }
+
+    /// Expand the macro with the given name after its name was read
+    fn expand(&mut self, name: Vec<u8>) -> AmarkResult<'static, ()> {
+        let line = self.line();
+        let Some(definition) = self.macros.get(&name) else {
+            unreachable!("Only defined macros are expanded");
+        };
+        let defined = definition.line;
+        let error = |msg: String| AmarkError::Macro {
+            name: name.clone(),
+            msg: msg.into(),
+            defined,
+            used: Some(line),
+        };
+
+        if self.frames.iter().any(|frame| frame.name == name) {
+            let chain = self
+                .frames
+                .iter()
+                .map(|frame| String::from_utf8_lossy(&frame.name))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(error(format!(
+                "It uses itself through {} -> {}",
+                chain,
+                String::from_utf8_lossy(&name)
+            )));
+        }
+        if definition.block && !self.blocks.last().copied().unwrap_or_default() {
+            return Err(error(String::from(
+                "It holds text and can only be used inside of blocks",
+            )));
+        }
+
+        let mut tok = self.next_raw()?;
+        let values = if tok == OwnedToken::ParamsStart {
+            let values = parse_params(self.read_group()?)?;
+            tok = self.next_raw()?;
+            values
+        } else {
+            Vec::new()
+        };
+        if tok != OwnedToken::ItemEnd {
+            return Err(error(String::from(
+                "Macros are used without body like name(...);",
+            )));
+        }
+
+        let Some(definition) = self.macros.get(&name) else {
+            unreachable!("Only defined macros are expanded");
+        };
+        if values.len() != definition.args.len() {
+            return Err(error(format!(
+                "It takes {} arguments but got {}",
+                definition.args.len(),
+                values.len()
+            )));
+        }
+
+        let tokens = definition
+            .body
+            .iter()
+            .map(|tok| match *tok {
+                OwnedToken::Text(ref text) => {
+                    OwnedToken::Text(substitute(text, &definition.args, &values))
+                }
+                ref tok => tok.clone(),
+            })
+            .collect::<Vec<_>>();
+        self.frames.push(Frame {
+            name,
+            tokens: tokens.into_iter(),
+            line,
+        });
+        Ok(())
+    }
+
+    /// Read the tokens up to the end of the group which was just opened, without the end
+    fn read_group(&mut self) -> AmarkResult<'static, Vec<OwnedToken>> {
+        read_group(|| self.next_raw(), b"The end of the macro")
+    }
+}
+
+impl<S: TokenStream> TokenStream for MacroExpander<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        loop {
+            let tok = self.next_raw()?;
+            match tok {
+                OwnedToken::ItemName(ref name) if name == DEFINE => self.define()?,
+                OwnedToken::ItemName(name) if self.macros.contains_key(&name) => {
+                    self.expand(name)?;
+                }
+                tok => {
+                    match tok {
+                        OwnedToken::BlockStart => self.blocks.push(true),
+                        OwnedToken::ContainerStart => self.blocks.push(false),
+                        OwnedToken::BlockEnd | OwnedToken::ContainerEnd => {
+                            self.blocks.pop();
+                        }
+                        _ => (),
+                    }
+                    self.current = tok;
+                    return Ok(self.current.as_token());
+                }
+            }
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
+
+/// Replace every `$arg` in the text by its value, other names are kept
+fn substitute(text: &[u8], args: &[Vec<u8>], values: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.iter().position(|&b| b == b'$') {
+        out.extend_from_slice(&rest[..pos + 1]);
+        rest = &rest[pos + 1..];
+        let len = rest.iter().take_while(|&&b| is_arg_char(b)).count();
+        if len == 0 {
+            continue;
+        }
+
+        let name = &rest[..len];
+        let Some(idx) = args.iter().position(|arg| arg == name) else {
+            continue;
+        };
+        out.pop();
+        out.extend_from_slice(&values[idx]);
+        rest = &rest[len..];
+    }
+
+    out.extend_from_slice(rest);
+    out
+}
+
+/// Whether the given parameter can be the name of an argument
+pub(crate) fn is_arg_name(arg: &[u8]) -> bool {
+    !arg.is_empty() && arg.iter().all(|&b| is_arg_char(b))
+}
+
+/// Whether the byte can be part of the name of an argument
+fn is_arg_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
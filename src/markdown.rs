@@ -0,0 +1,771 @@
+//! A Markdown (CommonMark) renderer. Items are rendered as the [`MarkdownElement`] configured for
+//! their name, items without one become HTML or are dropped depending on [`UnknownItems`].
+//!
+//! Lines of text are joined by soft line breaks, an empty line starts a new paragraph and `\n`
+//! or `br(N);` are hard line breaks. Markdown only has hard line breaks between inline content,
+//! so breaks at the start or end of a block and outside of paragraphs are left out.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    iter, mem,
+};
+
+use crate::{
+    event::{parse_count, Event, EventReader, Item, ItemKind},
+    html,
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// The Markdown construct an item is rendered as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarkdownElement {
+    /// A heading of the given level from 1 to 6, its content is written on a single line
+    Heading(u8),
+    /// A paragraph
+    Paragraph,
+    /// Emphasized text like `*text*`
+    Emphasis,
+    /// Strongly emphasized text like `**text**`
+    Strong,
+    /// Inline code, its content is written as is
+    Code,
+    /// A fenced code block with the first parameter as its language, its content is written as is
+    CodeBlock,
+    /// A block quote
+    Quote,
+    /// A list, its entries are the [`MarkdownElement::ListItem`]s inside of it
+    List {
+        /// Whether the entries are numbered instead of bullet points
+        ordered: bool,
+    },
+    /// An entry of the surrounding list
+    ListItem,
+    /// Hard line breaks, as many as the first parameter says
+    LineBreak,
+}
+
+/// What happens to items without a configured [`MarkdownElement`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownItems {
+    /// Render them as HTML elements with the item name as their class, `span` inside of text and
+    /// `div` everywhere else
+    #[default]
+    Html,
+    /// Render only their content
+    Unwrap,
+    /// Leave them out together with their content
+    Drop,
+}
+
+/// Renders Amark documents as Markdown
+#[derive(Debug, Clone)]
+pub struct MarkdownRenderer {
+    /// Elements configured for specific item names
+    elements: HashMap<Vec<u8>, MarkdownElement>,
+    /// What happens to items without an element
+    unknown: UnknownItems,
+}
+
+impl MarkdownRenderer {
+    /// Create a new renderer with elements for the HTML names `h1` to `h6`, `p`, `em`, `strong`,
+    /// `code`, `pre`, `blockquote`, `ul`, `ol`, `li` and `br`
+    pub fn new() -> Self {
+        let mut elements = HashMap::from([
+            (b"p".to_vec(), MarkdownElement::Paragraph),
+            (b"em".to_vec(), MarkdownElement::Emphasis),
+            (b"strong".to_vec(), MarkdownElement::Strong),
+            (b"code".to_vec(), MarkdownElement::Code),
+            (b"pre".to_vec(), MarkdownElement::CodeBlock),
+            (b"blockquote".to_vec(), MarkdownElement::Quote),
+            (b"ul".to_vec(), MarkdownElement::List { ordered: false }),
+            (b"ol".to_vec(), MarkdownElement::List { ordered: true }),
+            (b"li".to_vec(), MarkdownElement::ListItem),
+            (b"br".to_vec(), MarkdownElement::LineBreak),
+        ]);
+        for level in 1..=6 {
+            elements.insert(
+                format!("h{}", level).into_bytes(),
+                MarkdownElement::Heading(level),
+            );
+        }
+
+        Self {
+            elements,
+            unknown: UnknownItems::default(),
+        }
+    }
+
+    /// Render items with the given name as the given element
+    pub fn element(mut self, name: impl Into<Vec<u8>>, element: MarkdownElement) -> Self {
+        self.elements.insert(name.into(), element);
+        self
+    }
+
+    /// Set what happens to items without an element
+    pub fn unknown(mut self, unknown: UnknownItems) -> Self {
+        self.unknown = unknown;
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        let mut output = Output::new(writer);
+
+        while let Some(event) = events.next_event()? {
+            if output.dropping > 0 {
+                match event {
+                    Event::Start(_) => output.dropping += 1,
+                    Event::End(_) => output.dropping -= 1,
+                    _ => (),
+                }
+                continue;
+            }
+
+            match event {
+                Event::Start(item) => {
+                    let rendered = self.rendered(item, &output);
+                    output.start(item, rendered)?;
+                }
+                Event::End(_) => output.end()?,
+                Event::Empty(item) => match self.rendered(&item, &output) {
+                    Rendered::Element(MarkdownElement::LineBreak) => {
+                        let count = item.param(0).and_then(parse_count).unwrap_or(1);
+                        output.hard_breaks(count, Last::Text)?;
+                    }
+                    Rendered::Drop => (),
+                    rendered => {
+                        output.start(&item, rendered)?;
+                        output.end()?;
+                    }
+                },
+                Event::Text(text) => output.text(text)?,
+                Event::Escape(escape) => output.escape(escape.seq, escape.count())?,
+                Event::EmptyLine => output.empty_line(),
+            }
+        }
+
+        output.finish()?;
+        Ok(())
+    }
+
+    /// How an item is rendered at the current position
+    fn rendered<W: Write>(&self, item: &Item, output: &Output<W>) -> Rendered {
+        if output.code.is_some() {
+            return Rendered::Unwrap;
+        }
+
+        match self.elements.get(&item.name) {
+            Some(&element) => Rendered::Element(element),
+            None => match self.unknown {
+                UnknownItems::Html if output.inline() => Rendered::Span,
+                UnknownItems::Html => Rendered::Div,
+                UnknownItems::Unwrap => Rendered::Unwrap,
+                UnknownItems::Drop => Rendered::Drop,
+            },
+        }
+    }
+}
+
+impl Default for MarkdownRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How an item is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rendered {
+    /// As a configured element
+    Element(MarkdownElement),
+    /// As an HTML `div` between blocks
+    Div,
+    /// As an HTML `span` inside of text
+    Span,
+    /// Only its content
+    Unwrap,
+    /// Not at all
+    Drop,
+}
+
+/// Whitespace owed between the last and the next output, larger ones replace smaller ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    /// Nothing
+    None,
+    /// A space
+    Space,
+    /// A line break
+    Line,
+    /// An empty line between paragraphs
+    Paragraph,
+}
+
+/// What was read last in the current item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing on the current line
+    Start,
+    /// A line of text, the next one starts on a new line
+    Text,
+    /// An escape sequence or inline item, text continues on the same line
+    Inline,
+}
+
+/// An item that is currently rendered
+#[derive(Debug)]
+struct Frame {
+    /// How the item is rendered
+    rendered: Rendered,
+    /// Written at the start of every line inside of the item
+    prefix: Vec<u8>,
+    /// Whether the content of the item is text
+    inline: bool,
+    /// The number of entries of a list so far
+    entries: usize,
+}
+
+/// The content of inline code or a code block which is written at its end
+#[derive(Debug, Default)]
+struct Code {
+    /// The language of a code block
+    lang: Vec<u8>,
+    /// The collected content
+    text: Vec<u8>,
+    /// Line breaks which are only written if more content follows
+    breaks: usize,
+    /// Trailing whitespace of the last line, only kept if an escape sequence follows
+    trailing: Vec<u8>,
+    /// What was read last
+    last: Option<Last>,
+}
+
+impl Code {
+    /// Add a line of text
+    fn text(&mut self, text: &[u8]) {
+        if self.last == Some(Last::Text) {
+            self.breaks += 1;
+        }
+        let content = text.trim_ascii_end();
+        self.push(content);
+        self.trailing = text[content.len()..].to_vec();
+        self.last = Some(Last::Text);
+    }
+
+    /// Add a resolved escape sequence
+    fn escape(&mut self, b: u8, count: usize) {
+        let trailing = mem::take(&mut self.trailing);
+        self.text.extend_from_slice(&trailing);
+        self.push(&vec![b; count]);
+        self.last = Some(Last::Inline);
+    }
+
+    /// Add an empty line, the line break after an escape sequence only ends its line
+    fn empty_line(&mut self) {
+        self.breaks += match self.last {
+            Some(Last::Text) => 2,
+            Some(_) => 1,
+            None => 0,
+        };
+        if self.last.is_some() {
+            self.last = Some(Last::Start);
+        }
+    }
+
+    /// Add content after the pending line breaks
+    fn push(&mut self, content: &[u8]) {
+        if self.breaks > 0 {
+            self.trailing.clear();
+        }
+        self.text.extend(iter::repeat_n(b'\n', self.breaks));
+        self.breaks = 0;
+        self.text.extend_from_slice(content);
+    }
+}
+
+/// The state of rendering into a writer
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The items being rendered
+    frames: Vec<Frame>,
+    /// Whitespace owed before the next output
+    pending: Sep,
+    /// Whether anything was written yet
+    started: bool,
+    /// Whether the next output starts a line
+    at_line_start: bool,
+    /// Whether the last output was a list or quote marker, the first line of the content
+    /// follows it on the same line
+    after_marker: bool,
+    /// Trailing whitespace of the last text, only written if the line continues
+    trailing: Vec<u8>,
+    /// Hard line breaks, only written if inline content of the same block follows
+    breaks: usize,
+    /// What was read last
+    last: Last,
+    /// The content of the code currently being rendered
+    code: Option<Code>,
+    /// How deep the renderer is inside of dropped items
+    dropping: usize,
+}
+
+impl<'w, W: Write> Output<'w, W> {
+    /// Start rendering into the writer
+    fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            frames: Vec::new(),
+            pending: Sep::None,
+            started: false,
+            at_line_start: true,
+            after_marker: false,
+            trailing: Vec::new(),
+            breaks: 0,
+            last: Last::Start,
+            code: None,
+            dropping: 0,
+        }
+    }
+
+    /// Whether the current content is text
+    fn inline(&self) -> bool {
+        self.frames.last().is_some_and(|frame| frame.inline)
+    }
+
+    /// Whether the output is inside of a heading which has to stay on one line
+    fn in_heading(&self) -> bool {
+        self.frames.iter().any(|frame| {
+            matches!(
+                frame.rendered,
+                Rendered::Element(MarkdownElement::Heading(_))
+            )
+        })
+    }
+
+    /// Start rendering an item
+    fn start(&mut self, item: &Item, rendered: Rendered) -> io::Result<()> {
+        let inline = self.inline();
+        let mut frame = Frame {
+            rendered,
+            prefix: Vec::new(),
+            inline: true,
+            entries: 0,
+        };
+
+        match rendered {
+            Rendered::Element(MarkdownElement::Heading(level)) => {
+                self.block_start()?;
+                let level = usize::from(level.clamp(1, 6));
+                self.write(&[b"#".repeat(level).as_slice(), b" "].concat())?;
+            }
+            Rendered::Element(MarkdownElement::Paragraph) => self.block_start()?,
+            Rendered::Element(MarkdownElement::Quote) => {
+                self.block_start()?;
+                self.write_marker(b"> ")?;
+                frame.prefix = b"> ".to_vec();
+            }
+            Rendered::Element(MarkdownElement::List { .. }) => {
+                self.pending = self.pending.max(Sep::Paragraph);
+                frame.inline = false;
+            }
+            Rendered::Element(MarkdownElement::ListItem) => {
+                self.pending = self.pending.max(Sep::Line);
+                self.flush()?;
+                let marker = match self.frames.last_mut() {
+                    Some(&mut Frame {
+                        rendered: Rendered::Element(MarkdownElement::List { ordered: true }),
+                        ref mut entries,
+                        ..
+                    }) => {
+                        *entries += 1;
+                        format!("{}. ", entries).into_bytes()
+                    }
+                    _ => b"- ".to_vec(),
+                };
+                self.write_marker(&marker)?;
+                frame.prefix = vec![b' '; marker.len()];
+            }
+            Rendered::Element(MarkdownElement::Emphasis) => self.inline_start(b"*")?,
+            Rendered::Element(MarkdownElement::Strong) => self.inline_start(b"**")?,
+            Rendered::Element(MarkdownElement::Code) => {
+                self.inline_start(b"")?;
+                self.code = Some(Code::default());
+            }
+            Rendered::Element(MarkdownElement::CodeBlock) => {
+                self.block_start()?;
+                self.code = Some(Code {
+                    lang: item.param(0).unwrap_or_default().to_vec(),
+                    ..Code::default()
+                });
+            }
+            Rendered::Element(MarkdownElement::LineBreak) => {
+                let count = item.param(0).and_then(parse_count).unwrap_or(1);
+                self.hard_breaks(count, Last::Text)?;
+                frame.inline = inline;
+            }
+            Rendered::Div if item.kind == ItemKind::Bare => {
+                self.block_start()?;
+                self.html_tag(b"<div", &item.name)?;
+                self.write(b"</div>")?;
+                self.block_end();
+                frame.rendered = Rendered::Unwrap;
+            }
+            Rendered::Div => {
+                self.block_start()?;
+                self.html_tag(b"<div", &item.name)?;
+                self.pending = Sep::Paragraph;
+                frame.inline = item.kind != ItemKind::Container;
+            }
+            Rendered::Span if item.kind == ItemKind::Bare => {
+                self.html_tag(b"<span", &item.name)?;
+                self.inline_end(b"</span>")?;
+                frame.rendered = Rendered::Unwrap;
+            }
+            Rendered::Span => self.html_tag(b"<span", &item.name)?,
+            Rendered::Unwrap => frame.inline = inline,
+            Rendered::Drop => {
+                self.dropping += 1;
+                return Ok(());
+            }
+        }
+
+        if frame.rendered != Rendered::Unwrap {
+            self.last = Last::Start;
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    /// Finish rendering the current item
+    fn end(&mut self) -> io::Result<()> {
+        let Some(frame) = self.frames.pop() else {
+            return Ok(());
+        };
+
+        match frame.rendered {
+            Rendered::Element(
+                MarkdownElement::Heading(_)
+                | MarkdownElement::Paragraph
+                | MarkdownElement::Quote
+                | MarkdownElement::List { .. },
+            ) => self.block_end(),
+            Rendered::Element(MarkdownElement::ListItem) => {
+                self.breaks = 0;
+                self.pending = self.pending.max(Sep::Line);
+                self.last = Last::Start;
+            }
+            Rendered::Element(MarkdownElement::Emphasis) => self.inline_end(b"*")?,
+            Rendered::Element(MarkdownElement::Strong) => self.inline_end(b"**")?,
+            Rendered::Element(MarkdownElement::Code) => {
+                let code = self.code.take().unwrap_or_default().text;
+                if !code.is_empty() {
+                    self.write_inline_code(&code)?;
+                }
+                self.inline_end(b"")?;
+            }
+            Rendered::Element(MarkdownElement::CodeBlock) => {
+                let code = self.code.take().unwrap_or_default();
+                self.write_code_block(&code)?;
+                self.block_end();
+            }
+            Rendered::Div => {
+                self.block_start()?;
+                self.write(b"</div>")?;
+                self.block_end();
+            }
+            Rendered::Span => self.inline_end(b"</span>")?,
+            Rendered::Element(MarkdownElement::LineBreak) | Rendered::Unwrap | Rendered::Drop => {}
+        }
+
+        Ok(())
+    }
+
+    /// Render a line of text
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        if let Some(ref mut code) = self.code {
+            code.text(text);
+            return Ok(());
+        }
+
+        if self.last == Last::Text {
+            self.pending = self.pending.max(Sep::Line);
+        }
+        let content = text.trim_ascii_end();
+        self.continue_line()?;
+        self.write_escaped(content)?;
+        self.trailing = text[content.len()..].to_vec();
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Render an escape sequence, `\n` is a hard line break and `\s` a non-breaking space
+    fn escape(&mut self, seq: u8, count: usize) -> io::Result<()> {
+        let resolved = crate::event::resolve_escape(seq);
+        if let Some(ref mut code) = self.code {
+            code.escape(resolved, count);
+            return Ok(());
+        }
+
+        match seq {
+            b'n' => self.hard_breaks(count, Last::Inline)?,
+            _ => {
+                self.continue_line()?;
+                for _ in 0..count {
+                    if seq == b's' {
+                        self.write(b"&nbsp;")?;
+                    } else {
+                        self.write_escaped(&[resolved])?;
+                    }
+                }
+                self.last = Last::Inline;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render an empty line, it starts a new paragraph after text and ends the line after an
+    /// escape sequence
+    fn empty_line(&mut self) {
+        if let Some(ref mut code) = self.code {
+            code.empty_line();
+            return;
+        }
+
+        match self.last {
+            Last::Text => self.pending = self.pending.max(Sep::Paragraph),
+            Last::Inline => {
+                self.pending = self.pending.max(Sep::Line);
+                self.last = Last::Text;
+            }
+            Last::Start => (),
+        }
+    }
+
+    /// Add hard line breaks, they are written once inline content of the same block follows.
+    /// After `\n` the reader gives an empty line at the end of the line, after `br` items an
+    /// empty line starts a new paragraph like after text.
+    fn hard_breaks(&mut self, count: usize, last: Last) -> io::Result<()> {
+        if let Some(ref mut code) = self.code {
+            code.escape(b'\n', count);
+            return Ok(());
+        }
+
+        if self.last != Last::Start {
+            self.breaks += count;
+            self.last = last;
+        }
+        Ok(())
+    }
+
+    /// Write the pending hard line breaks, inside of headings they are spaces
+    fn write_breaks(&mut self) -> io::Result<()> {
+        let breaks = mem::take(&mut self.breaks);
+        // A new paragraph replaces the breaks
+        if breaks == 0 || self.pending == Sep::Paragraph {
+            return Ok(());
+        }
+
+        self.pending = Sep::None;
+        self.trailing.clear();
+        for _ in 0..breaks {
+            if self.in_heading() {
+                self.write(b" ")?;
+            } else {
+                self.write(b"\\")?;
+                self.newline()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the output with a line break
+    fn finish(&mut self) -> io::Result<()> {
+        if self.started {
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Start a block separated by an empty line
+    fn block_start(&mut self) -> io::Result<()> {
+        self.breaks = 0;
+        self.pending = self.pending.max(Sep::Paragraph);
+        self.flush()
+    }
+
+    /// End a block, whatever follows is separated by an empty line
+    fn block_end(&mut self) {
+        self.breaks = 0;
+        self.trailing.clear();
+        self.pending = self.pending.max(Sep::Paragraph);
+        self.last = Last::Start;
+    }
+
+    /// Start inline content on the current line with the given marker, it is separated from
+    /// text before it by a space
+    fn inline_start(&mut self, marker: &[u8]) -> io::Result<()> {
+        if self.last == Last::Text {
+            self.pending = self.pending.max(Sep::Space);
+        }
+        self.continue_line()?;
+        self.write(marker)
+    }
+
+    /// End inline content with the given marker, it is followed by a space
+    fn inline_end(&mut self, marker: &[u8]) -> io::Result<()> {
+        self.trailing.clear();
+        self.write(marker)?;
+        self.pending = self.pending.max(Sep::Space);
+        self.last = Last::Inline;
+        Ok(())
+    }
+
+    /// Write the owed whitespace and the trailing whitespace of the last text if the line
+    /// continues
+    fn continue_line(&mut self) -> io::Result<()> {
+        self.write_breaks()?;
+        self.flush()?;
+        let trailing = mem::take(&mut self.trailing);
+        self.write(&trailing)
+    }
+
+    /// Write the owed whitespace
+    fn flush(&mut self) -> io::Result<()> {
+        let mut sep = mem::replace(&mut self.pending, Sep::None);
+        if sep == Sep::None {
+            return Ok(());
+        }
+        self.trailing.clear();
+        if !self.started || self.after_marker {
+            return Ok(());
+        }
+        if sep > Sep::Space && self.in_heading() {
+            sep = Sep::Space;
+        }
+
+        match sep {
+            Sep::None => Ok(()),
+            Sep::Space => self.write(b" "),
+            Sep::Line => self.newline(),
+            Sep::Paragraph => {
+                let prefix = self.prefix();
+                self.writer.write_all(b"\n")?;
+                self.writer.write_all(prefix.trim_ascii_end())?;
+                self.newline()
+            }
+        }
+    }
+
+    /// Start a new line inside of the current items
+    fn newline(&mut self) -> io::Result<()> {
+        let prefix = self.prefix();
+        self.writer.write_all(b"\n")?;
+        self.writer.write_all(&prefix)?;
+        self.at_line_start = true;
+        Ok(())
+    }
+
+    /// The prefix of every line inside of the current items
+    fn prefix(&self) -> Vec<u8> {
+        self.frames
+            .iter()
+            .flat_map(|frame| frame.prefix.iter().copied())
+            .collect()
+    }
+
+    /// Write an HTML opening tag with the item name as class
+    fn html_tag(&mut self, tag: &[u8], name: &[u8]) -> io::Result<()> {
+        self.inline_start(tag)?;
+        self.write(b" class=\"")?;
+        html::write_escaped(name, self.writer)?;
+        self.write(b"\">")
+    }
+
+    /// Write inline code delimited by more backticks than it contains in a row
+    fn write_inline_code(&mut self, code: &[u8]) -> io::Result<()> {
+        let code: Vec<u8> = code
+            .iter()
+            .map(|&b| if b == b'\n' { b' ' } else { b })
+            .collect();
+        let fence = b"`".repeat(longest_run(&code, b'`') + 1);
+        // A space keeps backticks at the edges apart from the delimiters
+        let pad: &[u8] = if code.starts_with(b"`") || code.ends_with(b"`") {
+            b" "
+        } else {
+            b""
+        };
+        self.write(&[&fence, pad, &code, pad, &fence].concat())
+    }
+
+    /// Write a fenced code block
+    fn write_code_block(&mut self, code: &Code) -> io::Result<()> {
+        let fence = b"`".repeat(longest_run(&code.text, b'`').max(2) + 1);
+        self.write(&fence)?;
+        self.write(&code.lang)?;
+        for line in code.text.split(|&b| b == b'\n') {
+            self.newline()?;
+            self.write(line)?;
+        }
+        self.newline()?;
+        self.write(&fence)
+    }
+
+    /// Write text with the characters that have a meaning in Markdown escaped
+    fn write_escaped(&mut self, text: &[u8]) -> io::Result<()> {
+        // Digits followed by `.` or `)` at the start of a line would start a numbered list
+        let list_marker = if self.at_line_start {
+            Some(text.iter().take_while(|b| b.is_ascii_digit()).count())
+        } else {
+            None
+        };
+
+        let mut start = 0;
+        for (i, &b) in text.iter().enumerate() {
+            let escape = b"\\`*_[]<>&".contains(&b)
+                || i == 0 && self.at_line_start && b"#-+=|~".contains(&b)
+                || list_marker == Some(i) && i != 0 && b".)".contains(&b);
+            if escape {
+                self.write(&text[start..i])?;
+                self.write(&[b'\\', b])?;
+                start = i + 1;
+            }
+        }
+        self.write(&text[start..])
+    }
+
+    /// Write a list or quote marker, what follows is written as the start of a line
+    fn write_marker(&mut self, marker: &[u8]) -> io::Result<()> {
+        self.write(marker)?;
+        self.at_line_start = true;
+        self.after_marker = true;
+        Ok(())
+    }
+
+    /// Write to the output
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if !bytes.is_empty() {
+            self.writer.write_all(bytes)?;
+            self.started = true;
+            self.at_line_start = false;
+            self.after_marker = false;
+        }
+        Ok(())
+    }
+}
+
+/// The length of the longest run of the byte in the text
+fn longest_run(text: &[u8], b: u8) -> usize {
+    text.split(|&c| c != b).map(<[u8]>::len).max().unwrap_or(0)
+}
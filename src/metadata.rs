@@ -0,0 +1,417 @@
+//! Front matter with metadata like the title, date or tags of a document.
+//!
+//! The front matter is optional and comes before any content, either as a `meta` container with
+//! an item per key or fenced by `---` lines with a `key: value` per line:
+//!
+//! ```text
+//! meta [                        ---
+//!     title(Green boxes);       title: Green boxes
+//!     tags(amark, css);         tags: [amark, css]
+//!     summary {                 summary: How to draw them
+//!         How to draw them      ---
+//!     }
+//! ]
+//! ```
+//!
+//! Items with several parameters and `[a, b]` values are lists, everything else is text. The
+//! front matter is read with [`AmarkReader::read_metadata`] or [`AmarkStream::read_metadata`]
+//! before the first token, it is no content and not part of the tokens. With the `serde` feature
+//! it can be deserialized into your own types with `Metadata::deserialize`. Tools writing the
+//! source back, like the formatter, keep the bytes of [`front_matter`] as they are.
+//!
+//! ```
+//! use amark::{metadata::MetaValue, AmarkStream, TokenStream};
+//!
+//! let mut stream = AmarkStream::new(b"---\ntitle: Hi\ntags: [a, b]\n---\np { Text }".as_slice());
+//! let metadata = stream.read_metadata()?.unwrap();
+//!
+//! assert_eq!(metadata.get("title").and_then(MetaValue::as_text), Some("Hi"));
+//! assert_eq!(metadata.get("tags").map(MetaValue::as_list), Some(&["a", "b"].map(String::from)[..]));
+//! assert_eq!(stream.next_token()?, amark::AmarkToken::ItemName(b"p"));
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{borrow::Cow, io::BufRead, slice};
+
+#[cfg(doc)]
+use crate::AmarkStream;
+use crate::{
+    event::{parse_params, resolve_escape},
+    stream::read_group,
+    AmarkError, AmarkReader, AmarkResult, OwnedToken,
+};
+
+/// The name of the container holding the front matter
+const META: &[u8] = b"meta";
+
+/// The line fencing the front matter
+const FENCE: &[u8] = b"---";
+
+/// The metadata of a document in the order of the front matter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The entries in order
+    entries: Vec<Entry>,
+}
+
+/// An entry of the front matter
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// The key
+    key: String,
+    /// The value
+    value: MetaValue,
+    /// The line the entry is on
+    line: usize,
+}
+
+/// The value of a key of the front matter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaValue {
+    /// A single value
+    Text(String),
+    /// Several values
+    List(Vec<String>),
+}
+
+impl MetaValue {
+    /// The text of a single value, `None` for lists
+    pub fn as_text(&self) -> Option<&str> {
+        match *self {
+            Self::Text(ref text) => Some(text),
+            Self::List(_) => None,
+        }
+    }
+
+    /// The values of a list, a single value is a list of one
+    pub fn as_list(&self) -> &[String] {
+        match *self {
+            Self::Text(ref text) => slice::from_ref(text),
+            Self::List(ref list) => list,
+        }
+    }
+}
+
+impl Metadata {
+    /// The value of the given key
+    pub fn get(&self, key: &str) -> Option<&MetaValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    /// The keys and values in the order of the front matter
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MetaValue)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), &entry.value))
+    }
+
+    /// The number of keys
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no keys
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Deserialize the metadata into the given type like a document with an item per key, see
+    /// [`de`](crate::de)
+    ///
+    /// # Errors
+    ///
+    /// This errors when the metadata doesn't fit the type.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T, crate::SerdeError> {
+        use crate::{
+            event::{Item, ItemKind},
+            tree::{Document, Element, Node},
+        };
+
+        let children = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let params = entry
+                    .value
+                    .as_list()
+                    .iter()
+                    .map(|value| value.clone().into_bytes())
+                    .collect();
+                Node::Element(Element {
+                    item: Item {
+                        name: entry.key.clone().into_bytes(),
+                        params: Some(params),
+                        kind: ItemKind::Bare,
+                    },
+                    line: entry.line,
+                    children: Vec::new(),
+                })
+            })
+            .collect();
+        crate::de::from_document(&Document { children })
+    }
+
+    /// Add an entry, keys can only be set once
+    fn insert(&mut self, key: String, value: MetaValue, line: usize) -> AmarkResult<'static, ()> {
+        if self.get(&key).is_some() {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"A key which is not set already"),
+                got: Cow::Owned(key.into_bytes()),
+            });
+        }
+        self.entries.push(Entry { key, value, line });
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a Metadata {
+    type Item = (&'a str, &'a MetaValue);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+/// The bytes of the front matter at the start of the source with the line break after it, empty
+/// if the source has no front matter
+///
+/// # Errors
+///
+/// This errors when the front matter is malformed.
+pub fn front_matter(source: &[u8]) -> AmarkResult<'static, &[u8]> {
+    let mut reader = AmarkReader::new();
+    let mut input = source;
+    if read(&mut reader, &mut input)?.is_none() {
+        return Ok(&[]);
+    }
+
+    // The rest of the last line belongs to the front matter if it is only whitespace
+    let end = reader.offset().min(source.len());
+    let rest = &source[end..];
+    let line = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(rest, |i| &rest[..=i]);
+    if line.trim_ascii().is_empty() {
+        return Ok(&source[..end + line.len()]);
+    }
+    Ok(&source[..end])
+}
+
+/// The source with the bytes of the front matter replaced by spaces, which keeps the lines and
+/// columns of the content for parsers which don't skip front matter
+pub(crate) fn blank_front_matter(source: &[u8]) -> AmarkResult<'static, Cow<'_, [u8]>> {
+    let len = front_matter(source)?.len();
+    if len == 0 {
+        return Ok(Cow::Borrowed(source));
+    }
+
+    let mut blanked = source.to_vec();
+    for b in &mut blanked[..len] {
+        if !b.is_ascii_whitespace() {
+            *b = b' ';
+        }
+    }
+    Ok(Cow::Owned(blanked))
+}
+
+/// Read the front matter at the start of the input, see [`AmarkReader::read_metadata`]
+pub(crate) fn read<B: BufRead>(
+    reader: &mut AmarkReader,
+    input: &mut B,
+) -> AmarkResult<'static, Option<Metadata>> {
+    if reader.cur_line != 0 {
+        return Err(AmarkError::UnexpectedInput {
+            expected: Cow::Borrowed(b"Front matter before the first token"),
+            got: Cow::Borrowed(b"Tokens which were parsed already"),
+        });
+    }
+
+    // Empty lines before the front matter are skipped like before any other item
+    let buf = &mut reader.inner.buf;
+    loop {
+        buf.fill_with_line(&mut reader.cur_line, input)?;
+        if buf.storage_empty() {
+            return Ok(None);
+        }
+        if !buf.rest().trim_ascii().is_empty() {
+            break;
+        }
+    }
+
+    let line = buf.rest().trim_ascii();
+    if line == FENCE {
+        buf.skip_rest();
+        return read_fenced(reader, input).map(Some);
+    }
+    let is_meta = line
+        .strip_prefix(META)
+        .is_some_and(|rest| rest.trim_ascii_start().starts_with(b"["));
+    if is_meta {
+        return read_container(reader, input).map(Some);
+    }
+    Ok(None)
+}
+
+/// Read `key: value` lines up to the closing `---`
+fn read_fenced<B: BufRead>(
+    reader: &mut AmarkReader,
+    input: &mut B,
+) -> AmarkResult<'static, Metadata> {
+    let mut metadata = Metadata::default();
+    let buf = &mut reader.inner.buf;
+
+    loop {
+        buf.fill_with_line(&mut reader.cur_line, input)?;
+        if buf.storage_empty() {
+            return Err(AmarkError::UnexpectedEof {
+                expected: Cow::Borrowed(b"--- after the front matter"),
+            });
+        }
+        let line = buf.rest().trim_ascii();
+        if line == FENCE {
+            buf.skip_rest();
+            return Ok(metadata);
+        }
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+
+        let Some(colon) = line.iter().position(|&b| b == b':') else {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"key: value"),
+                got: Cow::Owned(line.to_vec()),
+            });
+        };
+        let key = line[..colon].trim_ascii();
+        if key.is_empty() {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"A key before :"),
+                got: Cow::Owned(line.to_vec()),
+            });
+        }
+
+        let value = line[colon + 1..].trim_ascii();
+        let value = match value.strip_prefix(b"[").and_then(|v| v.strip_suffix(b"]")) {
+            Some(list) => MetaValue::List(
+                list.split(|&b| b == b',')
+                    .map(<[u8]>::trim_ascii)
+                    .filter(|value| !value.is_empty())
+                    .map(string)
+                    .collect(),
+            ),
+            None => MetaValue::Text(string(unquote(value))),
+        };
+        metadata.insert(string(key), value, reader.cur_line)?;
+        buf.skip_rest();
+    }
+}
+
+/// Read a `meta` container with an item per key
+fn read_container<B: BufRead>(
+    reader: &mut AmarkReader,
+    input: &mut B,
+) -> AmarkResult<'static, Metadata> {
+    let (OwnedToken::ItemName(_), OwnedToken::ContainerStart) =
+        (next(reader, input)?, next(reader, input)?)
+    else {
+        unreachable!("The front matter starts with meta [");
+    };
+
+    let mut metadata = Metadata::default();
+    loop {
+        let key = match next(reader, input)? {
+            OwnedToken::ContainerEnd => return Ok(metadata),
+            OwnedToken::ItemName(key) => key,
+            _ => {
+                return Err(AmarkError::UnexpectedInput {
+                    expected: Cow::Borrowed(b"An item per key or ]"),
+                    got: Cow::Borrowed(b"Text"),
+                })
+            }
+        };
+        let line = reader.cur_line;
+
+        let value = match next(reader, input)? {
+            OwnedToken::ParamsStart => {
+                let tokens = read_group(|| next(reader, input), b"The end of the value")?;
+                let mut params = parse_params(tokens)?;
+                if next(reader, input)? != OwnedToken::ItemEnd {
+                    return Err(unexpected_value(key));
+                }
+                match params.len() {
+                    0 => return Err(unexpected_value(key)),
+                    1 => MetaValue::Text(string(&params.remove(0))),
+                    _ => MetaValue::List(params.iter().map(|param| string(param)).collect()),
+                }
+            }
+            OwnedToken::BlockStart => {
+                let tokens = read_group(|| next(reader, input), b"The end of the value")?;
+                MetaValue::Text(string(&block_text(tokens)))
+            }
+            _ => return Err(unexpected_value(key)),
+        };
+        metadata.insert(string(&key), value, line)?;
+    }
+}
+
+/// The next token of the reader
+fn next<B: BufRead>(reader: &mut AmarkReader, input: &mut B) -> AmarkResult<'static, OwnedToken> {
+    reader
+        .parse_next(input)
+        .map(|tok| tok.to_owned_token())
+        .map_err(AmarkError::to_owned)
+}
+
+/// The text of a block, lines are joined by line breaks
+fn block_text(tokens: Vec<OwnedToken>) -> Vec<u8> {
+    let mut text = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(tok) = tokens.next() {
+        match tok {
+            OwnedToken::Text(line) => {
+                if !text.is_empty() && !text.ends_with(b"\n") {
+                    text.push(b'\n');
+                }
+                text.extend_from_slice(line.trim_ascii_end());
+            }
+            OwnedToken::EscapeSequence(seq) => {
+                text.push(resolve_escape(seq));
+                // Parameters of escape sequences in front matter are ignored
+                if tokens.peek() == Some(&OwnedToken::ParamsStart) {
+                    tokens.by_ref().find(|tok| *tok == OwnedToken::ParamsEnd);
+                }
+            }
+            OwnedToken::EmptyLine => text.push(b'\n'),
+            _ => (),
+        }
+    }
+    text
+}
+
+/// Create an error for a key of a `meta` container without a value
+fn unexpected_value(key: Vec<u8>) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(b"key(value); or key { value }"),
+        got: Cow::Owned(key),
+    }
+}
+
+/// Remove the quotes around a value
+fn unquote(value: &[u8]) -> &[u8] {
+    [b'"', b'\'']
+        .iter()
+        .find_map(|&quote| value.strip_prefix(&[quote])?.strip_suffix(&[quote]))
+        .unwrap_or(value)
+}
+
+/// Convert bytes of the front matter to a string
+fn string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
@@ -0,0 +1,247 @@
+//! Expansion of `repeat` and `br` items on the token stream.
+//!
+//! `repeat(N) { … }` and `repeat(N) [ … ]` are replaced by their content N times.
+//! `repeat(N, i)` names an index variable: every `$i` in text and parameters of the content is
+//! replaced by the number of the repetition, starting at 1. `br(N);` is replaced by N `br;` items.
+//! The content is buffered once and replayed, so documents are expanded while they are read.
+//!
+//! Untrusted documents could ask for far more output than they are long, so the number of tokens
+//! created by expansions is limited, see [`RepeatExpander::max_tokens`].
+//!
+//! ```
+//! use amark::{html::HtmlRenderer, repeat::RepeatExpander, AmarkStream};
+//!
+//! let source = b"repeat(2, n) [\n    p {\n        Part $n\n    }\n]";
+//! let mut html = Vec::new();
+//! HtmlRenderer::new().render(RepeatExpander::new(AmarkStream::new(source.as_slice())), &mut html)?;
+//!
+//! assert_eq!(
+//!     html,
+//!     b"<div class=\"p\">\nPart 1\n</div>\n<div class=\"p\">\nPart 2\n</div>\n"
+//! );
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{borrow::Cow, rc::Rc};
+
+use crate::{
+    event::{parse_count, parse_params},
+    macros::is_arg_name,
+    stream::{read_group, TokenStream},
+    AmarkError, AmarkResult, AmarkToken, OwnedToken,
+};
+
+/// The name of the item repeating its content
+const REPEAT: &[u8] = b"repeat";
+
+/// The name of the line break item
+const BR: &[u8] = b"br";
+
+/// A stream adapter which expands `repeat` and `br` items, see the [module docs](self)
+#[derive(Debug)]
+pub struct RepeatExpander<S> {
+    /// The stream of the document
+    stream: S,
+    /// The repetitions being read, the innermost last
+    frames: Vec<Frame>,
+    /// The number of tokens created by expansions so far
+    expanded: usize,
+    /// The maximum number of tokens created by expansions
+    max_tokens: usize,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+/// A repetition being read
+#[derive(Debug)]
+struct Frame {
+    /// The repeated tokens
+    body: Rc<[OwnedToken]>,
+    /// The position of the next token in the body
+    pos: usize,
+    /// The number of the current repetition, starting at 1
+    index: usize,
+    /// How often the body is repeated
+    count: usize,
+    /// The name of the index variable
+    var: Option<Vec<u8>>,
+    /// Whether items in the body are expanded, `br;` is not expanded again
+    expand: bool,
+}
+
+impl<S: TokenStream> RepeatExpander<S> {
+    /// The default of [`RepeatExpander::max_tokens`]
+    pub const DEFAULT_MAX_TOKENS: usize = 1_000_000;
+
+    /// Create an expander over the given stream
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            frames: Vec::new(),
+            expanded: 0,
+            max_tokens: Self::DEFAULT_MAX_TOKENS,
+            current: OwnedToken::End,
+        }
+    }
+
+    /// Set how many tokens expansions may create in total before reading fails
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// The next token of the innermost repetition or the stream and whether it can be expanded
+    fn next_raw(&mut self) -> AmarkResult<'static, (OwnedToken, bool)> {
+        while let Some(frame) = self.frames.last_mut() {
+            let Some(tok) = frame.body.get(frame.pos) else {
+                if frame.index < frame.count {
+                    frame.index += 1;
+                    frame.pos = 0;
+                } else {
+                    self.frames.pop();
+                }
+                continue;
+            };
+            frame.pos += 1;
+
+            self.expanded += 1;
+            if self.expanded > self.max_tokens {
+                return Err(AmarkError::ExpansionLimit {
+                    limit: self.max_tokens,
+                });
+            }
+            let tok = match (tok, &frame.var) {
+                (OwnedToken::Text(text), Some(var)) => {
+                    OwnedToken::Text(replace_var(text, var, frame.index))
+                }
+                (tok, _) => tok.clone(),
+            };
+            return Ok((tok, frame.expand));
+        }
+        Ok((self.stream.next_token()?.to_owned_token(), true))
+    }
+
+    /// Read the tokens up to the end of the group which was just opened, without the end
+    fn read_group(&mut self) -> AmarkResult<'static, Vec<OwnedToken>> {
+        read_group(|| Ok(self.next_raw()?.0), b"The end of the repeated item")
+    }
+
+    /// Read a `repeat` item after its name and start repeating it
+    fn repeat(&mut self) -> AmarkResult<'static, ()> {
+        let params = match self.next_raw()?.0 {
+            OwnedToken::ParamsStart => parse_params(self.read_group()?)?,
+            _ => Vec::new(),
+        };
+        let (count, var) = match *params.as_slice() {
+            [ref count] => (count, None),
+            [ref count, ref var] if is_arg_name(var) => (count, Some(var.clone())),
+            _ => return Err(unexpected(b"repeat(N) or repeat(N, index)", REPEAT)),
+        };
+        let count =
+            parse_count(count).ok_or_else(|| unexpected(b"A number of repetitions", count))?;
+
+        let body = match self.next_raw()?.0 {
+            OwnedToken::BlockStart | OwnedToken::ContainerStart => self.read_group()?,
+            _ => return Err(unexpected(b"A block or container to repeat", REPEAT)),
+        };
+        self.push(body, count, var, true);
+        Ok(())
+    }
+
+    /// Read a `br` item after its name and start repeating it, `br;` is handed out as it is
+    fn br(&mut self) -> AmarkResult<'static, ()> {
+        let count = match self.next_raw()?.0 {
+            OwnedToken::ParamsStart => {
+                let params = parse_params(self.read_group()?)?;
+                let count = params.first().map(Vec::as_slice).unwrap_or_default();
+                let count =
+                    parse_count(count).ok_or_else(|| unexpected(b"br(N); with a number", count))?;
+                if self.next_raw()?.0 != OwnedToken::ItemEnd {
+                    return Err(unexpected(b"br(N); without a body", BR));
+                }
+                count
+            }
+            OwnedToken::ItemEnd => 1,
+            _ => return Err(unexpected(b"br(N); without a body", BR)),
+        };
+        self.push(
+            vec![OwnedToken::ItemName(BR.to_vec()), OwnedToken::ItemEnd],
+            count,
+            None,
+            false,
+        );
+        Ok(())
+    }
+
+    /// Start repeating the given tokens
+    fn push(&mut self, body: Vec<OwnedToken>, count: usize, var: Option<Vec<u8>>, expand: bool) {
+        // Nothing is handed out for empty repetitions, they are skipped right away
+        if body.is_empty() || count == 0 {
+            return;
+        }
+        self.frames.push(Frame {
+            body: body.into(),
+            pos: 0,
+            index: 1,
+            count,
+            var,
+            expand,
+        });
+    }
+}
+
+impl<S: TokenStream> TokenStream for RepeatExpander<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        loop {
+            match self.next_raw()? {
+                (OwnedToken::ItemName(ref name), true) if name == REPEAT => self.repeat()?,
+                (OwnedToken::ItemName(ref name), true) if name == BR => self.br()?,
+                (tok, _) => {
+                    self.current = tok;
+                    return Ok(self.current.as_token());
+                }
+            }
+        }
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
+
+/// Replace every `$var` in the text by the index, other `$` stay as they are
+fn replace_var(text: &[u8], var: &[u8], index: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.iter().position(|&b| b == b'$') {
+        out.extend_from_slice(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let len = rest
+            .iter()
+            .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_')
+            .count();
+        if &rest[..len] == var {
+            out.extend_from_slice(index.to_string().as_bytes());
+            rest = &rest[len..];
+        } else {
+            out.push(b'$');
+        }
+    }
+
+    out.extend_from_slice(rest);
+    out
+}
+
+/// Create an error for an item which can't be expanded
+fn unexpected(expected: &'static [u8], got: &[u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got.to_vec()),
+    }
+}
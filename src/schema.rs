@@ -0,0 +1,493 @@
+//! Schemas declaring which items a document may use and how, checked by a [`Validator`].
+//!
+//! A schema is Amark source with an item per allowed item. `name;` allows the item in any form, a
+//! container restricts it:
+//!
+//! - `form(bare, block, container);` are the forms the item may have
+//! - `params(int, text?);` are its parameters with their types. `?` marks optional parameters at
+//!   the end, `*` allows any number of the last one. Types are `text`, `int`, `number`, `bool` or
+//!   a choice of words like `left|right`
+//! - `children(p, l);` are the items allowed directly inside of it, none if the list is empty
+//! - `parents(ul, ol);` are the items it has to be directly inside of
+//!
+//! Items which aren't declared are violations, anything which isn't restricted is allowed.
+//!
+//! ```
+//! use amark::{schema::{Schema, Validator}, AmarkStream};
+//!
+//! let schema = Schema::load(b"gb [ form(block); ]\nbr [\n    form(bare);\n    params(int?);\n]")?;
+//! let source = b"gb {\n    Hi\n    @br(2);\n}\nbr(two);\nbb { Hi }";
+//! let violations = Validator::new(&schema).validate(AmarkStream::new(source.as_slice()))?;
+//!
+//! assert_eq!(violations[0].line, 5);
+//! assert_eq!(
+//!     violations[0].to_string(),
+//!     "Parameter 1 of item \"br\" has to be an int, got \"two\""
+//! );
+//! assert_eq!(violations[1].to_string(), "Unknown item \"bb\"");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str,
+};
+
+use crate::{
+    event::{Event, EventReader, Item, ItemKind},
+    stream::TokenStream,
+    tree::{Document, Element},
+    AmarkError, AmarkResult, ByteDisp,
+};
+
+/// The items a document may use, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// The declared items by name
+    items: HashMap<Vec<u8>, Rule>,
+}
+
+/// The restrictions of a declared item, `None` if something isn't restricted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Rule {
+    /// The allowed forms
+    forms: Option<Vec<ItemKind>>,
+    /// The allowed parameters
+    params: Option<Params>,
+    /// The items allowed directly inside
+    children: Option<Vec<Vec<u8>>>,
+    /// The items one of which has to be the direct parent
+    parents: Option<Vec<Vec<u8>>>,
+}
+
+/// The parameters of an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Params {
+    /// The types of the parameters in order
+    types: Vec<ParamType>,
+    /// The number of parameters which have to be given
+    required: usize,
+    /// Whether the last type is allowed any number of times
+    repeated: bool,
+}
+
+impl Params {
+    /// The maximum number of parameters, `None` if there is none
+    fn max(&self) -> Option<usize> {
+        (!self.repeated).then_some(self.types.len())
+    }
+
+    /// The type of the parameter at the given index
+    fn get(&self, idx: usize) -> Option<&ParamType> {
+        match self.types.get(idx) {
+            Some(ty) => Some(ty),
+            None if self.repeated => self.types.last(),
+            None => None,
+        }
+    }
+}
+
+/// The type of a parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamType {
+    /// Any text
+    Text,
+    /// An integer
+    Int,
+    /// A decimal number
+    Number,
+    /// `true` or `false`
+    Bool,
+    /// One of the given words
+    Choice(Vec<Vec<u8>>),
+}
+
+impl ParamType {
+    /// Parse a type of a `params` declaration
+    fn parse(ty: &[u8]) -> AmarkResult<'static, Self> {
+        Ok(match ty {
+            b"text" => Self::Text,
+            b"int" => Self::Int,
+            b"number" => Self::Number,
+            b"bool" => Self::Bool,
+            _ if ty.contains(&b'|') => Self::Choice(
+                ty.split(|&b| b == b'|')
+                    .map(|word| word.trim_ascii().to_vec())
+                    .collect(),
+            ),
+            _ => {
+                return Err(unexpected(
+                    b"text, int, number, bool or a choice like a|b",
+                    ty,
+                ))
+            }
+        })
+    }
+
+    /// Whether the parameter has this type
+    pub fn matches(&self, param: &[u8]) -> bool {
+        let text = str::from_utf8(param).unwrap_or_default();
+        match *self {
+            Self::Text => true,
+            Self::Int => text.parse::<i64>().is_ok(),
+            Self::Number => text.parse::<f64>().is_ok_and(f64::is_finite),
+            Self::Bool => matches!(param, b"true" | b"false"),
+            Self::Choice(ref words) => words.iter().any(|word| word == param),
+        }
+    }
+}
+
+impl Display for ParamType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Text => write!(f, "a text"),
+            Self::Int => write!(f, "an int"),
+            Self::Number => write!(f, "a number"),
+            Self::Bool => write!(f, "true or false"),
+            Self::Choice(ref words) => {
+                write!(f, "one of ")?;
+                for (i, word) in words.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { "|" };
+                    write!(f, "{}{}", sep, String::from_utf8_lossy(word))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Schema {
+    /// Create a new schema without any items
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the items declared in Amark source, see the [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed, an item is declared twice or a declaration
+    /// is invalid.
+    pub fn load(definitions: &[u8]) -> AmarkResult<'static, Self> {
+        let mut schema = Self::new();
+        for element in Document::parse(definitions)?.elements() {
+            if element.item.params.is_some() || element.item.kind == ItemKind::Block {
+                return Err(unexpected(
+                    b"name; or name [ ... ] declaring an item",
+                    element.name(),
+                ));
+            }
+            let rule = parse_rule(element)?;
+            if schema.items.insert(element.name().to_vec(), rule).is_some() {
+                return Err(unexpected(
+                    b"An item which is not declared already",
+                    element.name(),
+                ));
+            }
+        }
+
+        // Typos in the schema itself would silently forbid items
+        for rule in schema.items.values() {
+            let mut names = rule.children.iter().chain(&rule.parents).flatten();
+            if let Some(name) = names.find(|name| !schema.declares(name)) {
+                return Err(unexpected(b"The name of a declared item", name));
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Whether the schema declares an item with the given name
+    pub fn declares(&self, name: &[u8]) -> bool {
+        self.items.contains_key(name)
+    }
+
+    /// The names of all declared items in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &[u8]> {
+        self.items.keys().map(Vec::as_slice)
+    }
+}
+
+/// Parse the restrictions of a declared item
+fn parse_rule(element: &Element) -> AmarkResult<'static, Rule> {
+    let mut rule = Rule::default();
+    for restriction in element.elements() {
+        let params = match restriction.item.params {
+            Some(ref params) if !restriction.has_body() => params,
+            _ => {
+                return Err(unexpected(
+                    b"A restriction like form(block);",
+                    restriction.name(),
+                ))
+            }
+        };
+        let set = match restriction.name() {
+            b"form" => rule.forms.replace(parse_forms(params)?).is_some(),
+            b"params" => rule.params.replace(parse_params(params)?).is_some(),
+            b"children" => rule.children.replace(params.clone()).is_some(),
+            b"parents" => rule.parents.replace(params.clone()).is_some(),
+            name => {
+                return Err(unexpected(b"form, params, children or parents", name));
+            }
+        };
+        if set {
+            return Err(unexpected(
+                b"A restriction which is not set already",
+                restriction.name(),
+            ));
+        }
+    }
+    Ok(rule)
+}
+
+/// Parse the forms of `form(...)`
+fn parse_forms(forms: &[Vec<u8>]) -> AmarkResult<'static, Vec<ItemKind>> {
+    forms
+        .iter()
+        .map(|form| match form.as_slice() {
+            b"bare" => Ok(ItemKind::Bare),
+            b"block" => Ok(ItemKind::Block),
+            b"container" => Ok(ItemKind::Container),
+            form => Err(unexpected(b"bare, block or container", form)),
+        })
+        .collect()
+}
+
+/// Parse the types of `params(...)`
+fn parse_params(params: &[Vec<u8>]) -> AmarkResult<'static, Params> {
+    let mut parsed = Params {
+        types: Vec::with_capacity(params.len()),
+        required: 0,
+        repeated: false,
+    };
+    for (i, param) in params.iter().enumerate() {
+        let (ty, optional) = match param.split_last() {
+            Some((b'*', ty)) if i + 1 == params.len() => {
+                parsed.repeated = true;
+                (ty, true)
+            }
+            Some((b'?', ty)) => (ty, true),
+            _ => (param.as_slice(), false),
+        };
+        if !optional {
+            if parsed.required != i {
+                return Err(unexpected(b"Optional parameters at the end", param));
+            }
+            parsed.required += 1;
+        }
+        parsed.types.push(ParamType::parse(ty.trim_ascii())?);
+    }
+    Ok(parsed)
+}
+
+/// A place where a document doesn't follow its schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The name of the item
+    pub name: Vec<u8>,
+    /// The line the name of the item is on
+    pub line: usize,
+    /// What is wrong with the item
+    pub kind: ViolationKind,
+}
+
+/// What is wrong with an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The item isn't declared
+    UnknownItem,
+    /// The item has a form it may not have
+    Form(ItemKind),
+    /// The item has too few or too many parameters
+    ParamCount {
+        /// The number of parameters of the item
+        got: usize,
+        /// The number of parameters which have to be given
+        min: usize,
+        /// The maximum number of parameters, `None` if there is none
+        max: Option<usize>,
+    },
+    /// A parameter doesn't have its type
+    ParamType {
+        /// The index of the parameter
+        idx: usize,
+        /// The type of the parameter
+        expected: ParamType,
+        /// The parameter
+        got: Vec<u8>,
+    },
+    /// The item isn't allowed inside of its parent
+    Child {
+        /// The name of the parent
+        parent: Vec<u8>,
+    },
+    /// The item isn't directly inside of one of the items it has to be in
+    Parent {
+        /// The names of the allowed parents
+        parents: Vec<Vec<u8>>,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = ByteDisp(&self.name);
+        match self.kind {
+            ViolationKind::UnknownItem => write!(f, "Unknown item {}", name),
+            ViolationKind::Form(kind) => {
+                let form = match kind {
+                    ItemKind::Bare => "bare",
+                    ItemKind::Block => "a block",
+                    ItemKind::Container => "a container",
+                };
+                write!(f, "Item {} can't be {}", name, form)
+            }
+            ViolationKind::ParamCount { got, min, max } => {
+                write!(f, "Item {} takes ", name)?;
+                match max {
+                    Some(max) if max == min => write!(f, "{}", min)?,
+                    Some(max) => write!(f, "{} to {}", min, max)?,
+                    None => write!(f, "at least {}", min)?,
+                }
+                let plural = if (min, max) == (1, Some(1)) { "" } else { "s" };
+                write!(f, " parameter{}, got {}", plural, got)
+            }
+            ViolationKind::ParamType {
+                idx,
+                ref expected,
+                ref got,
+            } => write!(
+                f,
+                "Parameter {} of item {} has to be {}, got {}",
+                idx + 1,
+                name,
+                expected,
+                ByteDisp(got)
+            ),
+            ViolationKind::Child { ref parent } => {
+                write!(f, "Item {} isn't allowed in {}", name, ByteDisp(parent))
+            }
+            ViolationKind::Parent { ref parents } => {
+                write!(f, "Item {} has to be inside of ", name)?;
+                for (i, parent) in parents.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { " or " };
+                    write!(f, "{}{}", sep, ByteDisp(parent))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks documents against a [`Schema`]
+#[derive(Debug, Clone, Copy)]
+pub struct Validator<'s> {
+    /// The schema documents are checked against
+    schema: &'s Schema,
+}
+
+impl<'s> Validator<'s> {
+    /// Create a validator checking against the given schema
+    pub fn new(schema: &'s Schema) -> Self {
+        Self { schema }
+    }
+
+    /// Check all tokens of the given stream and return the violations in order
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors.
+    pub fn validate<S: TokenStream>(&self, stream: S) -> AmarkResult<'static, Vec<Violation>> {
+        let mut reader = EventReader::new(stream);
+        let mut violations = Vec::new();
+
+        loop {
+            let item = match reader.next_event()? {
+                Some(Event::Start(item)) => item.clone(),
+                Some(Event::Empty(item)) => item,
+                Some(_) => continue,
+                None => return Ok(violations),
+            };
+            // A started item is open already
+            let open = match item.kind {
+                ItemKind::Bare => reader.open_items(),
+                _ => &reader.open_items()[..reader.open_items().len() - 1],
+            };
+            let parent = open.last().map(|parent| parent.name.as_slice());
+            self.check(&item, parent, reader.item_line(), &mut violations);
+        }
+    }
+
+    /// Check a single item inside of the given parent
+    fn check(
+        &self,
+        item: &Item,
+        parent: Option<&[u8]>,
+        line: usize,
+        violations: &mut Vec<Violation>,
+    ) {
+        let mut violation = |kind| {
+            violations.push(Violation {
+                name: item.name.clone(),
+                line,
+                kind,
+            })
+        };
+
+        let Some(rule) = self.schema.items.get(&item.name) else {
+            return violation(ViolationKind::UnknownItem);
+        };
+
+        if rule.forms.as_ref().is_some_and(|f| !f.contains(&item.kind)) {
+            violation(ViolationKind::Form(item.kind));
+        }
+
+        if let Some(ref params) = rule.params {
+            let got = item.params().len();
+            if got < params.required || params.max().is_some_and(|max| got > max) {
+                violation(ViolationKind::ParamCount {
+                    got,
+                    min: params.required,
+                    max: params.max(),
+                });
+            }
+            for (idx, param) in item.params().iter().enumerate() {
+                match params.get(idx) {
+                    Some(ty) if !ty.matches(param) => violation(ViolationKind::ParamType {
+                        idx,
+                        expected: ty.clone(),
+                        got: param.clone(),
+                    }),
+                    _ => (),
+                }
+            }
+        }
+
+        let children = parent
+            .and_then(|parent| self.schema.items.get(parent))
+            .and_then(|rule| rule.children.as_ref());
+        if let (Some(parent), Some(children)) = (parent, children) {
+            if !children.contains(&item.name) {
+                violation(ViolationKind::Child {
+                    parent: parent.to_vec(),
+                });
+            }
+        }
+
+        if let Some(ref parents) = rule.parents {
+            if !parent.is_some_and(|parent| parents.iter().any(|p| p == parent)) {
+                violation(ViolationKind::Parent {
+                    parents: parents.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Create an error for an invalid declaration
+fn unexpected(expected: &'static [u8], got: &[u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got.to_vec()),
+    }
+}
@@ -0,0 +1,729 @@
+//! Serialize Rust data structures to Amark with serde.
+//!
+//! The value is mapped onto items which are written by an [`AmarkWriter`], the output reads back
+//! with [`from_slice`](crate::from_slice):
+//!
+//! - Structs and maps are containers of items named by their fields or keys, at the top level
+//!   the items are written directly. Fields which are `None` are left out.
+//! - Strings and characters are blocks, numbers and booleans a single parameter like `price(5);`.
+//! - Sequences and tuples of numbers and booleans are parameter lists, other sequences are
+//!   containers of items named after the struct or variant they hold, otherwise `item`.
+//! - Units and unit variants are items ended by `;`, enum variants are items named by the
+//!   variant inside of a container like `color [ Red; ]` or directly inside of a sequence.
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Card {
+//!     title: String,
+//!     price: u32,
+//!     tags: Vec<u32>,
+//! }
+//!
+//! let card = Card {
+//!     title: "Green box".into(),
+//!     price: 5,
+//!     tags: vec![1, 2],
+//! };
+//! let source = amark::to_string(&card)?;
+//! assert_eq!(source, "title {\n    Green box\n}\nprice(5);\ntags(1,2);\n");
+//! # Ok::<(), amark::SerdeError>(())
+//! ```
+
+use std::{fmt::Display, io::Write};
+
+use serde::{
+    ser::{
+        self, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+        SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Serialize, Serializer,
+};
+
+use crate::{validate_item_name, AmarkResult, AmarkWriter, SerdeError};
+
+/// The name of items in sequences which aren't named by a struct or variant
+const ELEMENT_NAME: &[u8] = b"item";
+
+/// Serialize the value as Amark into `writer`
+///
+/// # Errors
+///
+/// This errors when the value can't be represented in Amark or writing fails.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<(), SerdeError> {
+    let value = value.serialize(ValueSerializer)?;
+    if let Value::Param(_) | Value::Text(_) = value {
+        return Err(ser::Error::custom(
+            "expected a struct, map, sequence or enum at the top level",
+        ));
+    }
+
+    let mut writer = AmarkWriter::new(writer);
+    match value {
+        Value::Map { entries, .. } => write_items(&mut writer, entries),
+        Value::Seq(values) => values
+            .into_iter()
+            .try_for_each(|value| write_element(&mut writer, value)),
+        Value::None | Value::Unit => Ok(()),
+        value => write_element(&mut writer, value),
+    }
+    .and_then(|()| writer.finish())
+    .map(drop)
+    .map_err(SerdeError::Write)
+}
+
+/// Serialize the value as Amark source
+///
+/// # Errors
+///
+/// This errors when the value can't be represented in Amark.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerdeError> {
+    let mut source = Vec::new();
+    to_writer(&mut source, value)?;
+    String::from_utf8(source).map_err(ser::Error::custom)
+}
+
+/// Write the given items
+fn write_items<W: Write>(
+    writer: &mut AmarkWriter<W>,
+    entries: Vec<(Vec<u8>, Value)>,
+) -> AmarkResult<'static, ()> {
+    entries
+        .into_iter()
+        .try_for_each(|(name, value)| write_item(writer, &name, value))
+}
+
+/// Write an element of a sequence, enum variants and structs name the item
+fn write_element<W: Write>(writer: &mut AmarkWriter<W>, value: Value) -> AmarkResult<'static, ()> {
+    match value {
+        Value::Variant { name, value } => write_item(writer, name.as_bytes(), *value),
+        Value::Map {
+            name: Some(name), ..
+        } => write_item(writer, name.as_bytes(), value),
+        value => write_item(writer, ELEMENT_NAME, value),
+    }
+}
+
+/// Write the value as item with the given name
+fn write_item<W: Write>(
+    writer: &mut AmarkWriter<W>,
+    name: &[u8],
+    value: Value,
+) -> AmarkResult<'static, ()> {
+    match value {
+        Value::None => return Ok(()),
+        Value::Unit => writer.item(name)?,
+        Value::Param(param) => writer.item(name)?.params([param])?,
+        Value::Text(text) => writer.item(name)?.block(|w| w.text(text).map(drop))?,
+        Value::Seq(values) => match into_params(values) {
+            Ok(params) if params.is_empty() => writer.item(name)?,
+            Ok(params) => writer.item(name)?.params(params)?,
+            Err(values) => writer.item(name)?.container(|w| {
+                values
+                    .into_iter()
+                    .try_for_each(|value| write_element(w, value))
+            })?,
+        },
+        Value::Map { entries, .. } => writer.item(name)?.container(|w| write_items(w, entries))?,
+        value @ Value::Variant { .. } => {
+            writer.item(name)?.container(|w| write_element(w, value))?
+        }
+    };
+
+    Ok(())
+}
+
+/// The parameters of a sequence if all of its elements are written as parameters
+fn into_params(values: Vec<Value>) -> Result<Vec<Vec<u8>>, Vec<Value>> {
+    if !values.iter().all(|value| matches!(*value, Value::Param(_))) {
+        return Err(values);
+    }
+
+    Ok(values
+        .into_iter()
+        .filter_map(|value| match value {
+            Value::Param(param) => Some(param),
+            _ => None,
+        })
+        .collect())
+}
+
+/// A serialized value before it is written
+#[derive(Debug)]
+enum Value {
+    /// A value written as a single parameter
+    Param(Vec<u8>),
+    /// A value written as the text of a block
+    Text(String),
+    /// A value that is left out
+    None,
+    /// An item without content
+    Unit,
+    /// The elements of a sequence or tuple
+    Seq(Vec<Value>),
+    /// The fields of a struct or the entries of a map
+    Map {
+        /// The name of the struct
+        name: Option<&'static str>,
+        /// The field or key names with their values
+        entries: Vec<(Vec<u8>, Value)>,
+    },
+    /// An enum variant
+    Variant {
+        /// The name of the variant
+        name: &'static str,
+        /// The content of the variant
+        value: Box<Value>,
+    },
+}
+
+impl Value {
+    /// Wrap the value in the given variant if there is one
+    fn in_variant(self, variant: Option<&'static str>) -> Self {
+        match variant {
+            Some(name) => Self::Variant {
+                name,
+                value: Box::new(self),
+            },
+            None => self,
+        }
+    }
+}
+
+/// Create a parameter value from its text
+fn param(value: impl Display) -> Result<Value, SerdeError> {
+    Ok(Value::Param(value.to_string().into_bytes()))
+}
+
+/// Serializes values into [`Value`]s
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = SeqBuilder;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = MapBuilder;
+    type SerializeStructVariant = MapBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerdeError> {
+        param(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerdeError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerdeError> {
+        Ok(Value::Text(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerdeError> {
+        v.iter()
+            .map(|&b| param(b))
+            .collect::<Result<_, _>>()
+            .map(Value::Seq)
+    }
+
+    fn serialize_none(self) -> Result<Value, SerdeError> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerdeError> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerdeError> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerdeError> {
+        Ok(Value::Unit.in_variant(Some(variant)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, SerdeError> {
+        Ok(value.serialize(self)?.in_variant(Some(variant)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, SerdeError> {
+        Ok(SeqBuilder::new(None, len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, SerdeError> {
+        Ok(SeqBuilder::new(None, len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, SerdeError> {
+        Ok(SeqBuilder::new(None, len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, SerdeError> {
+        Ok(SeqBuilder::new(Some(variant), len))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapBuilder, SerdeError> {
+        Ok(MapBuilder::new(None, None, len.unwrap_or(0)))
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<MapBuilder, SerdeError> {
+        Ok(MapBuilder::new(Some(name), None, len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MapBuilder, SerdeError> {
+        Ok(MapBuilder::new(None, Some(variant), len))
+    }
+}
+
+/// Collects the elements of sequences and tuples
+struct SeqBuilder {
+    /// The enum variant the elements belong to
+    variant: Option<&'static str>,
+    /// The collected elements
+    values: Vec<Value>,
+}
+
+impl SeqBuilder {
+    /// Create a builder for a sequence of about `len` elements
+    fn new(variant: Option<&'static str>, len: usize) -> Self {
+        Self {
+            variant,
+            values: Vec::with_capacity(len),
+        }
+    }
+
+    /// Add an element, `None` has no item to leave out in a sequence
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        match value.serialize(ValueSerializer)? {
+            Value::None => Err(ser::Error::custom(
+                "None can only be the value of a field or map entry",
+            )),
+            value => {
+                self.values.push(value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Build the sequence
+    fn build(self) -> Value {
+        Value::Seq(self.values).in_variant(self.variant)
+    }
+}
+
+impl SerializeSeq for SeqBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+impl SerializeTuple for SeqBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+impl SerializeTupleStruct for SeqBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+impl SerializeTupleVariant for SeqBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+/// Collects the fields of structs and the entries of maps
+struct MapBuilder {
+    /// The name of the struct
+    name: Option<&'static str>,
+    /// The enum variant the fields belong to
+    variant: Option<&'static str>,
+    /// The collected fields or entries
+    entries: Vec<(Vec<u8>, Value)>,
+    /// The key of the entry whose value is serialized next
+    key: Option<Vec<u8>>,
+}
+
+impl MapBuilder {
+    /// Create a builder for about `len` fields or entries
+    fn new(name: Option<&'static str>, variant: Option<&'static str>, len: usize) -> Self {
+        Self {
+            name,
+            variant,
+            entries: Vec::with_capacity(len),
+            key: None,
+        }
+    }
+
+    /// Add a field or entry
+    fn push<T: Serialize + ?Sized>(&mut self, key: Vec<u8>, value: &T) -> Result<(), SerdeError> {
+        validate_item_name(&key).map_err(ser::Error::custom)?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    /// Build the struct or map
+    fn build(self) -> Value {
+        Value::Map {
+            name: self.name,
+            entries: self.entries,
+        }
+        .in_variant(self.variant)
+    }
+}
+
+impl SerializeMap for MapBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self.key.take().unwrap_or_default();
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+impl SerializeStruct for MapBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.push(key.into(), value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+impl SerializeStructVariant for MapBuilder {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.push(key.into(), value)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        Ok(self.build())
+    }
+}
+
+/// Serializes map keys into item names
+struct KeySerializer;
+
+impl KeySerializer {
+    /// Create an item name from the text of a key
+    fn name(key: impl Display) -> Result<Vec<u8>, SerdeError> {
+        Ok(key.to_string().into_bytes())
+    }
+
+    /// Create an error for keys which can't be item names
+    fn unsupported() -> SerdeError {
+        ser::Error::custom("map keys must be strings, characters, numbers or booleans")
+    }
+}
+
+impl Serializer for KeySerializer {
+    type Ok = Vec<u8>;
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<Vec<u8>, SerdeError>;
+    type SerializeTuple = Impossible<Vec<u8>, SerdeError>;
+    type SerializeTupleStruct = Impossible<Vec<u8>, SerdeError>;
+    type SerializeTupleVariant = Impossible<Vec<u8>, SerdeError>;
+    type SerializeMap = Impossible<Vec<u8>, SerdeError>;
+    type SerializeStruct = Impossible<Vec<u8>, SerdeError>;
+    type SerializeStructVariant = Impossible<Vec<u8>, SerdeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Vec<u8>, SerdeError> {
+        Self::name(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_none(self) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Vec<u8>, SerdeError> {
+        Self::name(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<u8>, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<u8>, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        Err(Self::unsupported())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Err(Self::unsupported())
+    }
+}
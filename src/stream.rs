@@ -0,0 +1,150 @@
+//! A common interface over sources of tokens, the [`AmarkReader`] bundled with its input and any
+//! adapter that transforms tokens on their way to a renderer.
+
+use std::{borrow::Cow, io::BufRead, vec};
+
+use crate::{AmarkError, AmarkReader, AmarkResult, AmarkToken, Metadata, OwnedToken};
+
+/// A source of [`AmarkToken`]s
+///
+/// Tokens borrow the stream, so they are only valid until the next call to
+/// [`TokenStream::next_token`]. Errors never borrow the stream so they can be passed on freely.
+pub trait TokenStream {
+    /// Produce the next token, [`AmarkToken::End`] is returned once the input is exhausted
+    ///
+    /// # Errors
+    ///
+    /// This errors when the underlying input can't be read or parsed.
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>>;
+
+    /// The line of the input the stream is currently at
+    fn cur_line(&self) -> usize;
+}
+
+impl<S: TokenStream + ?Sized> TokenStream for &mut S {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        (**self).next_token()
+    }
+
+    fn cur_line(&self) -> usize {
+        (**self).cur_line()
+    }
+}
+
+impl<S: TokenStream + ?Sized> TokenStream for Box<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        (**self).next_token()
+    }
+
+    fn cur_line(&self) -> usize {
+        (**self).cur_line()
+    }
+}
+
+/// An [`AmarkReader`] bundled with the input it reads from
+#[derive(Debug)]
+pub struct AmarkStream<B> {
+    /// The reader parsing the input
+    reader: AmarkReader,
+    /// The input
+    input: B,
+}
+
+impl<B: BufRead> AmarkStream<B> {
+    /// Create a new stream with a fresh [`AmarkReader`] over the given input
+    pub fn new(input: B) -> Self {
+        Self::with_reader(AmarkReader::new(), input)
+    }
+
+    /// Create a new stream with the given reader, e.g. to reuse its buffer
+    pub fn with_reader(reader: AmarkReader, input: B) -> Self {
+        Self { reader, input }
+    }
+
+    /// Read the front matter at the start of the input, see [`AmarkReader::read_metadata`]
+    ///
+    /// # Errors
+    ///
+    /// This errors when the input can't be read, the front matter is malformed or tokens were
+    /// read already.
+    pub fn read_metadata(&mut self) -> AmarkResult<'static, Option<Metadata>> {
+        self.reader.read_metadata(&mut self.input)
+    }
+
+    /// Split the stream into the reader and the input
+    pub fn into_inner(self) -> (AmarkReader, B) {
+        (self.reader, self.input)
+    }
+}
+
+impl<B: BufRead> TokenStream for AmarkStream<B> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        self.reader
+            .parse_next(&mut self.input)
+            .map_err(|e| e.to_owned())
+    }
+
+    fn cur_line(&self) -> usize {
+        self.reader.cur_line()
+    }
+}
+
+/// Tokens which are read again as a stream
+pub(crate) struct Replay {
+    /// The tokens not read yet
+    tokens: vec::IntoIter<OwnedToken>,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+impl Replay {
+    /// Create a stream of the given tokens, [`AmarkToken::End`] follows them
+    pub(crate) fn new(tokens: Vec<OwnedToken>) -> Self {
+        Self {
+            tokens: tokens.into_iter(),
+            current: OwnedToken::End,
+        }
+    }
+}
+
+impl TokenStream for Replay {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        self.current = self.tokens.next().unwrap_or(OwnedToken::End);
+        Ok(self.current.as_token())
+    }
+
+    fn cur_line(&self) -> usize {
+        0
+    }
+}
+
+/// Read the tokens up to the end of the group which was just opened, without the end
+pub(crate) fn read_group(
+    mut next: impl FnMut() -> AmarkResult<'static, OwnedToken>,
+    expected: &'static [u8],
+) -> AmarkResult<'static, Vec<OwnedToken>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0_usize;
+
+    loop {
+        let tok = next()?;
+        match tok {
+            OwnedToken::BlockStart | OwnedToken::ContainerStart | OwnedToken::ParamsStart => {
+                depth += 1;
+            }
+            OwnedToken::BlockEnd | OwnedToken::ContainerEnd | OwnedToken::ParamsEnd => {
+                if depth == 0 {
+                    return Ok(tokens);
+                }
+                depth -= 1;
+            }
+            OwnedToken::End => {
+                return Err(AmarkError::UnexpectedEof {
+                    expected: Cow::Borrowed(expected),
+                })
+            }
+            _ => (),
+        }
+        tokens.push(tok);
+    }
+}
@@ -0,0 +1,314 @@
+//! A renderer which fills templates per item name instead of hardcoded elements.
+//!
+//! A template is text with placeholders: `{{body}}` is the rendered content of the item,
+//! `{{param.0}}` its first parameter, `{{name}}` its name and `{{#repeat param.0}}<br>{{/repeat}}`
+//! repeats its content as often as the first parameter or a number says. Text is written like
+//! the [`HtmlRenderer`](crate::html::HtmlRenderer) writes it, parameters and names are
+//! HTML-escaped.
+//!
+//! Definitions are loaded from Amark source with an item per template, the template is the only
+//! parameter or the text of a block. `,` and `)` are written as `\,` and `\)` in parameters,
+//! `}` as `\}` in blocks:
+//!
+//! ```
+//! use amark::{template::TemplateRenderer, AmarkStream};
+//!
+//! let renderer = TemplateRenderer::load(
+//!     br#"gb(<div class="box green">{{body}}</div>);
+//! br({{#repeat param.0}}<br>{{/repeat}});"#,
+//! )?;
+//! let source = b"gb {\n    Hi\n}\nbr(2);\nl;";
+//! let mut html = Vec::new();
+//! let missing = renderer.render(AmarkStream::new(source.as_slice()), &mut html)?;
+//!
+//! assert_eq!(html, b"<div class=\"box green\">Hi\n</div><br><br>");
+//! assert_eq!(missing[0].line, 5);
+//! assert_eq!(missing[0].to_string(), "No template for item \"l\"");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    str,
+};
+
+use crate::{
+    event::{parse_count, Item},
+    html::{write_escaped, HtmlText},
+    stream::TokenStream,
+    tree::{Document, Element, Node},
+    AmarkError, AmarkResult, ByteDisp,
+};
+
+/// A parsed template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// The literal text and placeholders in order
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse a template, see the [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// This errors when a placeholder is unknown or not closed or a repeated section doesn't end.
+    pub fn parse(source: impl AsRef<[u8]>) -> AmarkResult<'static, Self> {
+        let mut rest = source.as_ref();
+        let parts = parse_parts(&mut rest, false)?;
+        Ok(Self { parts })
+    }
+}
+
+/// A piece of a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    /// Text written as it is
+    Literal(Vec<u8>),
+    /// `{{body}}`
+    Body,
+    /// `{{name}}`
+    Name,
+    /// `{{param.N}}`
+    Param(usize),
+    /// `{{#repeat N}}...{{/repeat}}`
+    Repeat(Count, Vec<Part>),
+}
+
+/// How often a section is repeated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Count {
+    /// A fixed number of times
+    Fixed(usize),
+    /// As often as a parameter says, once if it is missing or no number
+    Param(usize),
+}
+
+impl Count {
+    /// The number of repetitions for the given item
+    fn resolve(self, item: &Item) -> usize {
+        match self {
+            Self::Fixed(count) => count,
+            Self::Param(idx) => item.param(idx).and_then(parse_count).unwrap_or(1),
+        }
+    }
+}
+
+/// An item without a template, it is rendered as its content alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingTemplate {
+    /// The name of the item
+    pub name: Vec<u8>,
+    /// The line the name of the item is on
+    pub line: usize,
+}
+
+impl Display for MissingTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "No template for item {}", ByteDisp(&self.name))
+    }
+}
+
+/// Renders Amark documents by filling a template per item
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRenderer {
+    /// The templates by item name
+    templates: HashMap<Vec<u8>, Template>,
+}
+
+impl TemplateRenderer {
+    /// Create a new renderer without any templates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the templates defined in Amark source, see the [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed, an item defines no template or a template is
+    /// invalid.
+    pub fn load(definitions: &[u8]) -> AmarkResult<'static, Self> {
+        let mut renderer = Self::new();
+        for element in Document::parse(definitions)?.elements() {
+            let template = match element.item.params() {
+                _ if element.has_body() => element.text(),
+                [template] => template.clone(),
+                _ => {
+                    return Err(AmarkError::UnexpectedInput {
+                        expected: Cow::Borrowed(b"A template as block or single parameter"),
+                        got: Cow::Owned(element.name().to_vec()),
+                    })
+                }
+            };
+            renderer = renderer.template(element.name(), Template::parse(template)?);
+        }
+        Ok(renderer)
+    }
+
+    /// Render items with the given name with the given template
+    pub fn template(mut self, name: impl Into<Vec<u8>>, template: Template) -> Self {
+        self.templates.insert(name.into(), template);
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer and return the items without template
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, Vec<MissingTemplate>> {
+        let document = Document::read(stream)?;
+        let mut missing = Vec::new();
+        self.render_nodes(&document.children, writer, &mut missing)?;
+        writer.flush()?;
+        Ok(missing)
+    }
+
+    /// Render the nodes and collect the items without template
+    fn render_nodes<W: Write>(
+        &self,
+        nodes: &[Node],
+        writer: &mut W,
+        missing: &mut Vec<MissingTemplate>,
+    ) -> io::Result<()> {
+        let mut text = HtmlText::default();
+        for node in nodes {
+            match *node {
+                Node::Element(ref element) => {
+                    text.end_line(writer)?;
+                    self.render_element(element, writer, missing)?;
+                }
+                Node::Text(ref line) => text.text(line, writer)?,
+                Node::Escape(ref escape) => text.escape(escape, writer)?,
+                Node::EmptyLine => text.empty_line(writer)?,
+            }
+        }
+        text.end_line(writer)
+    }
+
+    /// Render an item with its template or only its content if it has none
+    fn render_element<W: Write>(
+        &self,
+        element: &Element,
+        writer: &mut W,
+        missing: &mut Vec<MissingTemplate>,
+    ) -> io::Result<()> {
+        let Some(template) = self.templates.get(element.name()) else {
+            missing.push(MissingTemplate {
+                name: element.name().to_vec(),
+                line: element.line,
+            });
+            return self.render_nodes(&element.children, writer, missing);
+        };
+
+        let mut body = Vec::new();
+        self.render_nodes(&element.children, &mut body, missing)?;
+        fill(&template.parts, &element.item, &body, writer)
+    }
+}
+
+/// Write the parts of a template for the item with the rendered body
+fn fill<W: Write>(parts: &[Part], item: &Item, body: &[u8], writer: &mut W) -> io::Result<()> {
+    for part in parts {
+        match *part {
+            Part::Literal(ref text) => writer.write_all(text)?,
+            Part::Body => writer.write_all(body)?,
+            Part::Name => write_escaped(&item.name, writer)?,
+            Part::Param(idx) => write_escaped(item.param(idx).unwrap_or_default(), writer)?,
+            Part::Repeat(count, ref parts) => {
+                for _ in 0..count.resolve(item) {
+                    fill(parts, item, body, writer)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse parts up to the end of the template or the end of a repeated section
+fn parse_parts(rest: &mut &[u8], in_repeat: bool) -> AmarkResult<'static, Vec<Part>> {
+    let mut parts = Vec::new();
+
+    loop {
+        let Some(start) = find(rest, b"{{") else {
+            if in_repeat {
+                return Err(AmarkError::UnexpectedEof {
+                    expected: Cow::Borrowed(b"{{/repeat}}"),
+                });
+            }
+            if !rest.is_empty() {
+                parts.push(Part::Literal(rest.to_vec()));
+            }
+            return Ok(parts);
+        };
+        if start > 0 {
+            parts.push(Part::Literal(rest[..start].to_vec()));
+        }
+
+        let tag_start = start + 2;
+        let end = find(&rest[tag_start..], b"}}").ok_or(AmarkError::UnexpectedEof {
+            expected: Cow::Borrowed(b"}} after a placeholder"),
+        })?;
+        let tag = rest[tag_start..tag_start + end].trim_ascii();
+        *rest = &rest[tag_start + end + 2..];
+
+        let part = match tag {
+            b"body" => Part::Body,
+            b"name" => Part::Name,
+            b"/repeat" if in_repeat => return Ok(parts),
+            _ => {
+                if let Some(count) = tag.strip_prefix(b"#repeat ") {
+                    let count = parse_repeat_count(count.trim_ascii())?;
+                    Part::Repeat(count, parse_parts(rest, true)?)
+                } else {
+                    Part::Param(parse_param(tag).ok_or_else(|| unknown_placeholder(tag))?)
+                }
+            }
+        };
+        parts.push(part);
+    }
+}
+
+/// Parse the count of a repeated section, `param.N` or a number
+fn parse_repeat_count(count: &[u8]) -> AmarkResult<'static, Count> {
+    if let Some(idx) = parse_param(count) {
+        return Ok(Count::Param(idx));
+    }
+    parse_count(count)
+        .map(Count::Fixed)
+        .ok_or_else(|| AmarkError::UnexpectedInput {
+            expected: Cow::Borrowed(b"param.N or a number to repeat by"),
+            got: Cow::Owned(count.to_vec()),
+        })
+}
+
+/// Parse the index of a `param.N` placeholder
+fn parse_param(tag: &[u8]) -> Option<usize> {
+    str::from_utf8(tag.strip_prefix(b"param.")?)
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Create an error for a placeholder which doesn't exist
+fn unknown_placeholder(tag: &[u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(b"{{body}}, {{name}}, {{param.N}} or {{#repeat N}}"),
+        got: Cow::Owned(tag.to_vec()),
+    }
+}
+
+/// The position of the first occurrence of `needle`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,277 @@
+//! A plain text renderer for search indexes and previews. All markup is dropped, the words of the
+//! text are joined by spaces and empty lines separate paragraphs.
+//!
+//! Items between other items start a new paragraph, items inside of text continue it. `\n` and
+//! `\s` are written as line breaks and spaces, every other escape sequence as its character.
+//! `br(N);` inside of text breaks the line N times, between items it is left out.
+
+use std::io::{self, Write};
+
+use crate::{
+    event::{parse_count, Event, EventReader, ItemKind},
+    stream::TokenStream,
+    AmarkResult,
+};
+
+/// The name of the item breaking the line
+const BR: &[u8] = b"br";
+
+/// Renders Amark documents as plain text
+#[derive(Debug, Clone, Default)]
+pub struct TextRenderer {
+    /// The column lines are wrapped at
+    width: Option<usize>,
+    /// Whether items between other items are introduced by their name
+    labels: bool,
+}
+
+impl TextRenderer {
+    /// Create a new renderer without word wrapping and labels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap lines between words so they are at most `width` characters long where possible
+    pub fn wrap(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Introduce every item between other items by its name on a line of its own, like `gb:`
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Render all tokens of the given stream to the writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors or writing fails.
+    pub fn render<S: TokenStream, W: Write>(
+        &self,
+        stream: S,
+        writer: &mut W,
+    ) -> AmarkResult<'static, ()> {
+        let mut events = EventReader::new(stream);
+        let mut output = Output {
+            writer,
+            width: self.width,
+            column: 0,
+            written: false,
+            sep: Sep::None,
+            last: Last::Start,
+        };
+        // Whether each open item holds text, the top level doesn't
+        let mut in_text = vec![false];
+
+        while let Some(event) = events.next_event()? {
+            match event {
+                Event::Start(item) => {
+                    let inline = in_text.last().copied().unwrap_or_default();
+                    if inline {
+                        output.inline();
+                    } else {
+                        output.separate(Sep::Paragraph);
+                        if self.labels {
+                            output.word(&item.name)?;
+                            output.glued(b":")?;
+                            output.separate(Sep::Line);
+                        }
+                    }
+                    in_text.push(item.kind == ItemKind::Block);
+                }
+                Event::End(_) => {
+                    in_text.pop();
+                    if in_text.last().copied().unwrap_or_default() {
+                        output.last = Last::Text;
+                    } else {
+                        output.separate(Sep::Paragraph);
+                    }
+                }
+                Event::Empty(item) if item.name == BR && in_text.last() == Some(&true) => {
+                    output.br(item.param(0).and_then(parse_count).unwrap_or(1))?;
+                }
+                Event::Empty(_) => (),
+                Event::Text(text) => output.text(text)?,
+                Event::Escape(escape) => {
+                    for _ in 0..escape.count() {
+                        match escape.seq {
+                            b'n' => output.line_break()?,
+                            b's' => output.glued(b" ")?,
+                            _ => output.glued(&[escape.resolved()])?,
+                        }
+                    }
+                }
+                Event::EmptyLine => output.empty_line(),
+            }
+        }
+
+        if output.written {
+            output.writer.write_all(b"\n")?;
+        }
+        output.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Whitespace owed between the last and the next output, larger ones replace smaller ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    /// Nothing
+    None,
+    /// A space or a line break when the line is full
+    Space,
+    /// A line break
+    Line,
+    /// An empty line
+    Paragraph,
+}
+
+/// What was written last, text continues after escape sequences without a space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Nothing or a separator
+    Start,
+    /// A line of text or an item inside of text
+    Text,
+    /// An escape sequence
+    Escape,
+}
+
+/// The state of the output while rendering
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The column lines are wrapped at
+    width: Option<usize>,
+    /// The number of characters in the current line
+    column: usize,
+    /// Whether anything was written yet
+    written: bool,
+    /// The whitespace owed before the next output
+    sep: Sep,
+    /// What was written last
+    last: Last,
+}
+
+impl<W: Write> Output<'_, W> {
+    /// Owe at least the given whitespace before the next output
+    fn separate(&mut self, sep: Sep) {
+        self.sep = self.sep.max(sep);
+        self.last = Last::Start;
+    }
+
+    /// Separate an item inside of text from text before it
+    fn inline(&mut self) {
+        if self.last == Last::Text {
+            self.sep = self.sep.max(Sep::Space);
+        }
+    }
+
+    /// Write the words of a line of text
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        self.inline();
+        let mut words = text
+            .split(u8::is_ascii_whitespace)
+            .filter(|w| !w.is_empty());
+        let Some(first) = words.next() else {
+            return Ok(());
+        };
+        self.word(first)?;
+        for word in words {
+            self.sep = Sep::Space;
+            self.word(word)?;
+        }
+        // Trailing whitespace is kept before escape sequences on the same line
+        if text.last().is_some_and(u8::is_ascii_whitespace) {
+            self.sep = Sep::Space;
+        }
+        self.last = Last::Text;
+        Ok(())
+    }
+
+    /// Write a word, wrapping the line before it if it doesn't fit anymore
+    fn word(&mut self, word: &[u8]) -> io::Result<()> {
+        let len = String::from_utf8_lossy(word).chars().count();
+        if self.sep == Sep::Space
+            && self
+                .width
+                .is_some_and(|width| self.column > 0 && self.column + 1 + len > width)
+        {
+            self.sep = Sep::Line;
+        }
+        self.write_sep()?;
+        self.writer.write_all(word)?;
+        self.column += len;
+        Ok(())
+    }
+
+    /// Write text which sticks to what was written before
+    fn glued(&mut self, text: &[u8]) -> io::Result<()> {
+        self.write_sep()?;
+        self.writer.write_all(text)?;
+        self.column += String::from_utf8_lossy(text).chars().count();
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Write an explicit line break
+    fn line_break(&mut self) -> io::Result<()> {
+        if self.sep >= Sep::Line {
+            self.write_sep()?;
+        }
+        self.sep = Sep::None;
+        self.writer.write_all(b"\n")?;
+        self.column = 0;
+        self.written = true;
+        self.last = Last::Escape;
+        Ok(())
+    }
+
+    /// Break the line for a `br` item, unlike after `\n` an empty line after it starts a new
+    /// paragraph
+    fn br(&mut self, count: usize) -> io::Result<()> {
+        for _ in 0..count {
+            self.line_break()?;
+        }
+        self.last = Last::Start;
+        Ok(())
+    }
+
+    /// Separate paragraphs, an empty line after an escape sequence is only the end of its line
+    fn empty_line(&mut self) {
+        if self.last != Last::Escape {
+            self.separate(Sep::Paragraph);
+            return;
+        }
+        if self.column > 0 {
+            self.separate(Sep::Space);
+        }
+        // A real empty line may follow the end of the line
+        self.last = Last::Start;
+    }
+
+    /// Write the owed whitespace, nothing at the start of the output and one line break less
+    /// after a line break
+    fn write_sep(&mut self) -> io::Result<()> {
+        let sep: &[u8] = match self.sep {
+            _ if !self.written => b"",
+            Sep::None => b"",
+            Sep::Space => b" ",
+            Sep::Line if self.column == 0 => b"",
+            Sep::Line => b"\n",
+            Sep::Paragraph if self.column == 0 => b"\n",
+            Sep::Paragraph => b"\n\n",
+        };
+        self.writer.write_all(sep)?;
+        if self.sep >= Sep::Line {
+            self.column = 0;
+        } else {
+            self.column += sep.len();
+        }
+        self.sep = Sep::None;
+        self.written = true;
+        Ok(())
+    }
+}
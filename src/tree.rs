@@ -0,0 +1,237 @@
+//! A document tree built from [`Event`]s for consumers that need the whole document at once, like
+//! the serde integration.
+
+use std::{io::Write, iter};
+
+use crate::{
+    event::{Escape, Event, EventReader, Item, ItemKind},
+    stream::TokenStream,
+    AmarkResult, AmarkStream, AmarkWriter,
+};
+
+/// A whole Amark document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    /// The items at the top level
+    pub children: Vec<Node>,
+}
+
+impl Document {
+    /// Read a document from a stream of tokens
+    ///
+    /// # Errors
+    ///
+    /// This errors when the stream errors.
+    pub fn read<S: TokenStream>(stream: S) -> AmarkResult<'static, Self> {
+        let mut events = EventReader::new(stream);
+        // The children of all open elements, the top level is at the bottom
+        let mut stack: Vec<(Vec<Node>, usize)> = vec![(Vec::new(), 0)];
+
+        while let Some(event) = events.next_event()? {
+            let node = match event {
+                Event::Start(_) => {
+                    stack.push((Vec::new(), events.item_line()));
+                    continue;
+                }
+                Event::End(item) => {
+                    let (children, line) = stack.pop().unwrap_or_default();
+                    Node::Element(Element {
+                        item,
+                        line,
+                        children,
+                    })
+                }
+                Event::Empty(item) => Node::Element(Element {
+                    item,
+                    line: events.item_line(),
+                    children: Vec::new(),
+                }),
+                Event::Text(text) => Node::Text(text.to_vec()),
+                Event::Escape(escape) => Node::Escape(escape),
+                Event::EmptyLine => Node::EmptyLine,
+            };
+
+            if let Some((children, _)) = stack.last_mut() {
+                children.push(node);
+            }
+        }
+
+        Ok(Self {
+            children: stack
+                .pop()
+                .map(|(children, _)| children)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Parse a document from its source
+    ///
+    /// # Errors
+    ///
+    /// This errors when the source can't be parsed.
+    pub fn parse(source: &[u8]) -> AmarkResult<'static, Self> {
+        Self::read(AmarkStream::new(source))
+    }
+
+    /// The elements at the top level
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        elements(&self.children)
+    }
+
+    /// Write the document as Amark source
+    ///
+    /// # Errors
+    ///
+    /// This errors when the document can't be written as Amark, e.g. because of text outside of
+    /// blocks, or writing fails.
+    pub fn write<W: Write>(&self, writer: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        write_nodes(&self.children, writer)
+    }
+}
+
+/// A node of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// An item with its content
+    Element(Element),
+    /// A line of text
+    Text(Vec<u8>),
+    /// An escape sequence
+    Escape(Escape),
+    /// An empty line
+    EmptyLine,
+}
+
+/// An item with its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    /// The name, parameters and form of the item
+    pub item: Item,
+    /// The line the name of the item is on
+    pub line: usize,
+    /// The content of a block or container
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// The name of the item
+    pub fn name(&self) -> &[u8] {
+        &self.item.name
+    }
+
+    /// The items inside of this element
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        elements(&self.children)
+    }
+
+    /// The text of a block with resolved escape sequences. Lines are joined with a line break, an
+    /// empty line adds another one, trailing whitespace of lines and inline items are dropped.
+    pub fn text(&self) -> Vec<u8> {
+        let mut text = Vec::new();
+        // Where the current line of text started, its trailing whitespace is dropped at its end
+        let mut line_start = None;
+        // Line breaks which are only written if more text follows
+        let mut breaks = 0;
+        let mut started = false;
+
+        for child in &self.children {
+            match *child {
+                Node::Text(ref line) => {
+                    if let Some(start) = line_start.take() {
+                        trim_end(&mut text, start);
+                        breaks += 1;
+                    }
+                    text.extend(iter::repeat_n(b'\n', breaks));
+                    breaks = 0;
+                    line_start = Some(text.len());
+                    text.extend_from_slice(line);
+                    started = true;
+                }
+                Node::Escape(ref escape) => {
+                    line_start = None;
+                    text.extend(iter::repeat_n(b'\n', breaks));
+                    breaks = 0;
+                    text.extend(iter::repeat_n(escape.resolved(), escape.count()));
+                    started = true;
+                }
+                Node::EmptyLine => {
+                    // The line break after an escape sequence ends its line, after text it is
+                    // followed by an actual empty line
+                    if let Some(start) = line_start.take() {
+                        trim_end(&mut text, start);
+                        breaks += 1;
+                    }
+                    if started {
+                        breaks += 1;
+                    }
+                }
+                Node::Element(_) => (),
+            }
+        }
+
+        if let Some(start) = line_start {
+            trim_end(&mut text, start);
+        }
+
+        text
+    }
+
+    /// Whether this element is a block or container
+    pub fn has_body(&self) -> bool {
+        self.item.kind != ItemKind::Bare
+    }
+
+    /// Write the element as Amark source, the children of an item without body are ignored
+    ///
+    /// # Errors
+    ///
+    /// This errors when the element can't be written as Amark or writing fails.
+    pub fn write<W: Write>(&self, writer: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+        writer.item(&self.item.name)?;
+        if let Some(ref params) = self.item.params {
+            writer.params(params)?;
+        }
+
+        match self.item.kind {
+            ItemKind::Bare => writer.end()?,
+            ItemKind::Block => writer.block(|w| write_nodes(&self.children, w))?,
+            ItemKind::Container => writer.container(|w| write_nodes(&self.children, w))?,
+        };
+        Ok(())
+    }
+}
+
+/// Write the nodes as Amark source
+fn write_nodes<W: Write>(nodes: &[Node], writer: &mut AmarkWriter<W>) -> AmarkResult<'static, ()> {
+    for node in nodes {
+        match *node {
+            Node::Element(ref element) => element.write(writer)?,
+            Node::Text(ref text) => {
+                writer.line(text)?;
+            }
+            Node::Escape(ref escape) => {
+                writer.escape(escape.seq, escape.params.as_ref())?;
+            }
+            Node::EmptyLine => {
+                writer.empty_line()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The elements among the given nodes
+fn elements(nodes: &[Node]) -> impl Iterator<Item = &Element> {
+    nodes.iter().filter_map(|node| match *node {
+        Node::Element(ref element) => Some(element),
+        _ => None,
+    })
+}
+
+/// Remove trailing whitespace from the text but not before `start`
+fn trim_end(text: &mut Vec<u8>, start: usize) {
+    while text.len() > start && text.last().is_some_and(u8::is_ascii_whitespace) {
+        text.pop();
+    }
+}
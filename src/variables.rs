@@ -0,0 +1,315 @@
+//! Variables set inside of documents and interpolated on the token stream.
+//!
+//! `set(name, value);`, or `@set(name, value);` inside of blocks, sets a variable for the rest of
+//! the item it is in, including nested items. `\v(name)` is replaced by the value in text and
+//! parameters, `${name}` only in parameters because `}` ends blocks. Variables set in nested items
+//! hide the ones of outer items until the nested item ends. Values can use variables set before
+//! with `${name}`.
+//!
+//! Undefined variables are errors unless the expander is told to collect them as warnings with
+//! [`VariableExpander::strict`], then they are replaced by nothing.
+//!
+//! ```
+//! use amark::{variables::VariableExpander, AmarkFormatter, AmarkStream};
+//!
+//! let source = br"set(product, Amark);
+//! p {
+//!     @set(version, 1.2);
+//!     Get \v(product)
+//!     @link(${product} ${version});
+//! }";
+//! let mut expanded = Vec::new();
+//! AmarkFormatter::new().format(VariableExpander::new(AmarkStream::new(source.as_slice())), &mut expanded)?;
+//!
+//! assert_eq!(expanded, b"p {\n    Get Amark\n    @link(Amark 1.2);\n}\n");
+//! # Ok::<(), amark::AmarkError>(())
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display, Formatter},
+    mem,
+};
+
+use crate::{
+    event::parse_params,
+    macros::is_arg_name,
+    stream::{read_group, TokenStream},
+    AmarkError, AmarkResult, AmarkToken, ByteDisp, OwnedToken,
+};
+
+/// The name of the item setting variables
+const SET: &[u8] = b"set";
+
+/// The escape sequence interpolating variables
+const VARIABLE: u8 = b'v';
+
+/// A variable which was used without being set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndefinedVariable {
+    /// The name of the variable
+    pub name: Vec<u8>,
+    /// The line the variable was used on
+    pub line: usize,
+}
+
+impl Display for UndefinedVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Undefined variable {}", ByteDisp(&self.name))
+    }
+}
+
+/// A stream adapter which sets and interpolates variables, see the [module docs](self)
+#[derive(Debug)]
+pub struct VariableExpander<S> {
+    /// The stream of the document
+    stream: S,
+    /// The variables of the document and every open item, the innermost last
+    scopes: Vec<HashMap<Vec<u8>, Vec<u8>>>,
+    /// How many parameter lists are open
+    params: usize,
+    /// Tokens created by interpolation which weren't handed out yet
+    queued: VecDeque<OwnedToken>,
+    /// Text which wasn't queued yet, values are joined with the text around them since every
+    /// text token is written on its own line
+    text: Vec<u8>,
+    /// The line of the value the collected text ends with, text of blocks on that line continues
+    /// it
+    value_line: Option<usize>,
+    /// Whether undefined variables are errors
+    strict: bool,
+    /// The undefined variables used so far if they aren't errors
+    undefined: Vec<UndefinedVariable>,
+    /// The last token handed out
+    current: OwnedToken,
+}
+
+impl<S: TokenStream> VariableExpander<S> {
+    /// Create an expander over the given stream without any variables
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            scopes: vec![HashMap::new()],
+            params: 0,
+            queued: VecDeque::new(),
+            text: Vec::new(),
+            value_line: None,
+            strict: true,
+            undefined: Vec::new(),
+            current: OwnedToken::End,
+        }
+    }
+
+    /// Set a variable for the whole document before reading it
+    pub fn variable(mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> Self {
+        self.scopes[0].insert(name.into(), value.into());
+        self
+    }
+
+    /// Set whether undefined variables are errors, which is the default, or collected as warnings
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The undefined variables used so far, they are only collected if the expander isn't strict
+    pub fn undefined(&self) -> &[UndefinedVariable] {
+        &self.undefined
+    }
+
+    /// Unwrap the inner stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// The next token of the stream
+    fn next_raw(&mut self) -> AmarkResult<'static, OwnedToken> {
+        Ok(self.stream.next_token()?.to_owned_token())
+    }
+
+    /// The value of a variable, undefined variables are errors or collected as warnings
+    fn value(&mut self, name: &[u8]) -> AmarkResult<'static, Vec<u8>> {
+        if let Some(value) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return Ok(value.clone());
+        }
+        if self.strict {
+            return Err(AmarkError::UndefinedVariable {
+                name: name.to_vec(),
+            });
+        }
+        self.undefined.push(UndefinedVariable {
+            name: name.to_vec(),
+            line: self.stream.cur_line(),
+        });
+        Ok(Vec::new())
+    }
+
+    /// Read a `set` item after its name and set the variable in the innermost scope
+    fn set(&mut self) -> AmarkResult<'static, ()> {
+        let params = match self.next_raw()? {
+            OwnedToken::ParamsStart => {
+                parse_params(read_group(|| self.next_raw(), b"The end of the variable")?)?
+            }
+            _ => Vec::new(),
+        };
+        let ([ref name, ref value], OwnedToken::ItemEnd) = (params.as_slice(), self.next_raw()?)
+        else {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"set(name, value);"),
+                got: Cow::Borrowed(SET),
+            });
+        };
+        if !is_arg_name(name) {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"A variable name of letters, digits and _"),
+                got: Cow::Owned(name.clone()),
+            });
+        }
+
+        let value = self
+            .interpolate(value)?
+            .into_iter()
+            .flat_map(|(text, _)| text)
+            .collect();
+        self.scopes
+            .last_mut()
+            .unwrap_or_else(|| unreachable!("The scope of the document is never closed"))
+            .insert(name.clone(), value);
+        Ok(())
+    }
+
+    /// Read a `\v(name)` reference after the escape sequence and queue its value
+    fn reference(&mut self) -> AmarkResult<'static, ()> {
+        let params = match self.next_raw()? {
+            OwnedToken::ParamsStart => {
+                parse_params(read_group(|| self.next_raw(), b"The end of the variable")?)?
+            }
+            _ => Vec::new(),
+        };
+        let [ref name] = *params.as_slice() else {
+            return Err(AmarkError::UnexpectedInput {
+                expected: Cow::Borrowed(b"\\v(name)"),
+                got: Cow::Borrowed(b"\\v"),
+            });
+        };
+
+        let value = self.value(name)?;
+        self.queue_value(value);
+        Ok(())
+    }
+
+    /// Replace every `${name}` in the text, the parts are paired with whether they are values
+    fn interpolate(&mut self, text: &[u8]) -> AmarkResult<'static, Vec<(Vec<u8>, bool)>> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.windows(2).position(|w| w == b"${") {
+            let name_len = rest[start + 2..]
+                .iter()
+                .take_while(|&&b| b.is_ascii_alphanumeric() || b == b'_')
+                .count();
+            let end = start + 2 + name_len;
+            if name_len == 0 || rest.get(end) != Some(&b'}') {
+                parts.push((rest[..end].to_vec(), false));
+                rest = &rest[end..];
+                continue;
+            }
+
+            parts.push((rest[..start].to_vec(), false));
+            parts.push((self.value(&rest[start + 2..end])?, true));
+            rest = &rest[end + 1..];
+        }
+
+        parts.push((rest.to_vec(), false));
+        Ok(parts)
+    }
+
+    /// Queue a value as text, inside of parameters `,` is escaped so it doesn't split them
+    fn queue_value(&mut self, value: Vec<u8>) {
+        if self.params == 0 {
+            self.text.extend_from_slice(&value);
+            self.value_line = Some(self.stream.cur_line());
+            return;
+        }
+
+        for (i, part) in value.split(|&b| b == b',').enumerate() {
+            if i > 0 {
+                self.flush_text();
+                self.queued.push_back(OwnedToken::EscapeSequence(b','));
+            }
+            self.text.extend_from_slice(part);
+        }
+    }
+
+    /// Queue a text with its variables replaced
+    fn queue_text(&mut self, text: &[u8]) -> AmarkResult<'static, ()> {
+        let parts = self.interpolate(text)?;
+        if self.params == 0 {
+            // Lines of blocks stay apart, only text right after a value is on its line. The
+            // reader skips the line break after `)`, so the line tells them apart.
+            if self.value_line != Some(self.stream.cur_line()) {
+                self.flush_text();
+            }
+            self.text
+                .extend(parts.into_iter().flat_map(|(text, _)| text));
+            self.value_line = None;
+            return Ok(());
+        }
+
+        for (text, value) in parts {
+            if value {
+                self.queue_value(text);
+            } else {
+                self.text.extend_from_slice(&text);
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue the text collected so far
+    fn flush_text(&mut self) {
+        self.value_line = None;
+        if !self.text.is_empty() {
+            let text = mem::take(&mut self.text);
+            self.queued.push_back(OwnedToken::Text(text));
+        }
+    }
+}
+
+impl<S: TokenStream> TokenStream for VariableExpander<S> {
+    fn next_token(&mut self) -> AmarkResult<'static, AmarkToken<'_>> {
+        while self.queued.is_empty() {
+            match self.next_raw()? {
+                OwnedToken::ItemName(ref name) if name == SET => self.set()?,
+                OwnedToken::EscapeSequence(VARIABLE) => self.reference()?,
+                OwnedToken::Text(ref text) => self.queue_text(text)?,
+                tok => {
+                    self.flush_text();
+                    self.queued.push_back(tok);
+                }
+            }
+        }
+
+        self.current = self
+            .queued
+            .pop_front()
+            .unwrap_or_else(|| unreachable!("A token was just queued"));
+        match self.current {
+            OwnedToken::BlockStart | OwnedToken::ContainerStart => {
+                self.scopes.push(HashMap::new());
+            }
+            OwnedToken::BlockEnd | OwnedToken::ContainerEnd if self.scopes.len() > 1 => {
+                self.scopes.pop();
+            }
+            OwnedToken::ParamsStart => self.params += 1,
+            OwnedToken::ParamsEnd => self.params = self.params.saturating_sub(1),
+            _ => (),
+        }
+        Ok(self.current.as_token())
+    }
+
+    fn cur_line(&self) -> usize {
+        self.stream.cur_line()
+    }
+}
@@ -0,0 +1,448 @@
+//! Programmatic emission of Amark source.
+//!
+//! The [`AmarkWriter`] escapes text and parameters, validates item names and closes every block
+//! and container it opens, so the output always parses back to what was written. The output is
+//! laid out by an [`AmarkFormatter`].
+
+use std::{borrow::Cow, io::Write};
+
+use crate::{format::AmarkFormatter, validate_item_name, AmarkError, AmarkResult, AmarkToken};
+
+/// Writes Amark source from method calls
+///
+/// ```
+/// use amark::AmarkWriter;
+///
+/// let mut writer = AmarkWriter::new(Vec::new());
+/// writer.item("p")?.block(|w| {
+///     w.text("Price: {5}!")?;
+///     w.item("br")?.params(["2"])?;
+///     w.text("@home")?;
+///     Ok(())
+/// })?;
+/// let source = writer.finish()?;
+///
+/// assert_eq!(source, b"p {\n    Price: {5\\}!\n    @br(2);\n    \\@home\n}\n");
+/// # Ok::<(), amark::AmarkError>(())
+/// ```
+#[derive(Debug)]
+pub struct AmarkWriter<W> {
+    /// The output
+    writer: W,
+    /// Lays out the written tokens
+    formatter: AmarkFormatter,
+    /// Whether the writer is inside of a block or a container or at the top level
+    contexts: Vec<Context>,
+    /// The item which is currently being written
+    pending: Pending,
+    /// What the last token of the current block was, line breaks depend on it
+    last: Last,
+}
+
+impl<W: Write> AmarkWriter<W> {
+    /// Create a new writer with the default formatting
+    pub fn new(writer: W) -> Self {
+        Self::with_formatter(writer, AmarkFormatter::new())
+    }
+
+    /// Create a new writer which lays out the output with the given formatter
+    pub fn with_formatter(writer: W, mut formatter: AmarkFormatter) -> Self {
+        formatter.reset();
+        Self {
+            writer,
+            formatter,
+            contexts: Vec::new(),
+            pending: Pending::None,
+            last: Last::Other,
+        }
+    }
+
+    /// Start an item with the given name. It can be followed by [`AmarkWriter::params`] and
+    /// either [`AmarkWriter::block`] or [`AmarkWriter::container`], otherwise it ends with `;`.
+    ///
+    /// # Errors
+    ///
+    /// This errors when the name is not a valid item name or writing fails.
+    pub fn item(&mut self, name: impl AsRef<[u8]>) -> AmarkResult<'static, &mut Self> {
+        let name = name.as_ref();
+        self.end_pending()?;
+
+        validate_item_name(name)?;
+        self.write(AmarkToken::ItemName(name))?;
+        self.pending = Pending::Item;
+        Ok(self)
+    }
+
+    /// Write the parameter list of the current item. Every parameter is escaped.
+    ///
+    /// # Errors
+    ///
+    /// This errors when no item was started or the item already has parameters or writing fails.
+    pub fn params<I, P>(&mut self, params: I) -> AmarkResult<'static, &mut Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        if self.pending != Pending::Item {
+            return Err(misplaced(b"An item without parameters", b"Parameters"));
+        }
+
+        self.write_params(params)?;
+        self.pending = Pending::ItemParams;
+        Ok(self)
+    }
+
+    /// Give the current item a block. The closure writes the content of the block which is
+    /// closed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This errors when no item was started, the closure errors or writing fails.
+    pub fn block(
+        &mut self,
+        content: impl FnOnce(&mut Self) -> AmarkResult<'static, ()>,
+    ) -> AmarkResult<'static, &mut Self> {
+        self.open(Context::Block, content)
+    }
+
+    /// Give the current item a container. The closure writes the items of the container which
+    /// is closed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This errors when no item was started, the closure errors or writing fails.
+    pub fn container(
+        &mut self,
+        content: impl FnOnce(&mut Self) -> AmarkResult<'static, ()>,
+    ) -> AmarkResult<'static, &mut Self> {
+        self.open(Context::Container, content)
+    }
+
+    /// End the current item with `;`. This happens automatically when anything else is written.
+    ///
+    /// # Errors
+    ///
+    /// This errors when writing fails.
+    pub fn end(&mut self) -> AmarkResult<'static, &mut Self> {
+        self.end_pending()?;
+        Ok(self)
+    }
+
+    /// Write text into the current block. Characters with a meaning in Amark are escaped, a
+    /// line break continues the text on the next line and an empty line separates paragraphs.
+    /// Text written after other text starts on a new line.
+    ///
+    /// Line breaks and whitespace which would otherwise be lost are escaped, so
+    /// [`Element::text`](crate::tree::Element::text) of the block reads back the written text.
+    ///
+    /// # Errors
+    ///
+    /// This errors when the writer is not inside of a block or writing fails.
+    pub fn text(&mut self, text: impl AsRef<[u8]>) -> AmarkResult<'static, &mut Self> {
+        self.end_pending()?;
+        self.expect_block(b"Text")?;
+
+        let text = text.as_ref();
+        if text.is_empty() {
+            return Ok(self);
+        }
+
+        let mut breaks = usize::from(self.last == Last::Text);
+        for (i, line) in text.split(|&b| b == b'\n').enumerate() {
+            breaks += usize::from(i != 0);
+            if line.is_empty() {
+                continue;
+            }
+            self.line_break(breaks, escaped_at_start(line, b"}"))?;
+            breaks = 0;
+
+            let mut run = Vec::with_capacity(line.len());
+            self.write_escaped(line, b"}", true, true, &mut run)?;
+            let ends_with_text = !run.is_empty();
+            self.flush_text(&mut run)?;
+            self.last = if ends_with_text {
+                Last::Text
+            } else {
+                Last::Escape
+            };
+        }
+
+        // Trailing line breaks are only kept as escape sequences
+        for _ in 0..breaks {
+            self.write(AmarkToken::EscapeSequence(b'n'))?;
+            self.last = Last::Escape;
+        }
+
+        Ok(self)
+    }
+
+    /// Write a line of text into the current block as a single text token, the way the reader
+    /// reads it. Unlike [`text`](Self::text) trailing whitespace is kept as it is, characters
+    /// with a meaning in Amark and line breaks are still escaped.
+    ///
+    /// # Errors
+    ///
+    /// This errors when the writer is not inside of a block or writing fails.
+    pub fn line(&mut self, line: impl AsRef<[u8]>) -> AmarkResult<'static, &mut Self> {
+        self.end_pending()?;
+        self.expect_block(b"Text")?;
+
+        let line = line.as_ref();
+        if line.is_empty() {
+            return Ok(self);
+        }
+        let mut run = Vec::with_capacity(line.len());
+        self.write_escaped(line, b"}\n", true, false, &mut run)?;
+        let ends_with_text = !run.is_empty();
+        self.flush_text(&mut run)?;
+        self.last = if ends_with_text {
+            Last::Text
+        } else {
+            Last::Escape
+        };
+        Ok(self)
+    }
+
+    /// Write an empty line into the current block
+    ///
+    /// # Errors
+    ///
+    /// This errors when the writer is not inside of a block or writing fails.
+    pub fn empty_line(&mut self) -> AmarkResult<'static, &mut Self> {
+        self.end_pending()?;
+        self.expect_block(b"Empty line")?;
+        self.write(AmarkToken::EmptyLine)?;
+        Ok(self)
+    }
+
+    /// Write an escape sequence with optional parameters into the current block, e.g. `\s(4)`
+    ///
+    /// # Errors
+    ///
+    /// This errors when the writer is not inside of a block or writing fails.
+    pub fn escape<I, P>(&mut self, seq: u8, params: Option<I>) -> AmarkResult<'static, &mut Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        self.end_pending()?;
+        self.expect_block(b"Escape sequence")?;
+        self.write(AmarkToken::EscapeSequence(seq))?;
+        if let Some(params) = params {
+            self.write_params(params)?;
+        }
+        self.last = Last::Escape;
+        Ok(self)
+    }
+
+    /// End the current item, finish the document and return the inner writer
+    ///
+    /// # Errors
+    ///
+    /// This errors when writing or flushing fails.
+    pub fn finish(mut self) -> AmarkResult<'static, W> {
+        self.end_pending()?;
+        self.write(AmarkToken::End)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Open a block or container for the current item, fill and close it
+    fn open(
+        &mut self,
+        ctx: Context,
+        content: impl FnOnce(&mut Self) -> AmarkResult<'static, ()>,
+    ) -> AmarkResult<'static, &mut Self> {
+        if self.pending == Pending::None {
+            return Err(misplaced(b"An item", b"Start of a block or container"));
+        }
+
+        let (start, end) = match ctx {
+            Context::Block => (AmarkToken::BlockStart, AmarkToken::BlockEnd),
+            Context::Container => (AmarkToken::ContainerStart, AmarkToken::ContainerEnd),
+        };
+        self.pending = Pending::None;
+        self.write(start)?;
+        self.contexts.push(ctx);
+        content(self)?;
+        self.end_pending()?;
+        self.contexts.pop();
+        self.write(end)?;
+
+        Ok(self)
+    }
+
+    /// End a started item with `;` if it has no block or container
+    fn end_pending(&mut self) -> AmarkResult<'static, ()> {
+        if self.pending != Pending::None {
+            self.pending = Pending::None;
+            self.write(AmarkToken::ItemEnd)?;
+        }
+
+        Ok(())
+    }
+
+    /// Make sure the writer is inside of a block
+    fn expect_block(&self, got: &'static [u8]) -> AmarkResult<'static, ()> {
+        if self.contexts.last() == Some(&Context::Block) {
+            Ok(())
+        } else {
+            Err(misplaced(b"Item", got))
+        }
+    }
+
+    /// Write a parameter list
+    fn write_params<I, P>(&mut self, params: I) -> AmarkResult<'static, ()>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        self.write(AmarkToken::ParamsStart)?;
+        // Text tokens in parameter lists end at line breaks, so the separators have to be
+        // part of the surrounding text
+        let mut run = Vec::new();
+        for (i, param) in params.into_iter().enumerate() {
+            if i != 0 {
+                run.push(b',');
+            }
+            self.write_escaped(param.as_ref(), b",)\n", i == 0, false, &mut run)?;
+        }
+        self.flush_text(&mut run)?;
+        self.write(AmarkToken::ParamsEnd)
+    }
+
+    /// Start a new line of text in the current block after `breaks` line breaks. Text following
+    /// text on the next line is joined with a line break, the line break after an escape
+    /// sequence or an empty line has to be written as an empty line and where neither works it
+    /// is escaped.
+    fn line_break(&mut self, breaks: usize, escaped: bool) -> AmarkResult<'static, ()> {
+        let (empty_lines, escapes) = match self.last {
+            Last::Text if breaks == 1 && escaped => (0, 1),
+            Last::Text => (breaks.saturating_sub(1), 0),
+            Last::Escape => (breaks, 0),
+            Last::Other => (0, breaks),
+        };
+        for _ in 0..empty_lines {
+            self.write(AmarkToken::EmptyLine)?;
+        }
+        for _ in 0..escapes {
+            self.write(AmarkToken::EscapeSequence(b'n'))?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a line as text and escape sequences. Escape sequences are used for the given
+    /// special characters and `\\`. At the start of a text token they are also used for
+    /// whitespace which the reader would skip, `@` which would start an item and `(` which would
+    /// start a parameter list after an escape sequence. With `trailing` they are also used for
+    /// trailing whitespace which is dropped from lines of text.
+    ///
+    /// Text is collected in `run` until an escape sequence is written, the caller has to flush
+    /// the rest.
+    fn write_escaped(
+        &mut self,
+        line: &[u8],
+        special: &[u8],
+        mut at_start: bool,
+        trailing: bool,
+        run: &mut Vec<u8>,
+    ) -> AmarkResult<'static, ()> {
+        let keep = if trailing {
+            line.len()
+                - line
+                    .iter()
+                    .rev()
+                    .take_while(|b| b.is_ascii_whitespace())
+                    .count()
+        } else {
+            line.len()
+        };
+
+        for (i, &b) in line.iter().enumerate() {
+            let starts_text = at_start && run.is_empty();
+            if b == b'\\'
+                || special.contains(&b)
+                || starts_text && escaped_at_start(&[b], special)
+                || i >= keep
+            {
+                self.flush_text(run)?;
+                let seq = match b {
+                    b'\n' => b'n',
+                    b' ' => b's',
+                    b'\t' => b't',
+                    b => b,
+                };
+                self.write(AmarkToken::EscapeSequence(seq))?;
+                at_start = true;
+            } else {
+                run.push(b);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the collected text as a token
+    fn flush_text(&mut self, run: &mut Vec<u8>) -> AmarkResult<'static, ()> {
+        if !run.is_empty() {
+            self.write(AmarkToken::Text(run))?;
+            run.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Write a single token
+    fn write(&mut self, tok: AmarkToken) -> AmarkResult<'static, ()> {
+        self.last = Last::Other;
+        self.formatter.write_token(&tok, &mut self.writer)?;
+        Ok(())
+    }
+}
+
+/// Create an error for a call that is not allowed in the current context
+fn misplaced(expected: &'static [u8], got: &'static [u8]) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Borrowed(got),
+    }
+}
+
+/// Whether text at the start of a line would begin with an escape sequence
+fn escaped_at_start(line: &[u8], special: &[u8]) -> bool {
+    line.first().is_some_and(|&b| {
+        b == b'\\' || special.contains(&b) || b.is_ascii_whitespace() || b"(@".contains(&b)
+    })
+}
+
+/// A context opened by the writer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// A block
+    Block,
+    /// A container
+    Container,
+}
+
+/// The state of the item currently being written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    /// No item is being written
+    None,
+    /// The name of an item was written
+    Item,
+    /// The name and parameters of an item were written
+    ItemParams,
+}
+
+/// The last token written into a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// Text
+    Text,
+    /// An escape sequence with its parameters
+    Escape,
+    /// Anything else
+    Other,
+}
@@ -0,0 +1,769 @@
+//! Generic XML export and import of Amark documents for archives and XML tooling.
+//!
+//! Documents are written as a tree below an `amark` element. Text is a `text` element per line,
+//! empty lines are `empty-line` elements and escape sequences are elements like
+//! `<escape seq="s"><params><text>4</text></params></escape>`. Items carry the line and column
+//! their name starts at, `<item name="rb" line="1" col="1">` holds an optional `params` element
+//! with a `text` per parameter followed by a `block` or `container` element with the content.
+//! Items ended by `;` have neither. [`SCHEMA`] describes the format as XML Schema.
+//!
+//! [`import`] reads the XML back and writes it as Amark source, lines and columns are ignored.
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    mem, str, vec,
+};
+
+use crate::{
+    cst::Cst,
+    event::{Escape, Item, ItemKind},
+    metadata,
+    stream::TokenStream,
+    tree::{Document, Element, Node},
+    AmarkError, AmarkResult, AmarkWriter, OwnedToken,
+};
+
+/// The XML Schema of the format
+pub const SCHEMA: &str = include_str!("../schema/amark.xsd");
+
+/// Parse the source and write it as XML, see the [module docs](self). This takes the whole
+/// source instead of a stream to find the columns of items. Front matter is left out.
+///
+/// Bytes which can't be written in XML 1.0, like invalid UTF-8 and most control characters, are
+/// replaced by `U+FFFD`.
+///
+/// # Errors
+///
+/// This errors when the source can't be parsed or writing fails.
+pub fn write_tree<W: Write>(source: &[u8], writer: &mut W) -> AmarkResult<'static, ()> {
+    // Front matter is no content, the items after it keep their lines and columns
+    let source = metadata::blank_front_matter(source)?;
+    let document = Document::parse(&source)?;
+    let mut output = Output {
+        writer,
+        positions: item_positions(&Cst::parse(&source)?).into_iter(),
+    };
+
+    output
+        .writer
+        .write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+    output.nodes(b"amark", &document.children, 0)?;
+    Ok(())
+}
+
+/// Read the document from the stream and write it as XML like [`write_tree`]. Streams don't
+/// track columns, so items only carry their line. This is used for streams which are expanded
+/// first, where items don't have a place in the source anyway.
+///
+/// # Errors
+///
+/// This errors when the stream errors or writing fails.
+pub fn write_stream_tree<S: TokenStream, W: Write>(
+    stream: S,
+    writer: &mut W,
+) -> AmarkResult<'static, ()> {
+    let document = Document::read(stream)?;
+    let mut output = Output {
+        writer,
+        positions: Vec::new().into_iter(),
+    };
+
+    output
+        .writer
+        .write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+    output.nodes(b"amark", &document.children, 0)?;
+    Ok(())
+}
+
+/// Write XML exported with [`write_tree`] as Amark source
+///
+/// # Errors
+///
+/// This errors when the input is not well-formed XML in the format, the document can't be
+/// written as Amark or writing fails.
+pub fn import<W: Write>(xml: &[u8], writer: &mut W) -> AmarkResult<'static, ()> {
+    let document = read_tree(xml)?;
+    let mut amark = AmarkWriter::new(&mut *writer);
+    document.write(&mut amark)?;
+    amark.finish()?;
+    Ok(())
+}
+
+/// Read the document exported with [`write_tree`], missing lines are 0
+///
+/// # Errors
+///
+/// This errors when the input is not well-formed XML in the format.
+pub fn read_tree(xml: &[u8]) -> AmarkResult<'static, Document> {
+    let root = XmlParser::new(xml).parse()?;
+    if root.name != b"amark" {
+        return Err(unexpected(b"An amark element", root.name));
+    }
+
+    Ok(Document {
+        children: read_nodes(&root)?,
+    })
+}
+
+/// The line and column every item name starts at, in the order of the items in the source
+fn item_positions(cst: &Cst) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+    let mut pos = (1, 1);
+
+    for tok in cst.tokens() {
+        advance(&mut pos, tok.leading());
+        if let OwnedToken::ItemName(_) = *tok.token() {
+            positions.push(pos);
+        }
+        advance(&mut pos, tok.raw());
+        advance(&mut pos, tok.trailing());
+    }
+
+    positions
+}
+
+/// Move a line and column behind the given source
+fn advance(pos: &mut (usize, usize), source: &[u8]) {
+    for c in String::from_utf8_lossy(source).chars() {
+        *pos = if c == '\n' {
+            (pos.0 + 1, 1)
+        } else {
+            (pos.0, pos.1 + 1)
+        };
+    }
+}
+
+/// The state of the output while writing
+struct Output<'w, W> {
+    /// The output
+    writer: &'w mut W,
+    /// The positions of the items which are not written yet
+    positions: vec::IntoIter<(usize, usize)>,
+}
+
+impl<W: Write> Output<'_, W> {
+    /// Write the nodes as children of an element with the given tag on its own lines
+    fn nodes(&mut self, tag: &[u8], nodes: &[Node], depth: usize) -> io::Result<()> {
+        self.indent(depth)?;
+        if nodes.is_empty() {
+            return self.tag(tag, b"/>\n");
+        }
+
+        self.tag(tag, b">\n")?;
+        for node in nodes {
+            self.node(node, depth + 1)?;
+        }
+        self.indent(depth)?;
+        self.end_tag(tag)
+    }
+
+    /// Write a single node on its own lines
+    fn node(&mut self, node: &Node, depth: usize) -> io::Result<()> {
+        match *node {
+            Node::Element(ref element) => return self.element(element, depth),
+            Node::Text(ref text) => {
+                self.indent(depth)?;
+                self.text(text)?;
+            }
+            Node::Escape(ref escape) => {
+                self.indent(depth)?;
+                self.writer.write_all(b"<escape seq=\"")?;
+                write_escaped(&[escape.seq], true, self.writer)?;
+                let Some(ref params) = escape.params else {
+                    return self.writer.write_all(b"\"/>\n");
+                };
+                self.writer.write_all(b"\">\n")?;
+                self.params(params, depth + 1)?;
+                self.indent(depth)?;
+                self.end_tag(b"escape")?;
+                return Ok(());
+            }
+            Node::EmptyLine => {
+                self.indent(depth)?;
+                self.writer.write_all(b"<empty-line/>")?;
+            }
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    /// Write an item with its position, parameters and content
+    fn element(&mut self, element: &Element, depth: usize) -> io::Result<()> {
+        self.indent(depth)?;
+        self.writer.write_all(b"<item name=\"")?;
+        write_escaped(element.name(), true, self.writer)?;
+        self.writer.write_all(b"\"")?;
+        match self.positions.next() {
+            Some((line, col)) => write!(self.writer, " line=\"{}\" col=\"{}\"", line, col)?,
+            None if element.line > 0 => write!(self.writer, " line=\"{}\"", element.line)?,
+            None => (),
+        }
+
+        let params = element.item.params.as_deref();
+        if params.is_none() && !element.has_body() {
+            return self.writer.write_all(b"/>\n");
+        }
+        self.writer.write_all(b">\n")?;
+        if let Some(params) = params {
+            self.params(params, depth + 1)?;
+        }
+        match element.item.kind {
+            ItemKind::Bare => (),
+            ItemKind::Block => self.nodes(b"block", &element.children, depth + 1)?,
+            ItemKind::Container => self.nodes(b"container", &element.children, depth + 1)?,
+        }
+        self.indent(depth)?;
+        self.end_tag(b"item")
+    }
+
+    /// Write a parameter list with a `text` element per parameter
+    fn params(&mut self, params: &[Vec<u8>], depth: usize) -> io::Result<()> {
+        self.indent(depth)?;
+        if params.is_empty() {
+            return self.writer.write_all(b"<params/>\n");
+        }
+
+        self.writer.write_all(b"<params>\n")?;
+        for param in params {
+            self.indent(depth + 1)?;
+            self.text(param)?;
+            self.writer.write_all(b"\n")?;
+        }
+        self.indent(depth)?;
+        self.end_tag(b"params")
+    }
+
+    /// Write a `text` element
+    fn text(&mut self, text: &[u8]) -> io::Result<()> {
+        if text.is_empty() {
+            return self.writer.write_all(b"<text/>");
+        }
+        self.writer.write_all(b"<text>")?;
+        write_escaped(text, false, self.writer)?;
+        self.writer.write_all(b"</text>")
+    }
+
+    /// Write the start of a tag followed by the given end
+    fn tag(&mut self, tag: &[u8], end: &[u8]) -> io::Result<()> {
+        self.writer.write_all(b"<")?;
+        self.writer.write_all(tag)?;
+        self.writer.write_all(end)
+    }
+
+    /// Write a closing tag and end the line
+    fn end_tag(&mut self, tag: &[u8]) -> io::Result<()> {
+        self.writer.write_all(b"</")?;
+        self.writer.write_all(tag)?;
+        self.writer.write_all(b">\n")
+    }
+
+    /// Indent a line for the given nesting depth
+    fn indent(&mut self, depth: usize) -> io::Result<()> {
+        for _ in 0..depth {
+            self.writer.write_all(b"  ")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write text as XML character data or attribute value, characters XML 1.0 can't hold are
+/// replaced by `U+FFFD`
+fn write_escaped<W: Write>(text: &[u8], attribute: bool, writer: &mut W) -> io::Result<()> {
+    for chunk in text.utf8_chunks() {
+        let valid = chunk.valid().as_bytes();
+        let mut start = 0;
+        for (i, &b) in valid.iter().enumerate() {
+            let escaped: &[u8] = match b {
+                b'&' => b"&amp;",
+                b'<' => b"&lt;",
+                b'>' => b"&gt;",
+                b'"' if attribute => b"&quot;",
+                b'\t' if attribute => b"&#9;",
+                b'\n' if attribute => b"&#10;",
+                b'\r' => b"&#13;",
+                b'\t' | b'\n' => continue,
+                0..=0x1F | 0x7F => "\u{FFFD}".as_bytes(),
+                _ => continue,
+            };
+            writer.write_all(&valid[start..i])?;
+            writer.write_all(escaped)?;
+            start = i + 1;
+        }
+        writer.write_all(&valid[start..])?;
+
+        if !chunk.invalid().is_empty() {
+            writer.write_all("\u{FFFD}".as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the nodes inside of an element
+fn read_nodes(element: &XmlElement) -> AmarkResult<'static, Vec<Node>> {
+    element.elements()?.into_iter().map(read_node).collect()
+}
+
+/// Read a single node
+fn read_node(element: &XmlElement) -> AmarkResult<'static, Node> {
+    match element.name.as_slice() {
+        b"text" => Ok(Node::Text(element.text()?)),
+        b"empty-line" => Ok(Node::EmptyLine),
+        b"escape" => {
+            let seq = element.required(b"seq")?;
+            let mut parts = element.elements()?.into_iter();
+            let params = read_params(parts.next())?;
+            end_of(element, parts.next())?;
+            Ok(Node::Escape(Escape {
+                seq: single_byte(seq)?,
+                params,
+            }))
+        }
+        b"item" => read_item(element).map(Node::Element),
+        name => Err(unexpected(
+            b"An item, text, escape or empty-line element",
+            name.to_vec(),
+        )),
+    }
+}
+
+/// Read an item element
+fn read_item(element: &XmlElement) -> AmarkResult<'static, Element> {
+    let name = element.required(b"name")?;
+    let line = match element.attribute(b"line") {
+        Some(line) => str::from_utf8(line)
+            .ok()
+            .and_then(|line| line.parse().ok())
+            .ok_or_else(|| unexpected(b"A line number", line.to_vec()))?,
+        None => 0,
+    };
+
+    let mut parts = element.elements()?.into_iter().peekable();
+    let params = read_params(parts.next_if(|part| part.name == b"params"))?;
+    let (kind, children) = match parts.next() {
+        None => (ItemKind::Bare, Vec::new()),
+        Some(body) if body.name == b"block" => (ItemKind::Block, read_nodes(body)?),
+        Some(body) if body.name == b"container" => (ItemKind::Container, read_nodes(body)?),
+        Some(other) => {
+            return Err(unexpected(
+                b"A params, block or container element",
+                other.name.clone(),
+            ))
+        }
+    };
+    end_of(element, parts.next())?;
+
+    Ok(Element {
+        item: Item {
+            name: name.to_vec(),
+            params,
+            kind,
+        },
+        line,
+        children,
+    })
+}
+
+/// Read the parameters of a `params` element if there is one
+fn read_params(element: Option<&XmlElement>) -> AmarkResult<'static, Option<Vec<Vec<u8>>>> {
+    let Some(element) = element else {
+        return Ok(None);
+    };
+    if element.name != b"params" {
+        return Err(unexpected(b"A params element", element.name.clone()));
+    }
+
+    element
+        .elements()?
+        .into_iter()
+        .map(|param| match param.name.as_slice() {
+            b"text" => param.text(),
+            name => Err(unexpected(b"A text element", name.to_vec())),
+        })
+        .collect::<AmarkResult<_>>()
+        .map(Some)
+}
+
+/// Check that an element has no further children
+fn end_of(element: &XmlElement, next: Option<&XmlElement>) -> AmarkResult<'static, ()> {
+    match next {
+        Some(next) => Err(unexpected(
+            b"The end of the element",
+            [element.name.as_slice(), b": ", &next.name].concat(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The only byte of an escape sequence
+fn single_byte(seq: &[u8]) -> AmarkResult<'static, u8> {
+    match *seq {
+        [b] => Ok(b),
+        _ => Err(unexpected(b"A single escaped byte", seq.to_vec())),
+    }
+}
+
+/// Create an error for unexpected input
+fn unexpected(expected: &'static [u8], got: Vec<u8>) -> AmarkError<'static> {
+    AmarkError::UnexpectedInput {
+        expected: Cow::Borrowed(expected),
+        got: Cow::Owned(got),
+    }
+}
+
+/// A parsed XML element
+#[derive(Debug)]
+struct XmlElement {
+    /// The name as written in the input
+    name: Vec<u8>,
+    /// The attributes with their values in order
+    attributes: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The content
+    children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    /// The value of the attribute with the given name
+    fn attribute(&self, name: &[u8]) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// The value of an attribute the element needs
+    fn required(&self, name: &'static [u8]) -> AmarkResult<'static, &[u8]> {
+        self.attribute(name).ok_or_else(|| {
+            unexpected(
+                b"An attribute",
+                [self.name.as_slice(), b" without ", name].concat(),
+            )
+        })
+    }
+
+    /// The child elements, whitespace around them is ignored
+    fn elements(&self) -> AmarkResult<'static, Vec<&XmlElement>> {
+        self.children
+            .iter()
+            .filter_map(|child| match *child {
+                XmlNode::Element(ref element) => Some(Ok(element)),
+                XmlNode::Text(ref text) if text.trim_ascii().is_empty() => None,
+                XmlNode::Text(ref text) => Some(Err(unexpected(b"An element", text.clone()))),
+            })
+            .collect()
+    }
+
+    /// The text content, there must be no child elements
+    fn text(&self) -> AmarkResult<'static, Vec<u8>> {
+        let mut text = Vec::new();
+        for child in &self.children {
+            match *child {
+                XmlNode::Text(ref t) => text.extend_from_slice(t),
+                XmlNode::Element(ref element) => {
+                    return Err(unexpected(b"Text", element.name.clone()))
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Content of an XML element
+#[derive(Debug)]
+enum XmlNode {
+    /// A child element
+    Element(XmlElement),
+    /// Character data with resolved references
+    Text(Vec<u8>),
+}
+
+/// A recursive descent parser for the XML the format needs. Document type declarations are
+/// rejected, comments and processing instructions are skipped.
+struct XmlParser<'a> {
+    /// The input
+    xml: &'a [u8],
+    /// The position of the next byte
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    /// Create a parser for the input
+    fn new(xml: &'a [u8]) -> Self {
+        Self { xml, pos: 0 }
+    }
+
+    /// Parse the input as a document with a single root element
+    fn parse(mut self) -> AmarkResult<'static, XmlElement> {
+        self.misc()?;
+        if self.rest().starts_with(b"<!") {
+            return Err(self.unexpected(b"An element without document type declaration"));
+        }
+        let root = self.element()?;
+        self.misc()?;
+        if self.pos < self.xml.len() {
+            return Err(self.unexpected(b"The end of the input"));
+        }
+        Ok(root)
+    }
+
+    /// Skip whitespace, comments and processing instructions outside of the root element
+    fn misc(&mut self) -> AmarkResult<'static, ()> {
+        loop {
+            self.skip_whitespace();
+            if !self.skip_markup()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skip a comment or processing instruction if one is next
+    fn skip_markup(&mut self) -> AmarkResult<'static, bool> {
+        let end: &'static [u8] = if self.rest().starts_with(b"<!--") {
+            b"-->"
+        } else if self.rest().starts_with(b"<?") {
+            b"?>"
+        } else {
+            return Ok(false);
+        };
+        self.until(end)?;
+        Ok(true)
+    }
+
+    /// Parse an element starting at its `<`
+    fn element(&mut self) -> AmarkResult<'static, XmlElement> {
+        if !self.eat(b'<') {
+            return Err(self.unexpected(b"An element"));
+        }
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            if self.eat_all(b"/>") {
+                return Ok(XmlElement {
+                    name,
+                    attributes,
+                    children: Vec::new(),
+                });
+            }
+            if self.eat(b'>') {
+                break;
+            }
+
+            let key = self.name()?;
+            self.skip_whitespace();
+            if !self.eat(b'=') {
+                return Err(self.unexpected(b"="));
+            }
+            self.skip_whitespace();
+            let value = self.attribute_value()?;
+            attributes.push((key, value));
+        }
+
+        let children = self.content()?;
+        if self.name()? != name {
+            return Err(self.unexpected(b"The closing tag of the element"));
+        }
+        self.skip_whitespace();
+        if !self.eat(b'>') {
+            return Err(self.unexpected(b">"));
+        }
+
+        Ok(XmlElement {
+            name,
+            attributes,
+            children,
+        })
+    }
+
+    /// Parse the content of an element up to and including the `</` of its closing tag
+    fn content(&mut self) -> AmarkResult<'static, Vec<XmlNode>> {
+        let mut children = Vec::new();
+        let mut text = Vec::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.unexpected(b"The closing tag of the element")),
+                Some(b'<') => {
+                    if self.eat_all(b"</") {
+                        if !text.is_empty() {
+                            children.push(XmlNode::Text(text));
+                        }
+                        return Ok(children);
+                    } else if self.eat_all(b"<![CDATA[") {
+                        let data = self.until(b"]]>")?;
+                        push_normalized(&mut text, data);
+                    } else if !self.skip_markup()? {
+                        if !text.is_empty() {
+                            children.push(XmlNode::Text(mem::take(&mut text)));
+                        }
+                        children.push(XmlNode::Element(self.element()?));
+                    }
+                }
+                Some(b'&') => self.reference(&mut text)?,
+                Some(_) => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|b| b != b'<' && b != b'&') {
+                        self.pos += 1;
+                    }
+                    push_normalized(&mut text, &self.xml[start..self.pos]);
+                }
+            }
+        }
+    }
+
+    /// Parse a quoted attribute value, whitespace characters become spaces
+    fn attribute_value(&mut self) -> AmarkResult<'static, Vec<u8>> {
+        let quote = match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => quote,
+            _ => return Err(self.unexpected(b"A quoted attribute value")),
+        };
+        self.pos += 1;
+        let mut value = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                None | Some(b'<') => return Err(self.unexpected(b"The end of the attribute value")),
+                Some(b'&') => self.reference(&mut value)?,
+                Some(b'\r') => {
+                    self.pos += 1;
+                    self.eat(b'\n');
+                    value.push(b' ');
+                }
+                Some(b'\t' | b'\n') => {
+                    self.pos += 1;
+                    value.push(b' ');
+                }
+                Some(b) => {
+                    self.pos += 1;
+                    value.push(b);
+                }
+            }
+        }
+    }
+
+    /// Parse a character or predefined entity reference and add what it stands for
+    fn reference(&mut self, out: &mut Vec<u8>) -> AmarkResult<'static, ()> {
+        let start = self.pos;
+        let end = self.xml[start..]
+            .iter()
+            .take(12)
+            .position(|&b| b == b';')
+            .ok_or_else(|| self.unexpected(b"A reference ended by ;"))?;
+        let name = &self.xml[start + 1..start + end];
+
+        let c = match name {
+            b"lt" => '<',
+            b"gt" => '>',
+            b"amp" => '&',
+            b"quot" => '"',
+            b"apos" => '\'',
+            _ => {
+                let code = match *name {
+                    [b'#', b'x', ref hex @ ..] => str::from_utf8(hex)
+                        .ok()
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok()),
+                    [b'#', ref dec @ ..] => str::from_utf8(dec).ok().and_then(|d| d.parse().ok()),
+                    _ => None,
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| self.unexpected(b"A character or predefined entity reference"))?
+            }
+        };
+
+        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        self.pos = start + end + 1;
+        Ok(())
+    }
+
+    /// Parse the name of an element or attribute
+    fn name(&mut self) -> AmarkResult<'static, Vec<u8>> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|b| !b.is_ascii_whitespace() && !b"/>=<".contains(&b))
+        {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.unexpected(b"A name"));
+        }
+        Ok(self.xml[start..self.pos].to_vec())
+    }
+
+    /// Consume everything up to and including `end` and return what was before it
+    fn until(&mut self, end: &'static [u8]) -> AmarkResult<'static, &'a [u8]> {
+        let xml = self.xml;
+        let rest = &xml[self.pos..];
+        let len =
+            rest.windows(end.len())
+                .position(|w| w == end)
+                .ok_or(AmarkError::UnexpectedEof {
+                    expected: Cow::Borrowed(end),
+                })?;
+        self.pos += len + end.len();
+        Ok(&rest[..len])
+    }
+
+    /// Skip whitespace between markup
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b" \t\r\n".contains(&b)) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume the given byte if it is next
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// Consume the given bytes if they are next
+    fn eat_all(&mut self, bytes: &[u8]) -> bool {
+        let found = self.rest().starts_with(bytes);
+        if found {
+            self.pos += bytes.len();
+        }
+        found
+    }
+
+    /// The input which is not consumed yet
+    fn rest(&self) -> &'a [u8] {
+        &self.xml[self.pos..]
+    }
+
+    /// The next byte without consuming it
+    fn peek(&self) -> Option<u8> {
+        self.xml.get(self.pos).copied()
+    }
+
+    /// Create an error for the input at the current position
+    fn unexpected(&self, expected: &'static [u8]) -> AmarkError<'static> {
+        if self.pos >= self.xml.len() {
+            return AmarkError::UnexpectedEof {
+                expected: Cow::Borrowed(expected),
+            };
+        }
+
+        let end = self.xml.len().min(self.pos + 16);
+        unexpected(expected, self.xml[self.pos..end].to_vec())
+    }
+}
+
+/// Add character data with its line breaks normalized to `\n` like XML processors do
+fn push_normalized(text: &mut Vec<u8>, data: &[u8]) {
+    let mut bytes = data.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        if b == b'\r' {
+            bytes.next_if_eq(&b'\n');
+            text.push(b'\n');
+        } else {
+            text.push(b);
+        }
+    }
+}
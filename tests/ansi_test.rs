@@ -0,0 +1,84 @@
+mod common;
+
+use amark::ansi::{AnsiColor, AnsiRenderer, AnsiStyle};
+use common::render;
+
+#[test]
+fn boxes_are_colored() {
+    let output = render(
+        &AnsiRenderer::new(),
+        "yb {\n    A yellow box\n\n    @bb { Blue }\n}",
+    );
+
+    assert_eq!(
+        output,
+        "\x1b[33m┌ \x1b[1myb\x1b[0m
+\x1b[33m│ \x1b[0mA yellow box
+\x1b[33m│\x1b[0m
+\x1b[33m│ \x1b[0m\x1b[34m┌ \x1b[1mbb\x1b[0m
+\x1b[33m│ \x1b[0m\x1b[34m│ \x1b[0mBlue
+\x1b[33m│ \x1b[0m\x1b[34m└─\x1b[0m
+\x1b[33m└─\x1b[0m
+"
+    );
+}
+
+#[test]
+fn sections_are_labeled_and_indented() {
+    let output = render(
+        &AnsiRenderer::new(),
+        "card [\n    title {\n        Some\n        @em { text }\n        here\\nnext\n    }\n    l;\n]",
+    );
+
+    assert_eq!(
+        output,
+        "\x1b[1mcard\x1b[0m
+  \x1b[1mtitle\x1b[0m
+    Some \x1b[3mtext\x1b[0m here
+    next
+  \x1b[1ml\x1b[0m
+"
+    );
+}
+
+#[test]
+fn styles_are_configurable() {
+    let renderer = AnsiRenderer::new()
+        .indent(1)
+        .style("note", AnsiStyle::new().bold().color(AnsiColor::Magenta))
+        .style("gb", AnsiStyle::new().underline());
+    let output = render(&renderer, "p {\n    A\n    @note { b }\n    @gb { c }\n}");
+
+    assert_eq!(
+        output,
+        "\x1b[1mp\x1b[0m\n A \x1b[1;35mb\x1b[0m \x1b[4mc\x1b[0m\n"
+    );
+}
+
+#[test]
+fn br_items_break_lines() {
+    let output = render(
+        &AnsiRenderer::new(),
+        "p {\n    Additional text\n    @br(3);\n    Another\n    @br;\n    additional text\n}\ngb;",
+    );
+
+    assert_eq!(
+        output,
+        "\x1b[1mp\x1b[0m
+  Additional text
+
+
+  Another
+  additional text
+\x1b[32m┌ \x1b[1mgb\x1b[0m
+\x1b[32m└─\x1b[0m
+"
+    );
+    assert_eq!(
+        render(
+            &AnsiRenderer::new(),
+            "p {\n    a\n    @br;\n\n    b\\n\n\n    c\\n\n    d\n}"
+        ),
+        "\x1b[1mp\x1b[0m\n  a\n\n  b\n\n  c\n  d\n"
+    );
+}
@@ -0,0 +1,241 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn amark(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_amark"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start amark");
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn check_reports_errors() {
+    let ok = amark(&["check", "example_files/*.amark"], b"");
+    assert!(ok.status.success(), "{:?}", ok);
+
+    let invalid = amark(&["check", "-"], b"p {\n    unclosed\n");
+    assert_eq!(invalid.status.code(), Some(1));
+    let stderr = String::from_utf8(invalid.stderr).unwrap();
+    assert!(stderr.starts_with("error: <stdin>:3:"), "{}", stderr);
+
+    let missing = amark(&["check", "example_files/missing.amark"], b"");
+    assert_eq!(missing.status.code(), Some(2));
+
+    let schema = ["check", "--schema", "example_files/site.schema"];
+    let valid = amark(&schema, b"list(bullet) [\n    li { One }\n]\np { Hi }");
+    assert!(valid.status.success(), "{:?}", valid);
+    let violated = amark(&schema, b"p { Hi }\nbb [ gbb { Hi } ]");
+    assert_eq!(violated.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(violated.stderr).unwrap(),
+        "error: <stdin>:2: Item \"bb\" can't be a container\n\
+         error: <stdin>:2: Unknown item \"gbb\"\n"
+    );
+}
+
+#[test]
+fn dump_and_convert() {
+    let dump = amark(&["dump"], b"l;");
+    assert_eq!(dump.stdout, b"ItemName(l)\nItemEnd\nEnd\n");
+
+    let json = amark(&["dump", "--json"], b"l;");
+    assert_eq!(
+        json.stdout,
+        b"[\n{\"t\":\"ItemName\",\"v\":\"l\"},\n{\"t\":\"ItemEnd\"},\n{\"t\":\"End\"}\n]\n"
+    );
+
+    let html = amark(&["convert", "--to", "html"], b"p { hi }");
+    assert_eq!(html.stdout, b"<div class=\"p\">\nhi \n</div>\n");
+
+    let md = amark(&["convert", "--to", "md"], b"h1 { Hi }\np { *hi* }");
+    assert_eq!(md.stdout, b"# Hi\n\n\\*hi\\*\n");
+
+    let wp = amark(&["convert", "--to", "wp"], b"p { hi }");
+    assert_eq!(
+        wp.stdout,
+        b"<!-- wp:paragraph -->\n<p>hi</p>\n<!-- /wp:paragraph -->\n"
+    );
+
+    let text = amark(
+        &["convert", "--to", "text", "--wrap", "5", "--labels"],
+        b"p { hi there }",
+    );
+    assert_eq!(text.stdout, b"p:\nhi\nthere\n");
+
+    let tex = amark(&["convert", "--to", "tex", "--standalone"], b"p { 5% }");
+    assert!(String::from_utf8(tex.stdout)
+        .unwrap()
+        .ends_with("\\begin{document}\n5\\%\n\\end{document}\n"));
+
+    let template = amark(
+        &[
+            "convert",
+            "--to",
+            "template",
+            "--templates",
+            "example_files/templates.amark",
+        ],
+        b"gb {\n    hi\n    @br(2);\n    @x { y }\n}",
+    );
+    assert!(template.status.success());
+    assert_eq!(
+        template.stdout,
+        b"<div class=\"box green\">hi\n<br><br>y \n</div>"
+    );
+    assert_eq!(
+        template.stderr,
+        b"warning: <stdin>:4: No template for item \"x\"\n"
+    );
+
+    let expanded = amark(
+        &["convert", "--to", "html", "--expand"],
+        b"define(x, t) [\n    p { $t }\n]\nx(hi);",
+    );
+    assert_eq!(expanded.stdout, b"<div class=\"p\">\nhi \n</div>\n");
+    let front_matter_xml = amark(&["convert", "--to", "xml"], b"---\ntitle: Hi\n---\nl;");
+    assert!(
+        String::from_utf8(front_matter_xml.stdout)
+            .unwrap()
+            .contains("<item name=\"l\" line=\"4\" col=\"1\"/>"),
+        "{:?}",
+        front_matter_xml.stderr
+    );
+    let expanded_xml = amark(
+        &["convert", "--to", "xml", "-D", "t=hi"],
+        b"define(x) [\n    p { \\v(t) }\n]\nx;",
+    );
+    assert_eq!(
+        String::from_utf8(expanded_xml.stdout).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<amark>\n  <item name=\"p\" line=\"4\">\n    \
+         <block>\n      <text>hi</text>\n    </block>\n  </item>\n</amark>\n"
+    );
+
+    let defined = amark(
+        &[
+            "convert",
+            "--to",
+            "html",
+            "-D",
+            "site=de",
+            "--define",
+            "product=Amark",
+        ],
+        b"if(site=de) [\n    p { \\v(product) }\n]\nelse [ l; ]",
+    );
+    assert_eq!(defined.stdout, b"<div class=\"p\">\nAmark\n</div>\n");
+    let undefined = amark(
+        &["convert", "--to", "html", "--expand", "--allow-undefined"],
+        b"p {\n    Get \\v(product)\n}",
+    );
+    assert!(undefined.status.success(), "{:?}", undefined);
+    assert_eq!(undefined.stdout, b"<div class=\"p\">\nGet \n</div>\n");
+    assert_eq!(
+        undefined.stderr,
+        b"warning: <stdin>:2: Undefined variable \"product\"\n"
+    );
+    let unknown = amark(
+        &["convert", "--to", "html", "-D", "site=de"],
+        b"if(lang=de) [ l; ]",
+    );
+    assert_eq!(unknown.stderr, b"error: <stdin>:1: Unknown flag \"lang\"\n");
+
+    let front_matter = amark(
+        &["convert", "--to", "html"],
+        b"---\ntitle: Hi\n---\np { hi }",
+    );
+    assert_eq!(front_matter.stdout, b"<div class=\"p\">\nhi \n</div>\n");
+
+    // Without a terminal the preview is printed directly
+    let view = amark(&["view"], b"gb { hi }");
+    assert_eq!(
+        String::from_utf8(view.stdout).unwrap(),
+        "\x1b[32m┌ \x1b[1mgb\x1b[0m\n\x1b[32m│ \x1b[0mhi\n\x1b[32m└─\x1b[0m\n"
+    );
+}
+
+#[test]
+fn json_is_imported() {
+    let tree = amark(&["convert", "--to", "json-tree"], b"p { hi }\nl;");
+    assert_eq!(
+        tree.stdout,
+        b"[{\"name\":\"p\",\"line\":1,\"kind\":\"Block\",\"children\":[\"hi \"]},\
+          {\"name\":\"l\",\"line\":2,\"kind\":\"Bare\"}]\n"
+    );
+    let imported = amark(&["import"], &tree.stdout);
+    assert_eq!(imported.stdout, b"p {\n    hi \n}\nl;\n");
+
+    let json = amark(&["convert", "--to", "json"], b"p { hi }");
+    let imported = amark(&["import"], &json.stdout);
+    assert_eq!(imported.stdout, b"p {\n    hi \n}\n");
+
+    let xml = amark(&["convert", "--to", "xml"], b"p { hi }");
+    let imported = amark(&["import", "--from", "xml"], &xml.stdout);
+    assert_eq!(imported.stdout, b"p {\n    hi \n}\n");
+
+    let invalid = amark(&["import"], b"[{\"name\":\"two words\"}]");
+    assert_eq!(invalid.status.code(), Some(1));
+
+    let markdown = amark(&["import", "--from", "md"], b"# Hi\n\nA *b*");
+    assert_eq!(
+        markdown.stdout,
+        b"h1 {\n    Hi\n}\np {\n    A\n    @em {\n        b\n    }\n}\n"
+    );
+}
+
+#[test]
+fn fmt_formats_and_checks() {
+    let formatted = amark(&["fmt", "--tabs"], b"p{ Hello\n  @br (1) ;}");
+    assert!(formatted.status.success(), "{:?}", formatted);
+    assert_eq!(formatted.stdout, b"p {\n\tHello\n\t@br(1);\n}\n");
+
+    let check = amark(&["fmt", "--check", "example_files/functions.amark"], b"");
+    assert_eq!(check.status.code(), Some(1));
+    let check = amark(&["fmt", "--check", "--tabs"], &formatted.stdout);
+    assert_eq!(check.status.code(), Some(0));
+
+    let front_matter = amark(&["fmt"], b"---\ntitle:  Hi\n---\np{\n  Hello\n}");
+    assert!(front_matter.status.success(), "{:?}", front_matter);
+    assert_eq!(
+        front_matter.stdout,
+        b"---\ntitle:  Hi\n---\np {\n    Hello\n}\n"
+    );
+}
+
+#[test]
+fn lint_reports_and_fixes() {
+    let lints = amark(&["lint", "example_files/functions.amark"], b"");
+    assert!(lints.status.success(), "{:?}", lints);
+    let stderr = String::from_utf8(lints.stderr).unwrap();
+    assert!(
+        stderr.starts_with(
+            "warning: example_files/functions.amark:9: \
+             Indented with tabs, line 2 is indented with spaces [mixed-indentation]\n"
+        ),
+        "{}",
+        stderr
+    );
+
+    let fixed = amark(&["lint", "--fix"], b"p {\n    a  \n\n\n    b\n}\nbr(0);");
+    assert_eq!(fixed.stdout, b"p {\n    a\n\n    b\n}\nbr(0);\n");
+    assert_eq!(
+        fixed.stderr,
+        b"warning: <stdin>:6: br(0) adds no line break [br-zero]\n"
+    );
+
+    let front_matter = amark(&["lint", "--fix"], b"meta [ title(Hi); ]\np {\n    a  \n}");
+    assert!(front_matter.status.success(), "{:?}", front_matter);
+    assert_eq!(front_matter.stdout, b"meta [ title(Hi); ]\np {\n    a\n}\n");
+
+    let missing = amark(&["lint", "--config", "example_files/missing.amark"], b"");
+    assert_eq!(missing.status.code(), Some(2));
+}
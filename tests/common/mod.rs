@@ -0,0 +1,53 @@
+//! Helpers shared by the tests of the renderers
+
+use std::io::Cursor;
+
+use amark::{
+    ansi::AnsiRenderer, gutenberg::GutenbergRenderer, html::HtmlRenderer, latex::LatexRenderer,
+    markdown::MarkdownRenderer, text::TextRenderer, AmarkResult, AmarkStream,
+};
+
+/// A renderer of documents, implemented by all renderers with a `render` method
+pub trait Render {
+    /// Render all tokens of the stream to the output
+    fn render_to(
+        &self,
+        stream: AmarkStream<Cursor<&str>>,
+        out: &mut Vec<u8>,
+    ) -> AmarkResult<'static, ()>;
+}
+
+/// Implement [`Render`] by the `render` methods of the given renderers
+macro_rules! impl_render {
+    ($($renderer:ty),*) => {
+        $(
+            impl Render for $renderer {
+                fn render_to(
+                    &self,
+                    stream: AmarkStream<Cursor<&str>>,
+                    out: &mut Vec<u8>,
+                ) -> AmarkResult<'static, ()> {
+                    self.render(stream, out)
+                }
+            }
+        )*
+    };
+}
+
+impl_render!(
+    AnsiRenderer,
+    GutenbergRenderer,
+    HtmlRenderer,
+    LatexRenderer,
+    MarkdownRenderer,
+    TextRenderer
+);
+
+/// Render the source, panicking if that fails
+pub fn render(renderer: &impl Render, source: &str) -> String {
+    let mut out = Vec::new();
+    renderer
+        .render_to(AmarkStream::new(Cursor::new(source)), &mut out)
+        .unwrap_or_else(|e| panic!("Failure while rendering: {:?}", e));
+    String::from_utf8(out).unwrap()
+}
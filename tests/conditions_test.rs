@@ -0,0 +1,85 @@
+use amark::{
+    conditions::{ConditionFilter, UnknownFlag},
+    AmarkError, AmarkFormatter, AmarkStream,
+};
+
+fn filter(source: &str) -> ConditionFilter<AmarkStream<&[u8]>> {
+    ConditionFilter::new(AmarkStream::new(source.as_bytes()))
+        .flag("site", "de")
+        .flag("draft", "false")
+}
+
+fn format(filter: ConditionFilter<AmarkStream<&[u8]>>) -> Result<String, AmarkError<'static>> {
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(filter, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn conditions_select_items() {
+    let filtered = format(filter(
+        "if(site=de) [\n    a;\n    if(draft) [ b; ]\n    else [ c; ]\n]\nelse [ d; ]\n\
+         p {\n    Text\n    @if(site!=de, site=x) {\n        e\n    }\n    @else {\n        \
+         f\n    }\n    @if(site = de) {\n        g\n    }\n}\nif(site=en) [ h; ]\nl;",
+    ))
+    .unwrap();
+
+    assert_eq!(filtered, "a;\nc;\np {\n    Text\n    f\n    g\n}\nl;\n");
+}
+
+#[test]
+fn empty_lines_before_else_are_skipped() {
+    let filtered = format(filter(
+        "p {\n    a\n    @if(site=de) {\n        b\n    }\n\n    @else {\n        c\n    }\n\n    \
+         d\n    @if(site=en) {\n        e\n    }\n\n    f\n}",
+    ))
+    .unwrap();
+
+    assert_eq!(filtered, "p {\n    a\n    b\n\n    d\n\n    f\n}\n");
+}
+
+#[test]
+fn unknown_flags_are_diagnosed() {
+    let error = format(filter("if(lang=de) [ a; ]")).unwrap_err();
+    assert_eq!(error.to_string(), "Unknown flag \"lang\"");
+
+    let mut filter =
+        filter("l;\nif(lang=de) [ a; ]\nelse [ b; ]\nif(lang!=de) [ c; ]").strict(false);
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(&mut filter, &mut out).unwrap();
+    assert_eq!(out, b"l;\nb;\nc;\n");
+    assert_eq!(
+        filter.unknown(),
+        [
+            UnknownFlag {
+                name: b"lang".to_vec(),
+                line: 2
+            },
+            UnknownFlag {
+                name: b"lang".to_vec(),
+                line: 4
+            }
+        ]
+    );
+}
+
+#[test]
+fn invalid_conditions_are_rejected() {
+    for source in [
+        "if [ a; ]",
+        "if() [ a; ]",
+        "if(=de) [ a; ]",
+        "if(site=de);",
+        "else [ a; ]",
+        "if(site=de) [ a; ]\nl;\nelse [ b; ]",
+        "if(site=de) [ a; ]\nelse [ b; ]\nelse [ c; ]",
+        "if(site=de) [ a; ]\nelse(x) [ b; ]",
+        "if(site=de) { a }",
+        "if(site=en) { a }",
+        "c [\n    if(site=de) { a }\n]",
+        "if(site=de) [ a; ]\nelse { b }",
+        "p {\n    @l [\n        if(site=de) { a }\n    ]\n}",
+    ] {
+        assert!(format(filter(source)).is_err(), "{}", source);
+    }
+}
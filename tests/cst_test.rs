@@ -0,0 +1,87 @@
+use std::io::Cursor;
+
+use amark::{cst::Cst, AmarkReader, OwnedToken};
+use proptest::prelude::*;
+
+fn tokens(source: &[u8]) -> Option<Vec<OwnedToken>> {
+    let mut aml_reader = AmarkReader::new();
+    let mut source = Cursor::new(source);
+    let mut tokens = Vec::new();
+
+    loop {
+        tokens.push(aml_reader.parse_next(&mut source).ok()?.to_owned_token());
+        if tokens.last() == Some(&OwnedToken::End) {
+            return Some(tokens);
+        }
+    }
+}
+
+fn cst_tokens(cst: &Cst) -> Vec<OwnedToken> {
+    cst.tokens()
+        .into_iter()
+        .map(|t| t.token().clone())
+        .collect()
+}
+
+#[test]
+fn sources_are_reproduced() {
+    for source in [
+        include_bytes!("../example_files/simple_reference.amark").as_ref(),
+        include_bytes!("../example_files/large_file.amark"),
+        include_bytes!("../example_files/functions.amark"),
+        b"\xEF\xBB\xBFa;\r\n  ) b [ c(x,\n y) ; ]\n\n",
+        b"p {\\s(2)  x\\\n\n\t@i (a\\)) {}   \n@b;text\\}\n}\n\n",
+    ] {
+        let cst = Cst::parse(source).unwrap();
+        assert_eq!(cst.to_bytes(), source);
+        assert_eq!(Some(cst_tokens(&cst)), tokens(source));
+    }
+}
+
+#[test]
+fn items_are_renamed() {
+    let source = include_bytes!("../example_files/simple_reference.amark");
+    let mut cst = Cst::parse(source).unwrap();
+
+    assert_eq!(cst.rename_all(b"bb", b"blue_box").unwrap(), 3);
+    let path = cst.find_item(b"gb").unwrap();
+    cst.item_mut(&path).unwrap().rename("green_box").unwrap();
+    assert!(cst.item_mut(&path).unwrap().rename("two words").is_err());
+
+    let expected = String::from_utf8(source.to_vec())
+        .unwrap()
+        .replace("gb {", "green_box {")
+        .replace("bb {", "blue_box {");
+    assert_eq!(String::from_utf8(cst.to_bytes()).unwrap(), expected);
+}
+
+#[test]
+fn items_are_inserted_and_deleted() {
+    let mut cst = Cst::parse(b"list [\n  a;\n  b(1) {\n      Text\n  }\n]\nend;\n").unwrap();
+
+    let b = cst.find_item(b"b").unwrap();
+    assert_eq!(b, [0, 1]);
+    cst.insert(&[0, 1, 1], "@br;").unwrap();
+    cst.insert(&[0, 1, 0], "First").unwrap();
+    cst.insert(&[0, 2], "c [ d; ]").unwrap();
+    cst.insert(&[2], "e;").unwrap();
+    assert!(cst.delete(&[0, 0]).is_some());
+    assert!(cst.insert(&[1, 0], "x;").is_err());
+    assert!(cst.insert(&[0, 1, 0], "unbalanced }").is_err());
+
+    assert_eq!(
+        String::from_utf8(cst.to_bytes()).unwrap(),
+        "list [\n  b(1) {\n      First\n      Text\n      @br;\n  }\n  c [ d; ]\n]\nend;\ne;\n"
+    );
+}
+
+proptest! {
+    #[test]
+    fn any_valid_source_is_reproduced(source in "[a-z@ ;,\\\\(){}\\[\\]\n\t]{0,40}") {
+        if let Some(expected) = tokens(source.as_bytes()) {
+            let cst = Cst::parse(source.as_bytes()).unwrap();
+            prop_assert_eq!(cst.to_bytes(), source.as_bytes());
+            prop_assert_eq!(cst_tokens(&cst), expected);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Site {
+    title: String,
+    description: String,
+    draft: bool,
+    author: Option<String>,
+    theme: Theme,
+    cards: Vec<Card>,
+    shapes: Vec<Shape>,
+    links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Card {
+    name: String,
+    price: u32,
+    size: (u8, u8),
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum Theme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum Shape {
+    Dot,
+    Circle(f64),
+    Rect { w: u32, h: u32 },
+}
+
+#[test]
+fn site_is_deserialized() {
+    let source = b"title { My \\@site }
+description {
+    Products for
+    everyone\\s
+
+    Cheap!
+}
+draft;
+theme [ Dark; ]
+cards [
+    card [
+        name { Green box }
+        price(5);
+        size(1, 2);
+        tags(new, sale\\, cheap);
+    ]
+    card [ name { Blue box } price(7); size(3,4); tags; ]
+]
+shapes [
+    Dot;
+    Circle(1.5);
+    Rect(2, 3);
+    Rect [ w(4); h(5); ]
+]
+links [ home { / } blog { /blog } ]
+";
+
+    let site: Site = amark::from_slice(source).unwrap();
+    assert_eq!(
+        site,
+        Site {
+            title: "My @site".into(),
+            description: "Products for\neveryone \n\nCheap!".into(),
+            draft: true,
+            author: None,
+            theme: Theme::Dark,
+            cards: vec![
+                Card {
+                    name: "Green box".into(),
+                    price: 5,
+                    size: (1, 2),
+                    tags: vec!["new".into(), "sale, cheap".into()],
+                },
+                Card {
+                    name: "Blue box".into(),
+                    price: 7,
+                    size: (3, 4),
+                    tags: Vec::new(),
+                },
+            ],
+            shapes: vec![
+                Shape::Dot,
+                Shape::Circle(1.5),
+                Shape::Rect { w: 2, h: 3 },
+                Shape::Rect { w: 4, h: 5 },
+            ],
+            links: [("home", "/"), ("blog", "/blog")]
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    );
+}
+
+#[test]
+fn errors_carry_the_line() {
+    let err =
+        amark::from_slice::<Card>(b"name { A }\n\nprice(many);\nsize(1,2);\ntags;").unwrap_err();
+    assert_eq!(err.line(), Some(3));
+    assert!(
+        err.to_string()
+            .starts_with("line 3: invalid value \"many\""),
+        "{}",
+        err
+    );
+
+    let err = amark::from_slice::<Card>(b"name { A }\nprice(1);\nsize [\n  a(1);\n  b(x);\n]")
+        .unwrap_err();
+    assert_eq!(err.line(), Some(5));
+
+    let err = amark::from_reader::<Card>(&b"name {\n  A\n"[..]).unwrap_err();
+    assert!(
+        matches!(err, amark::SerdeError::Amark { line: 3, .. }),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,74 @@
+use std::io::{BufReader, Cursor, ErrorKind};
+
+use amark::{AmarkError, AmarkReader, AmarkToken, DecodingReader};
+
+const SOURCE: &str = "TopLevel [\n  Text {\n      Hello \u{1F600} World\n  }\n]";
+
+fn expected() -> [AmarkToken<'static>; 8] {
+    [
+        AmarkToken::ItemName(b"TopLevel"),
+        AmarkToken::ContainerStart,
+        AmarkToken::ItemName(b"Text"),
+        AmarkToken::BlockStart,
+        AmarkToken::Text("Hello \u{1F600} World".as_bytes()),
+        AmarkToken::BlockEnd,
+        AmarkToken::ContainerEnd,
+        AmarkToken::End,
+    ]
+}
+
+fn assert_tokens(source: &mut impl std::io::BufRead) {
+    let mut aml_reader = AmarkReader::new();
+
+    for expected_token in expected() {
+        let (got_token, line) = aml_reader.parse_next_get_cur_line(source);
+        let got_token =
+            got_token.unwrap_or_else(|e| panic!("Failure while parsing on line {}: {:?}", line, e));
+
+        assert_eq!(
+            got_token, expected_token,
+            "Unexpected token! Expected: {:?}, got: {:?} on line {}",
+            expected_token, got_token, line
+        );
+    }
+}
+
+fn utf16(bom: &[u8], encode: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut bytes = bom.to_vec();
+    bytes.extend(SOURCE.encode_utf16().flat_map(encode));
+    bytes
+}
+
+#[test]
+fn utf8_bom_is_stripped() {
+    let mut bytes = b"\xEF\xBB\xBF".to_vec();
+    bytes.extend_from_slice(SOURCE.as_bytes());
+
+    assert_tokens(&mut Cursor::new(&bytes));
+    assert_tokens(&mut DecodingReader::new(Cursor::new(&bytes)));
+}
+
+#[test]
+fn utf16_is_transcoded() {
+    let le = utf16(b"\xFF\xFE", u16::to_le_bytes);
+    let be = utf16(b"\xFE\xFF", u16::to_be_bytes);
+
+    assert_tokens(&mut DecodingReader::new(Cursor::new(&le)));
+    assert_tokens(&mut DecodingReader::new(Cursor::new(&be)));
+    // Odd buffer sizes split code units and surrogate pairs between reads
+    assert_tokens(&mut DecodingReader::new(BufReader::with_capacity(
+        3,
+        Cursor::new(&le),
+    )));
+}
+
+#[test]
+fn utf16_without_decoding_errors() {
+    let le = utf16(b"\xFF\xFE", u16::to_le_bytes);
+    let mut aml_reader = AmarkReader::new();
+
+    match aml_reader.parse_next(&mut Cursor::new(&le)) {
+        Err(AmarkError::IoError(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        other => panic!("Expected an encoding error, got {:?}", other),
+    }
+}
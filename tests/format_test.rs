@@ -0,0 +1,146 @@
+use std::io::Cursor;
+
+use amark::{
+    format::{AmarkFormatter, Indent},
+    AmarkReader, OwnedToken,
+};
+use proptest::{collection::vec, prelude::*};
+
+fn tokens(source: &[u8]) -> Vec<OwnedToken> {
+    let mut aml_reader = AmarkReader::new();
+    let mut source = Cursor::new(source);
+    let mut tokens = Vec::new();
+
+    loop {
+        let (got_token, line) = aml_reader.parse_next_get_cur_line(&mut source);
+        let got_token =
+            got_token.unwrap_or_else(|e| panic!("Failure while parsing on line {}: {:?}", line, e));
+        tokens.push(got_token.to_owned_token());
+
+        if tokens.last() == Some(&OwnedToken::End) {
+            return tokens;
+        }
+    }
+}
+
+#[test]
+fn functions_are_formatted() {
+    let formatted = AmarkFormatter::with_indent(Indent::Spaces(2))
+        .format_bytes(include_bytes!("../example_files/functions.amark"))
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(formatted).unwrap(),
+        "p {\n  hi\n}\nbr(1);\np {\n  Hello\n}\nbr(5);\np {\n  Hello\n  @br(3);\n\n  \
+         @ro(hi,7) {\n    hello\n\n    @p {\n      hi\n\n      @ri(good day) {\n        bye\n\n      \
+         }\n    }\n\n  }\n\n  World\n}\n"
+    );
+}
+
+#[test]
+fn tokens_survive_formatting() {
+    for source in [
+        include_bytes!("../example_files/simple_reference.amark").as_ref(),
+        include_bytes!("../example_files/large_file.amark"),
+        include_bytes!("../example_files/functions.amark"),
+        b"c [] p {} e {\\}} x(\n  a\n\n  b\\,\\s(2)) { \\n\n\n\\s(1)\n}",
+    ] {
+        for indent in [Indent::Tab, Indent::Spaces(4)] {
+            let formatted = AmarkFormatter::with_indent(indent)
+                .format_bytes(source)
+                .unwrap();
+            assert_eq!(tokens(source), tokens(&formatted));
+        }
+    }
+}
+
+fn text() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9.,!?]([a-zA-Z0-9.,!?; ]{0,12}[a-zA-Z0-9.,!?;])?"
+}
+
+fn escape() -> impl Strategy<Value = String> {
+    prop_oneof!["\\\\[nst}@\\\\(]", "\\\\[ns]\\([0-9]\\)"]
+}
+
+fn params() -> impl Strategy<Value = String> {
+    "\\(([a-z0-9 ,]|\\\\[,)]){0,8}\\)"
+}
+
+fn ws() -> impl Strategy<Value = String> {
+    "[ \t]{0,2}"
+}
+
+/// A block with lines of text, escapes, empty lines and nested inline items
+fn block(depth: u32) -> BoxedStrategy<String> {
+    let inline_item =
+        if depth == 0 {
+            (ws(), prop::option::of(params()))
+                .prop_map(|(ws, params)| format!("@i{}{};", params.unwrap_or_default(), ws))
+                .boxed()
+        } else {
+            prop_oneof![
+                (prop::option::of(params())).prop_map(|p| format!("@i{};", p.unwrap_or_default())),
+                (prop::option::of(params()), ws(), block(depth - 1))
+                    .prop_map(|(p, ws, b)| format!("@b{}{}{}", p.unwrap_or_default(), ws, b)),
+            ]
+            .boxed()
+        };
+    let segment = prop_oneof![3 => text(), 2 => escape(), 1 => inline_item];
+    let line = (ws(), vec(segment, 1..4), ws()).prop_map(|(indent, segments, trailing)| {
+        let mut line = indent;
+        for (i, segment) in segments.iter().enumerate() {
+            // An item directly after text would be part of the text
+            let after_text = i > 0 && !segments[i - 1].starts_with(['@', '\\']);
+            if segment.starts_with('@') && after_text {
+                line.push('\n');
+            }
+            line.push_str(segment);
+        }
+        line + &trailing
+    });
+    let lines = vec(prop_oneof![4 => line, 1 => Just(String::new())], 0..5);
+
+    (lines, ws())
+        .prop_map(|(lines, ws)| format!("{{{}\n{}\n}}", ws, lines.join("\n")))
+        .boxed()
+}
+
+/// An item as it can appear at the top level or in containers
+fn item(depth: u32) -> BoxedStrategy<String> {
+    let name = "[a-zA-Z_][a-zA-Z0-9_]{0,4}";
+    let leaf =
+        prop_oneof![
+            (name, prop::option::of(params()), ws()).prop_map(|(n, p, ws)| format!(
+                "{}{}{};",
+                n,
+                p.unwrap_or_default(),
+                ws
+            )),
+            (name, prop::option::of(params()), ws(), block(depth))
+                .prop_map(|(n, p, ws, b)| format!("{}{}{}{}", n, p.unwrap_or_default(), ws, b)),
+        ];
+    if depth == 0 {
+        return leaf.boxed();
+    }
+
+    prop_oneof![
+        2 => leaf,
+        1 => (name, ws(), vec(item(depth - 1), 0..4))
+            .prop_map(|(n, ws, items)| format!("{}{}[\n{}\n]", n, ws, items.join("\n"))),
+    ]
+    .boxed()
+}
+
+proptest! {
+    #[test]
+    fn formatting_keeps_tokens(items in vec(item(2), 0..4), tabs: bool) {
+        let source = items.join("\n");
+        let indent = if tabs { Indent::Tab } else { Indent::Spaces(2) };
+        let mut formatter = AmarkFormatter::with_indent(indent);
+        let formatted = formatter.format_bytes(source.as_bytes()).unwrap();
+
+        prop_assert_eq!(tokens(source.as_bytes()), tokens(&formatted));
+        // Formatting is idempotent
+        prop_assert_eq!(&formatter.format_bytes(&formatted).unwrap(), &formatted);
+    }
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use amark::gutenberg::{GutenbergBlock, GutenbergElement, GutenbergRenderer};
+use common::render;
+
+#[test]
+fn items_become_blocks() {
+    let renderer = GutenbergRenderer::new();
+    let markup = render(
+        &renderer,
+        "h1(right) { Fish & Chips }
+p(center) {
+    Some
+    @em { emphasis }
+    and
+    @br;
+    a\\s(2)break
+}
+ul [ li { One } ]
+hr;",
+    );
+
+    assert_eq!(
+        markup,
+        "<!-- wp:heading {\"level\":1,\"align\":\"right\"} -->
+<h1 class=\"wp-block-heading\">Fish &amp; Chips</h1>
+<!-- /wp:heading -->
+
+<!-- wp:paragraph {\"align\":\"center\"} -->
+<p>Some <em>emphasis</em> and<br>a&nbsp;&nbsp;break</p>
+<!-- /wp:paragraph -->
+
+<!-- wp:list -->
+<ul class=\"wp-block-list\"><!-- wp:list-item -->
+<li>One</li>
+<!-- /wp:list-item --></ul>
+<!-- /wp:list -->
+
+<!-- wp:separator -->
+<hr class=\"wp-block-separator has-alpha-channel-opacity\"/>
+<!-- /wp:separator -->
+"
+    );
+}
+
+#[test]
+fn nested_items_become_inner_blocks() {
+    let markup = render(
+        &GutenbergRenderer::new(),
+        "yb(wide) {
+    A yellow box
+
+    @bb { A blue box }
+    @em { After }
+}",
+    );
+
+    assert_eq!(
+        markup,
+        "<!-- wp:group {\"className\":\"yb wide\"} -->
+<div class=\"wp-block-group yb wide\"><!-- wp:paragraph -->
+<p>A yellow box</p>
+<!-- /wp:paragraph -->
+
+<!-- wp:group {\"className\":\"bb\"} -->
+<div class=\"wp-block-group bb\"><!-- wp:paragraph -->
+<p>A blue box</p>
+<!-- /wp:paragraph --></div>
+<!-- /wp:group -->
+
+<!-- wp:paragraph -->
+<p><em>After</em></p>
+<!-- /wp:paragraph --></div>
+<!-- /wp:group -->
+"
+    );
+
+    // Inside of the text of a block other items stay inline
+    let markup = render(
+        &GutenbergRenderer::new(),
+        "p {\n    A\n    @bb(big) { box }\n}",
+    );
+    assert_eq!(
+        markup,
+        "<!-- wp:paragraph -->\n<p>A <span class=\"bb big\">box</span></p>\n<!-- /wp:paragraph -->\n"
+    );
+}
+
+#[test]
+fn params_become_attributes() {
+    let renderer = GutenbergRenderer::new().element(
+        "posts",
+        GutenbergElement::Block(
+            GutenbergBlock::dynamic("core/latest-posts")
+                .with_attribute("displayPostDate", "true")
+                .with_params(["postsToShow", "className", "order"]),
+        ),
+    );
+    let markup = render(
+        &renderer,
+        "posts(5, a--><b>&\"c\", -1.5e3, unused) { dropped }",
+    );
+
+    assert_eq!(
+        markup,
+        "<!-- wp:latest-posts {\"displayPostDate\":true,\"postsToShow\":5,\
+         \"className\":\"a\\u002d\\u002d\\u003e\\u003cb\\u003e\\u0026\\u0022c\\u0022\",\
+         \"order\":-1.5e3} /-->\n"
+    );
+}
+
+#[test]
+fn empty_lines_and_br_items_between_blocks() {
+    let markup = render(
+        &GutenbergRenderer::new(),
+        "br(2);
+p(center) {
+    One
+
+    Two
+    @br(2);
+    three\\n
+
+    Four\\n
+}
+br;
+h2 {
+    A\\n
+    B
+
+    C
+
+}",
+    );
+
+    assert_eq!(
+        markup,
+        "<!-- wp:paragraph {\"align\":\"center\"} -->
+<p>One</p>
+<!-- /wp:paragraph -->
+
+<!-- wp:paragraph {\"align\":\"center\"} -->
+<p>Two<br><br>three</p>
+<!-- /wp:paragraph -->
+
+<!-- wp:paragraph {\"align\":\"center\"} -->
+<p>Four</p>
+<!-- /wp:paragraph -->
+
+<!-- wp:heading -->
+<h2 class=\"wp-block-heading\">A<br>B<br>C</h2>
+<!-- /wp:heading -->
+"
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use amark::html::{HtmlElement, HtmlRenderer};
+use common::render;
+
+#[test]
+fn items_become_elements() {
+    let renderer = HtmlRenderer::new().element("p", HtmlElement::new("p"));
+    let html = render(
+        &renderer,
+        "p {\n    Fish & Chips\n    @br(2);\n    <b>\\}}\nbox(wide) [\n    l;\n]",
+    );
+
+    assert_eq!(
+        html,
+        "<p>\nFish &amp; Chips\n<br>\n<br>\n&lt;b&gt;}\n</p>\n\
+         <div class=\"box\" data-params=\"wide\">\n<div class=\"l\">\n</div>\n</div>\n"
+    );
+}
+
+#[test]
+fn escapes_are_resolved() {
+    let html = render(&HtmlRenderer::new(), "t {\n    a\\s(2)b\\nc\n}");
+
+    assert_eq!(html, "<div class=\"t\">\na&nbsp;&nbsp;b<br>\nc\n</div>\n");
+}
+
+#[test]
+fn escapes_end_lines_without_breaks() {
+    let html = render(
+        &HtmlRenderer::new(),
+        "t {\n    50\\%\n    off\\n\n    now\n\n    end\n}",
+    );
+
+    assert_eq!(
+        html,
+        "<div class=\"t\">\n50%\noff<br>\nnow\n<br>\nend\n</div>\n"
+    );
+}
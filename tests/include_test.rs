@@ -0,0 +1,61 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use amark::{include::IncludeExpander, AmarkError, AmarkFormatter, AmarkStream};
+
+fn path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "include", name]
+        .iter()
+        .collect()
+}
+
+fn expand_file(name: &str) -> Result<String, AmarkError<'static>> {
+    let path = path(name);
+    let stream = AmarkStream::new(BufReader::new(File::open(&path).unwrap()));
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(IncludeExpander::new(stream, path), &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+/// The error with the paths relative to the include directory
+fn error(name: &str) -> String {
+    let error = expand_file(name).unwrap_err().to_string();
+    error.replace(&path("").display().to_string(), "")
+}
+
+#[test]
+fn files_are_spliced() {
+    assert_eq!(
+        expand_file("handbook.amark").unwrap(),
+        "h1 {\n    Handbook \n}\nh2 {\n    Intro \n}\np {\n    Welcome\n    @em {\n        \
+         note \n    }\n}\np {\n    End \n}\n"
+    );
+}
+
+#[test]
+fn cycles_are_detected() {
+    assert_eq!(
+        error("cycle_a.amark"),
+        "Unexpected input:\nexpected: \"A file which is not already being included\"\n\
+         got: \"cycle_a.amark\"\nin cycle_b.amark:3\nincluded from cycle_a.amark:1"
+    );
+}
+
+#[test]
+fn errors_have_the_include_chain() {
+    assert_eq!(
+        error("broken.amark"),
+        "Unexpected end of file:\nexpected: \"End of Block: }\"\ngot: End of File\n\
+         in chapters/broken.amark:3\nincluded from broken.amark:2"
+    );
+
+    let stream = AmarkStream::new(b"l;\ninclude(missing.amark);".as_slice());
+    let mut expander = IncludeExpander::new(stream, path("doc.amark"));
+    let error = AmarkFormatter::new()
+        .format(&mut expander, &mut Vec::new())
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        AmarkError::Include { ref chain, .. } if chain == &[(path("doc.amark"), 2)]
+    ));
+    assert!(error.to_string().contains("missing.amark: "), "{}", error);
+}
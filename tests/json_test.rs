@@ -0,0 +1,119 @@
+use amark::{
+    event::Item,
+    json,
+    tree::{Document, Node},
+    AmarkFormatter, AmarkStream, AmarkToken, OwnedToken, TokenStream,
+};
+
+const EXAMPLES: [&[u8]; 3] = [
+    include_bytes!("../example_files/simple_reference.amark"),
+    include_bytes!("../example_files/large_file.amark"),
+    include_bytes!("../example_files/functions.amark"),
+];
+
+fn tree_json(source: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    json::write_tree(AmarkStream::new(source), &mut out).unwrap();
+    out
+}
+
+fn import(json: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    json::import(json, &mut out).unwrap();
+    out
+}
+
+fn read_tokens(source: &[u8]) -> Vec<OwnedToken> {
+    let mut stream = AmarkStream::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let tok = stream.next_token().unwrap();
+        if tok == AmarkToken::End {
+            return tokens;
+        }
+        tokens.push(tok.to_owned_token());
+    }
+}
+
+/// The items of the document with their text in document order
+fn items(nodes: &[Node], out: &mut Vec<(Item, Vec<u8>)>) {
+    for node in nodes {
+        if let Node::Element(element) = node {
+            out.push((element.item.clone(), element.text()));
+            items(&element.children, out);
+        }
+    }
+}
+
+#[test]
+fn tree_is_exported() {
+    let json =
+        tree_json(b"rb(a, b) {\n    Hello \\s(2) world\n\n    @em { \"x\" }\n}\nl;\nc [ d; ]");
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        "[{\"name\":\"rb\",\"line\":1,\"kind\":\"Block\",\"params\":[\"a\",\"b\"],\"children\":[\
+         \"Hello \",{\"escape\":\"s\",\"params\":[\"2\"]},\"world\",{\"empty_line\":true},\
+         {\"name\":\"em\",\"line\":4,\"kind\":\"Block\",\"children\":[\"\\\"x\\\" \"]}]},\
+         {\"name\":\"l\",\"line\":6,\"kind\":\"Bare\"},\
+         {\"name\":\"c\",\"line\":7,\"kind\":\"Container\",\"children\":[\
+         {\"name\":\"d\",\"line\":7,\"kind\":\"Bare\"}]}]\n"
+    );
+}
+
+#[test]
+fn exports_are_imported() {
+    for source in EXAMPLES {
+        let mut tokens = Vec::new();
+        json::write_tokens(AmarkStream::new(source), &mut tokens).unwrap();
+        let formatted = AmarkFormatter::new().format_bytes(source).unwrap();
+        assert_eq!(import(&tokens), formatted);
+
+        let tree = tree_json(source);
+        let document = Document::parse(source).unwrap();
+        assert_eq!(json::read_tree(&tree).unwrap(), document);
+
+        // Trailing whitespace of text is kept
+        assert_eq!(read_tokens(&import(&tree)), read_tokens(source));
+        let imported = Document::parse(&import(&tree)).unwrap();
+        let (mut expected, mut got) = (Vec::new(), Vec::new());
+        items(&document.children, &mut expected);
+        items(&imported.children, &mut got);
+        assert_eq!(got, expected);
+    }
+}
+
+#[test]
+fn invalid_json_is_rejected() {
+    for json in [
+        b"".as_ref(),
+        b"{}",
+        b"[{\"t\":\"Unknown\"}]",
+        b"[{\"t\":\"Text\"}]",
+        b"[\"text outside of a block\"]",
+        b"[{\"name\":\"two words\"}]",
+        b"[{\"escape\":\"ab\"}]",
+        b"[\"unterminated]",
+        b"[1,]",
+        b"[{\"name\":\"p\",\"x\":1-2}]",
+        b"[{\"name\":\"p\",\"x\":01}]",
+        b"[{\"name\":\"p\",\"x\":1.}]",
+        b"[{\"name\":\"p\",\"x\":-e5}]",
+        b"[{\"name\":\"p\",\"x\":2E+}]",
+    ] {
+        assert!(
+            json::import(json, &mut Vec::new()).is_err(),
+            "{}",
+            String::from_utf8_lossy(json)
+        );
+    }
+    assert!(json::import(b"[{\"name\":\"p\",\"x\":-0.5e+10}]", &mut Vec::new()).is_ok());
+
+    // Deep nesting is an error instead of overflowing the stack
+    let deep = "[".repeat(100_000);
+    let error = json::import(deep.as_bytes(), &mut Vec::new()).unwrap_err();
+    assert!(
+        error.to_string().contains("At most 128 nested"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use amark::latex::{LatexElement, LatexRenderer};
+use common::render;
+
+#[test]
+fn items_become_latex() {
+    let latex = render(
+        &LatexRenderer::new(),
+        "h1 { Prices }
+p {
+    Some
+    @em { emphasis }
+    here\\nand\\s(2)there
+    @br(2);
+    After
+}
+rb {
+    A red box
+}
+ol [ li { One } li { Two } ]",
+    );
+
+    assert_eq!(
+        latex,
+        "\\section{Prices}
+
+Some
+\\emph{emphasis}
+here\\\\
+and~~there
+\\vspace{2\\baselineskip}
+After
+
+\\begin{tcolorbox}[colback=red!5!white,colframe=red!75!black]
+A red box
+\\end{tcolorbox}
+\\begin{enumerate}
+\\item One
+\\item Two
+\\end{enumerate}
+"
+    );
+}
+
+#[test]
+fn specials_are_escaped() {
+    let latex = render(
+        &LatexRenderer::new(),
+        "p { 100% of $5 & #1 for a_b {x\\} ~^\\\\ }",
+    );
+
+    assert_eq!(
+        latex,
+        "100\\% of \\$5 \\& \\#1 for a\\_b \\{x\\}\\textasciitilde{}\\textasciicircum{}\
+         \\textbackslash{}\n"
+    );
+}
+
+#[test]
+fn br_items_are_vertical_space() {
+    let latex = render(
+        &LatexRenderer::new(),
+        "br(2);
+p {
+    a
+    @br;
+
+    b\\n
+    c
+}
+br;
+rb {
+    d
+
+    e
+}",
+    );
+
+    assert_eq!(
+        latex,
+        "\\vspace{2\\baselineskip}
+
+a
+\\vspace{1\\baselineskip}
+
+b\\\\
+c
+
+\\vspace{1\\baselineskip}
+\\begin{tcolorbox}[colback=red!5!white,colframe=red!75!black]
+d
+
+e
+\\end{tcolorbox}
+"
+    );
+}
+
+#[test]
+fn documents_have_a_preamble() {
+    let renderer = LatexRenderer::new()
+        .element(
+            "note",
+            LatexElement::environment("minipage").with_options("t"),
+        )
+        .element("title", LatexElement::command("textsc"))
+        .preamble("\\documentclass{scrartcl}");
+    let latex = render(&renderer, "note(0.5cm) [ title { A_b } ]");
+
+    assert_eq!(
+        latex,
+        "\\documentclass{scrartcl}
+\\begin{document}
+\\begin{minipage}[t]{0.5cm}
+\\textsc{A\\_b}
+\\end{minipage}
+\\end{document}
+"
+    );
+}
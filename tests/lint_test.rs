@@ -0,0 +1,138 @@
+use amark::{
+    lint::{Context, Lint, Linter, Rule, Severity},
+    AmarkToken,
+};
+
+fn lint(linter: &mut Linter, source: &str) -> Vec<(&'static str, usize, String)> {
+    linter
+        .lint(source.as_bytes())
+        .unwrap_or_else(|e| panic!("Failure while linting: {:?}", e))
+        .into_iter()
+        .map(|lint| (lint.rule, lint.line, lint.msg))
+        .collect()
+}
+
+fn found(rule: &'static str, line: usize, msg: &str) -> (&'static str, usize, String) {
+    (rule, line, String::from(msg))
+}
+
+#[test]
+fn built_in_rules_find_problems() {
+    let lints = lint(
+        &mut Linter::new(),
+        "define(unused) [ l; ]\ndefine(card, title) [ p { $title } ]\ncard(Hi);\n\
+         a [\n    b [\n        c [\n            d [\n                e { Deep }\n            ]\n        ]\n    ]\n]\n\
+         p {\n    Text \n\n\n\n    @br(0);\n\tTabbed\n    @br(1);\n}\nbox [\n]",
+    );
+
+    assert_eq!(
+        lints,
+        [
+            found("unused-macro", 1, "Macro \"unused\" is never used"),
+            found(
+                "deep-nesting",
+                8,
+                "Item \"e\" is nested 5 levels deep, more than 4"
+            ),
+            found("trailing-whitespace", 14, "Trailing whitespace"),
+            found(
+                "repeated-empty-lines",
+                16,
+                "More than one empty line in a row"
+            ),
+            found(
+                "repeated-empty-lines",
+                17,
+                "More than one empty line in a row"
+            ),
+            found("br-zero", 18, "br(0) adds no line break"),
+            found(
+                "mixed-indentation",
+                19,
+                "Indented with tabs, line 5 is indented with spaces"
+            ),
+            found("empty-block", 23, "Item \"box\" is empty"),
+        ]
+    );
+}
+
+#[test]
+fn rules_are_configured_and_fixed() {
+    let source = "p {\n    a  \n\n\n\tb\n}\nc [\n    d [\n        e { Deep }\n    ]\n]\nbr(0);";
+    let mut linter =
+        Linter::load(b"deep-nesting(error, 2);\nbr-zero(off);\nrepeated-empty-lines(info);")
+            .unwrap();
+    let lints = linter.lint(source.as_bytes()).unwrap();
+
+    let severities: Vec<_> = lints
+        .iter()
+        .map(|l| (l.rule, l.severity, l.fixable))
+        .collect();
+    assert_eq!(
+        severities,
+        [
+            ("trailing-whitespace", Severity::Warning, true),
+            ("repeated-empty-lines", Severity::Info, true),
+            ("mixed-indentation", Severity::Warning, true),
+            ("deep-nesting", Severity::Error, false),
+        ]
+    );
+    assert_eq!(
+        linter.fix(source.as_bytes()).unwrap(),
+        b"p {\n    a\n\n    b\n}\nc [\n    d [\n        e {\n            Deep\n        }\n    ]\n]\nbr(0);\n"
+    );
+
+    for config in [
+        "unknown(error);",
+        "br-zero;",
+        "br-zero(fatal);",
+        "br-zero(error, 2);",
+        "deep-nesting(error, deep);",
+        "br-zero { error }",
+    ] {
+        assert!(Linter::load(config.as_bytes()).is_err(), "{}", config);
+    }
+}
+
+/// Reports items named `todo`
+#[derive(Debug)]
+struct NoTodo;
+
+impl Rule for NoTodo {
+    fn name(&self) -> &'static str {
+        "no-todo"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check_token(&mut self, tok: &AmarkToken<'_>, cx: &mut Context<'_>) {
+        if *tok == AmarkToken::ItemName(b"todo") {
+            cx.report("Unfinished item");
+        }
+    }
+}
+
+#[test]
+fn custom_rules_are_called() {
+    let mut linter = Linter::empty().rule(NoTodo);
+    let lints = linter.lint(b"p { Text }\ntodo;\n").unwrap();
+
+    assert_eq!(
+        lints,
+        [Lint {
+            rule: "no-todo",
+            severity: Severity::Error,
+            line: 2,
+            msg: String::from("Unfinished item"),
+            fixable: false,
+        }]
+    );
+    assert_eq!(lints[0].to_string(), "Unfinished item [no-todo]");
+
+    let mut linter = Linter::new()
+        .rule(NoTodo)
+        .severity("no-todo", Severity::Off);
+    assert_eq!(lint(&mut linter, "todo;"), []);
+}
@@ -0,0 +1,379 @@
+use amark::{lsp::Server, schema::Schema};
+
+/// Frame the messages with their headers
+fn input(messages: &[String]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for message in messages {
+        input.extend_from_slice(format!("Content-Length: {}\r\n\r\n", message.len()).as_bytes());
+        input.extend_from_slice(message.as_bytes());
+    }
+    input
+}
+
+/// Run the server over the messages and return whether it exited cleanly and its messages
+fn session(mut server: Server, messages: &[String]) -> (bool, Vec<String>) {
+    let mut output = Vec::new();
+    let clean = server
+        .run(input(messages).as_slice(), &mut output)
+        .unwrap_or_else(|e| panic!("Failure while serving: {:?}", e));
+
+    let mut replies = Vec::new();
+    let mut rest = String::from_utf8(output).unwrap();
+    while let Some((header, body)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        replies.push(body[..length].to_owned());
+        rest = body[length..].to_owned();
+    }
+    assert_eq!(rest, "");
+    (clean, replies)
+}
+
+fn request(id: usize, method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{}}}"#,
+        id, method, params
+    )
+}
+
+fn notify(method: &str, params: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","method":"{}","params":{}}}"#,
+        method, params
+    )
+}
+
+fn open(uri: &str, text: &str) -> String {
+    notify(
+        "textDocument/didOpen",
+        &format!(
+            r#"{{"textDocument":{{"uri":"{}","languageId":"amark","version":1,"text":"{}"}}}}"#,
+            uri,
+            text.replace('\n', "\\n")
+        ),
+    )
+}
+
+fn document(uri: &str, rest: &str) -> String {
+    format!(r#"{{"textDocument":{{"uri":"{}"}}{}}}"#, uri, rest)
+}
+
+const URI: &str = "file:///docs/post%20one.amark";
+
+const SOURCE: &str =
+    "define(card, t) [\n    p { $t }\n]\nc [\n    p {\n        Hi\n    }\n    card(x);\n]\n\
+     include(chapters/intro.amark);";
+
+#[test]
+fn diagnostics_are_published() {
+    let schema = Schema::load(b"p [ form(block); ]\nl [ form(bare); ]").unwrap();
+    let (clean, replies) = session(
+        Server::new().schema(schema),
+        &[
+            open(URI, "p {\n    Hi\n}\nl [ p { Hi } ]\nq;"),
+            notify(
+                "textDocument/didChange",
+                &format!(
+                    r#"{{"textDocument":{{"uri":"{}","version":2}},"contentChanges":[{{"text":"l;\np {{\n"}}]}}"#,
+                    URI
+                ),
+            ),
+            notify(
+                "textDocument/didChange",
+                &format!(
+                    r#"{{"textDocument":{{"uri":"{}","version":3}},"contentChanges":[{{"text":"l;\np(a)(b);"}}]}}"#,
+                    URI
+                ),
+            ),
+            notify("textDocument/didClose", &document(URI, "")),
+        ],
+    );
+
+    assert!(!clean);
+    assert_eq!(
+        replies,
+        [
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"{}","diagnostics":[{},{}]}}}}"#,
+                URI,
+                r#"{"range":{"start":{"line":3,"character":0},"end":{"line":3,"character":14}},"severity":2,"source":"amark","message":"Item \"l\" can't be a container"}"#,
+                r#"{"range":{"start":{"line":4,"character":0},"end":{"line":4,"character":2}},"severity":2,"source":"amark","message":"Unknown item \"q\""}"#,
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"{}","diagnostics":[{}]}}}}"#,
+                URI,
+                r#"{"range":{"start":{"line":2,"character":0},"end":{"line":2,"character":0}},"severity":1,"source":"amark","message":"Unexpected end of file:\nexpected: \"End of Block: }\"\ngot: End of File"}"#,
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"{}","diagnostics":[{}]}}}}"#,
+                URI,
+                r#"{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":8}},"severity":1,"source":"amark","message":"Unexpected input:\nexpected: \"Item start or end\"\ngot: \"Other token\""}"#,
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"{}","diagnostics":[]}}}}"#,
+                URI
+            ),
+        ]
+    );
+}
+
+#[test]
+fn documents_are_navigated() {
+    let (_, replies) = session(
+        Server::new(),
+        &[
+            open(URI, SOURCE),
+            request(1, "textDocument/foldingRange", &document(URI, "")),
+            request(
+                2,
+                "textDocument/definition",
+                &document(URI, r#","position":{"line":7,"character":6}"#),
+            ),
+            request(
+                3,
+                "textDocument/definition",
+                &document(URI, r#","position":{"line":9,"character":12}"#),
+            ),
+            request(
+                4,
+                "textDocument/definition",
+                &document(URI, r#","position":{"line":5,"character":9}"#),
+            ),
+            request(5, "textDocument/documentSymbol", &document(URI, "")),
+        ],
+    );
+
+    assert_eq!(
+        replies[1..5],
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":[{"startLine":0,"endLine":2},{"startLine":3,"endLine":8},{"startLine":4,"endLine":6}]}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":{"uri":"file:///docs/post%20one.amark","range":{"start":{"line":0,"character":0},"end":{"line":2,"character":1}}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"uri":"file:///docs/chapters/intro.amark","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"result":null}"#,
+        ]
+    );
+
+    let symbol = |name: &str, detail: &str, kind: usize, range: [usize; 4], children: &str| {
+        format!(
+            r#"{{"name":"{}","detail":"{}","kind":{},"range":{{"start":{{"line":{},"character":{}}},"end":{{"line":{},"character":{}}}}},"selectionRange":{{"start":{{"line":{},"character":{}}},"end":{{"line":{},"character":{}}}}},"children":[{}]}}"#,
+            name,
+            detail,
+            kind,
+            range[0],
+            range[1],
+            range[2],
+            range[3],
+            range[0],
+            range[1],
+            range[0],
+            range[1] + name.len(),
+            children
+        )
+    };
+    let symbols = [
+        symbol(
+            "define",
+            "card, t",
+            3,
+            [0, 0, 2, 1],
+            &symbol("p", "", 15, [1, 4, 1, 12], ""),
+        ),
+        symbol(
+            "c",
+            "",
+            3,
+            [3, 0, 8, 1],
+            &[
+                symbol("p", "", 15, [4, 4, 6, 5], ""),
+                symbol("card", "x", 14, [7, 4, 7, 12], ""),
+            ]
+            .join(","),
+        ),
+        symbol("include", "chapters/intro.amark", 14, [9, 0, 9, 30], ""),
+    ];
+    assert_eq!(
+        replies[5],
+        format!(
+            r#"{{"jsonrpc":"2.0","id":5,"result":[{}]}}"#,
+            symbols.join(",")
+        )
+    );
+}
+
+#[test]
+fn definitions_resolve_like_macros() {
+    const WINDOWS: &str = "file:///C:/docs/post.amark";
+    let (_, replies) = session(
+        Server::new(),
+        &[
+            open(
+                WINDOWS,
+                "card;\ndefine(card) [ p; ]\ndefine(outer) [\n    define(inner) [ p; ]\n]\ninner;\ncard;\ninclude(chapters/intro.amark);",
+            ),
+            request(
+                1,
+                "textDocument/definition",
+                &document(WINDOWS, r#","position":{"line":0,"character":1}"#),
+            ),
+            request(
+                2,
+                "textDocument/definition",
+                &document(WINDOWS, r#","position":{"line":5,"character":1}"#),
+            ),
+            request(
+                3,
+                "textDocument/definition",
+                &document(WINDOWS, r#","position":{"line":6,"character":1}"#),
+            ),
+            request(
+                4,
+                "textDocument/definition",
+                &document(WINDOWS, r#","position":{"line":7,"character":12}"#),
+            ),
+        ],
+    );
+
+    assert_eq!(
+        replies[1..],
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":null}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":null}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"uri":"file:///C:/docs/post.amark","range":{"start":{"line":1,"character":0},"end":{"line":1,"character":19}}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"result":{"uri":"file:///C%3A/docs/chapters/intro.amark","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}}}}"#,
+        ]
+    );
+}
+
+#[test]
+fn message_lengths_are_not_trusted() {
+    let mut input = b"Content-Length: 1000000000000\r\n\r\n{}".to_vec();
+    let result = Server::new().run(input.as_slice(), &mut Vec::new());
+    assert!(result.is_err(), "{:?}", result);
+
+    input = b"Content-Length: 99999999999999999999999\r\n\r\n{}".to_vec();
+    assert!(Server::new()
+        .run(input.as_slice(), &mut Vec::new())
+        .is_err());
+}
+
+#[test]
+fn documents_are_formatted_and_completed() {
+    let schema = Schema::load(b"p;\nc [ form(container); ]\nl;").unwrap();
+    let formatting = |id, options| {
+        request(
+            id,
+            "textDocument/formatting",
+            &document(URI, &format!(r#","options":{}"#, options)),
+        )
+    };
+    let (clean, replies) = session(
+        Server::new().schema(schema),
+        &[
+            request(1, "initialize", r#"{"capabilities":{}}"#),
+            notify("initialized", "{}"),
+            open(URI, "c [\n  l;\n]\n"),
+            formatting(2, r#"{"tabSize":2,"insertSpaces":true}"#),
+            formatting(3, r#"{"tabSize":4,"insertSpaces":false}"#),
+            request(
+                4,
+                "textDocument/completion",
+                &document(URI, r#","position":{"line":1,"character":2}"#),
+            ),
+            request(5, "textDocument/hover", &document(URI, "")),
+            request(6, "textDocument/formatting", &document("file:///x", "")),
+            request(7, "shutdown", "null"),
+            request(8, "shutdown", "null"),
+            notify("exit", "null"),
+        ],
+    );
+
+    assert!(clean);
+    assert_eq!(
+        replies[0],
+        r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":1,"documentSymbolProvider":true,"foldingRangeProvider":true,"definitionProvider":true,"documentFormattingProvider":true,"completionProvider":{"triggerCharacters":["@"]}},"serverInfo":{"name":"amark-lsp","version":"0.1.0"}}}"#
+    );
+    assert_eq!(
+        replies[2..],
+        [
+            r#"{"jsonrpc":"2.0","id":2,"result":[]}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":[{"range":{"start":{"line":0,"character":0},"end":{"line":3,"character":0}},"newText":"c [\n\tl;\n]\n"}]}"#,
+            r#"{"jsonrpc":"2.0","id":4,"result":[{"label":"c","kind":14},{"label":"l","kind":14},{"label":"p","kind":14}]}"#,
+            r#"{"jsonrpc":"2.0","id":5,"error":{"code":-32601,"message":"Unknown method textDocument/hover"}}"#,
+            r#"{"jsonrpc":"2.0","id":6,"error":{"code":-32602,"message":"Unknown document file:///x"}}"#,
+            r#"{"jsonrpc":"2.0","id":7,"result":null}"#,
+            r#"{"jsonrpc":"2.0","id":8,"error":{"code":-32600,"message":"The server is shut down"}}"#,
+        ]
+    );
+}
+
+#[test]
+fn front_matter_is_skipped() {
+    let schema = Schema::load(b"p [ form(block); ]").unwrap();
+    let (_, replies) = session(
+        Server::new().schema(schema),
+        &[
+            open(URI, "---\ntitle: Hi\n---\np {\n  Hi\n}\nq;"),
+            request(1, "textDocument/foldingRange", &document(URI, "")),
+            request(
+                2,
+                "textDocument/formatting",
+                &document(URI, r#","options":{"tabSize":4,"insertSpaces":true}"#),
+            ),
+        ],
+    );
+
+    assert_eq!(
+        replies,
+        [
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":"{}","diagnostics":[{}]}}}}"#,
+                URI,
+                r#"{"range":{"start":{"line":6,"character":0},"end":{"line":6,"character":2}},"severity":2,"source":"amark","message":"Unknown item \"q\""}"#,
+            ),
+            String::from(r#"{"jsonrpc":"2.0","id":1,"result":[{"startLine":3,"endLine":5}]}"#),
+            String::from(
+                r#"{"jsonrpc":"2.0","id":2,"result":[{"range":{"start":{"line":0,"character":0},"end":{"line":6,"character":2}},"newText":"---\ntitle: Hi\n---\np {\n    Hi\n}\nq;\n"}]}"#
+            ),
+        ]
+    );
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn binary_serves_stdio() {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_amark-lsp"))
+        .args(["--stdio", "--schema", "example_files/site.schema"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to start amark-lsp");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&input(&[
+            open(URI, "gb { Hi }"),
+            request(1, "shutdown", "null"),
+            notify("exit", "null"),
+        ]))
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""diagnostics":[]"#), "{}", stdout);
+    assert!(
+        stdout.ends_with(r#"{"jsonrpc":"2.0","id":1,"result":null}"#),
+        "{}",
+        stdout
+    );
+}
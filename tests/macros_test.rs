@@ -0,0 +1,92 @@
+use amark::{macros::MacroExpander, AmarkError, AmarkFormatter, AmarkStream};
+
+fn expand(source: &str) -> Result<String, AmarkError<'static>> {
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(
+        MacroExpander::new(AmarkStream::new(source.as_bytes())),
+        &mut out,
+    )?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn error(source: &str) -> String {
+    match expand(source) {
+        Ok(expanded) => panic!("Expanded without error: {}", expanded),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn macros_are_expanded() {
+    let expanded = expand(
+        "define(box, color, title) [
+    card($color) {
+        $title costs \\$5 or $ 5
+    }
+]
+define(price, amount) {
+    only $amount€
+}
+define(both) [
+    box(red, A);
+    box(blue, B);
+]
+both;
+p {
+    Now
+    @price(3);
+}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        expanded,
+        "card(red) {
+    A costs \\$5 or $ 5
+}
+card(blue) {
+    B costs \\$5 or $ 5
+}
+p {
+    Now
+    only 3€
+}
+"
+    );
+}
+
+#[test]
+fn definitions_are_checked() {
+    assert_eq!(
+        error("define(a) [ l; ]\n\ndefine(a) [ l; ]"),
+        "Macro \"a\" defined on line 3: It is already defined on line 1"
+    );
+    assert_eq!(
+        error("define(a, b c) [ l; ]"),
+        "Macro \"a\" defined on line 1: The argument \"b c\" is not a name of letters, digits and _"
+    );
+    assert!(expand("define [ l; ]").is_err());
+    assert!(expand("define(a) [ l;").is_err());
+}
+
+#[test]
+fn uses_are_checked() {
+    assert_eq!(
+        error("define(a) [\n    b;\n]\ndefine(b) [\n    a;\n]\n\nb;"),
+        "Macro \"b\" defined on line 4 and used on line 8: It uses itself through b -> a -> b"
+    );
+    assert_eq!(
+        error("define(a, x) [ l; ]\na(1, 2);"),
+        "Macro \"a\" defined on line 1 and used on line 2: It takes 1 arguments but got 2"
+    );
+    assert_eq!(
+        error("define(a) { text }\na;"),
+        "Macro \"a\" defined on line 1 and used on line 2: It holds text and can only be used \
+         inside of blocks"
+    );
+    assert_eq!(
+        error("define(a) [ l; ]\na { text }"),
+        "Macro \"a\" defined on line 1 and used on line 2: Macros are used without body like \
+         name(...);"
+    );
+}
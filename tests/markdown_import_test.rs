@@ -0,0 +1,175 @@
+#![cfg(feature = "markdown-import")]
+
+use amark::{
+    commonmark::MarkdownImporter,
+    markdown::{MarkdownElement, MarkdownRenderer},
+    tree::{Document, Node},
+    AmarkStream,
+};
+
+fn import(importer: &MarkdownImporter, markdown: &str) -> String {
+    let mut out = Vec::new();
+    importer
+        .import(markdown, &mut out)
+        .unwrap_or_else(|e| panic!("Failure while importing: {:?}", e));
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn markdown_becomes_items() {
+    let amark = import(
+        &MarkdownImporter::new(),
+        "# Title
+
+Some *emphasis* and `code`
+on two lines\\
+after a break.
+
+- One
+- Two
+
+> [Link](/home) ![Logo](logo.png)
+
+```rust
+let x = 1;
+```
+
+---
+",
+    );
+    assert_eq!(
+        amark,
+        "h1 {
+    Title
+}
+p {
+    Some
+    @em {
+        emphasis
+    }
+    and
+    @code {
+        code
+    }
+    on two lines
+    @br;
+    after a break.
+}
+ul [
+    li {
+        One
+    }
+    li {
+        Two
+    }
+]
+blockquote [
+    p {
+        @a(/home) {
+            Link
+        }
+        @img(logo.png,Logo);
+    }
+]
+pre(rust) {
+    let x = 1;
+}
+hr;
+"
+    );
+    Document::parse(amark.as_bytes()).unwrap();
+
+    let mut markdown = Vec::new();
+    MarkdownRenderer::new()
+        .render(AmarkStream::new(amark.as_bytes()), &mut markdown)
+        .unwrap();
+    let markdown = String::from_utf8(markdown).unwrap();
+    assert!(
+        markdown.contains("Some *emphasis* and `code` on two lines\\\nafter a break."),
+        "{}",
+        markdown
+    );
+}
+
+#[test]
+fn elements_are_configurable() {
+    let importer = MarkdownImporter::new()
+        .element(MarkdownElement::Heading(2), "subtitle")
+        .element(MarkdownElement::Emphasis, "i");
+    assert_eq!(
+        import(&importer, "## Sub *title*"),
+        "subtitle {\n    Sub\n    @i {\n        title\n    }\n}\n"
+    );
+
+    let importer = MarkdownImporter::new().element(MarkdownElement::Paragraph, "two words");
+    assert!(importer.import("Text", &mut Vec::new()).is_err());
+}
+
+#[test]
+fn text_is_read_back_exactly() {
+    for (markdown, text) in [
+        ("a } b ) c { d ( e", "a } b ) c { d ( e"),
+        ("@home and f(x)", "@home and f(x)"),
+        ("\\\\ back \\) slash \\@", "\\ back ) slash @"),
+        ("(first\nsecond}\n@third", "(first\nsecond}\n@third"),
+        ("x&amp;y &copy;", "x&y ©"),
+    ] {
+        let amark = import(&MarkdownImporter::new(), markdown);
+        let document = Document::parse(amark.as_bytes()).unwrap();
+        let Some(Node::Element(p)) = document.children.first() else {
+            panic!("No paragraph in {}", amark);
+        };
+        assert_eq!(String::from_utf8(p.text()).unwrap(), text, "{}", amark);
+    }
+}
+
+#[test]
+fn rendered_markdown_is_imported_back() {
+    let source = "ul [
+    li [
+        p {
+            first
+        }
+        p {
+            second
+        }
+    ]
+    li [
+        p {
+            # other
+        }
+    ]
+]
+blockquote [
+    p {
+        quoted
+    }
+]
+";
+    let mut markdown = Vec::new();
+    MarkdownRenderer::new()
+        .render(AmarkStream::new(source.as_bytes()), &mut markdown)
+        .unwrap();
+    let markdown = String::from_utf8(markdown).unwrap();
+
+    assert_eq!(markdown, "- first\n\n  second\n\n- \\# other\n\n> quoted\n");
+    assert_eq!(import(&MarkdownImporter::new(), &markdown), source);
+}
+
+#[test]
+fn line_breaks_are_imported_back() {
+    let source = "p {\n    a\n    @br;\n    b\n    @br;\n}\nbr(2);\nx {\n    c\\n\n}\n";
+    let mut markdown = Vec::new();
+    MarkdownRenderer::new()
+        .render(AmarkStream::new(source.as_bytes()), &mut markdown)
+        .unwrap();
+    let markdown = String::from_utf8(markdown).unwrap();
+
+    // Only the break between a and b is one in Markdown
+    assert_eq!(markdown, "a\\\nb\n\n<div class=\"x\">\n\nc\n\n</div>\n");
+    assert_eq!(
+        import(&MarkdownImporter::new(), &markdown),
+        "p {\n    a\n    @br;\n    b\n}\nhtml {\n    <div class=\"x\">\n}\np {\n    c\n}\n\
+         html {\n    </div>\n}\n"
+    );
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use amark::markdown::{MarkdownElement, MarkdownRenderer, UnknownItems};
+use common::render;
+
+#[test]
+fn items_become_markdown() {
+    let md = render(
+        &MarkdownRenderer::new(),
+        "h1 { Title \\n here }
+p {
+    Some
+    @em { emphasis }
+    and
+    @code { a`b }
+    text
+    @br(2);
+    After\\nbreak
+
+    Next paragraph
+}
+ul [ li { One } li { Two\n lines } ]
+ol [ li { First } li { Second } ]
+blockquote { Quoted\n\n more }
+pre(rust) {
+    fn f() {\\}
+    let x = 1;
+}",
+    );
+
+    assert_eq!(
+        md,
+        "# Title here
+
+Some *emphasis* and ``a`b`` text\\
+\\
+After\\
+break
+
+Next paragraph
+
+- One
+- Two
+  lines
+
+1. First
+2. Second
+
+> Quoted
+>
+> more
+
+```rust
+fn f() {}
+let x = 1;
+```
+"
+    );
+}
+
+#[test]
+fn items_are_configurable() {
+    let source = "title { Hi }\nnote [ p { Inside\n @tag { x } } ]\nl;";
+
+    let renderer = MarkdownRenderer::new().element("title", MarkdownElement::Heading(2));
+    assert_eq!(
+        render(&renderer, source),
+        "## Hi\n\n<div class=\"note\">\n\nInside <span class=\"tag\">x</span>\n\n</div>\n\n\
+         <div class=\"l\"></div>\n"
+    );
+
+    let unwrap = renderer.clone().unknown(UnknownItems::Unwrap);
+    assert_eq!(render(&unwrap, source), "## Hi\n\nInside\nx\n");
+
+    let drop = renderer.unknown(UnknownItems::Drop);
+    assert_eq!(render(&drop, source), "## Hi\n");
+}
+
+#[test]
+fn text_is_escaped() {
+    let md = render(
+        &MarkdownRenderer::new(),
+        "p {\n    # 1. *a* [b](c) <d> & e_f\n    1. - x\n    - y\n}",
+    );
+
+    assert_eq!(
+        md,
+        "\\# 1. \\*a\\* \\[b\\](c) \\<d\\> \\& e\\_f\n1\\. - x\n\\- y\n"
+    );
+}
+
+#[test]
+fn hard_breaks_are_only_between_text() {
+    let md = render(
+        &MarkdownRenderer::new(),
+        "br(2);\np {\n    @br;\n    a\n    @br;\n\n    b\\n\n    @br;\n}\nh2 {\n    c\n    @br;\n    d\n}",
+    );
+
+    assert_eq!(md, "a\n\nb\n\n## c d\n");
+}
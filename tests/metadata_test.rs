@@ -0,0 +1,153 @@
+use amark::{
+    lint::Linter,
+    metadata::{front_matter, MetaValue},
+    xml, AmarkFormatter, AmarkStream, AmarkToken, Metadata, TokenStream,
+};
+
+fn read(source: &str) -> (Option<Metadata>, AmarkStream<&[u8]>) {
+    let mut stream = AmarkStream::new(source.as_bytes());
+    let metadata = stream
+        .read_metadata()
+        .unwrap_or_else(|e| panic!("Failure while reading the front matter: {:?}", e));
+    (metadata, stream)
+}
+
+fn entries(metadata: &Metadata) -> Vec<(&str, MetaValue)> {
+    metadata.iter().map(|(k, v)| (k, v.clone())).collect()
+}
+
+fn text(text: &str) -> MetaValue {
+    MetaValue::Text(String::from(text))
+}
+
+fn list(list: &[&str]) -> MetaValue {
+    MetaValue::List(list.iter().map(|s| String::from(*s)).collect())
+}
+
+#[test]
+fn fenced_front_matter_is_read() {
+    let (metadata, mut stream) = read(
+        "\n---\ntitle: Green boxes: a guide\n# A comment\n\nslug: \"green-boxes\"\n\
+         tags: [amark, css, ]\ndate:2024-05-01\n---\np { Text }",
+    );
+
+    assert_eq!(
+        entries(&metadata.unwrap()),
+        [
+            ("title", text("Green boxes: a guide")),
+            ("slug", text("green-boxes")),
+            ("tags", list(&["amark", "css"])),
+            ("date", text("2024-05-01")),
+        ]
+    );
+    assert_eq!(stream.next_token().unwrap(), AmarkToken::ItemName(b"p"));
+    assert_eq!(stream.cur_line(), 10);
+}
+
+#[test]
+fn meta_container_is_read() {
+    let (metadata, mut stream) = read(
+        "meta [\n    title(Green boxes\\, a guide);\n    tags(amark, css);\n    \
+         summary {\n        How to draw\n        them\n    }\n]\nl;",
+    );
+    let metadata = metadata.unwrap();
+
+    assert_eq!(
+        entries(&metadata),
+        [
+            ("title", text("Green boxes, a guide")),
+            ("tags", list(&["amark", "css"])),
+            ("summary", text("How to draw\nthem")),
+        ]
+    );
+    assert_eq!(metadata.get("tags").unwrap().as_list(), ["amark", "css"]);
+    assert_eq!(metadata.get("title").unwrap().as_list().len(), 1);
+    assert_eq!(stream.next_token().unwrap(), AmarkToken::ItemName(b"l"));
+
+    // Documents without front matter are read as they are
+    let (metadata, mut stream) = read("\nmetadata [ l; ]");
+    assert!(metadata.is_none());
+    assert_eq!(
+        stream.next_token().unwrap(),
+        AmarkToken::ItemName(b"metadata")
+    );
+}
+
+#[test]
+fn invalid_front_matter_is_rejected() {
+    for source in [
+        "---\ntitle: a\n",
+        "---\ntitle\n---",
+        "---\n: a\n---",
+        "---\ntitle: a\ntitle: b\n---",
+        "meta [ title; ]",
+        "meta [ title() ]",
+        "meta [ title(a) { b } ]",
+        "meta [\n    title(a);\n",
+    ] {
+        assert!(
+            AmarkStream::new(source.as_bytes()).read_metadata().is_err(),
+            "{}",
+            source
+        );
+    }
+
+    let mut stream = AmarkStream::new(b"l;".as_slice());
+    stream.next_token().unwrap();
+    assert!(stream.read_metadata().is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn metadata_is_deserialized() {
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct Post {
+        title: String,
+        year: u32,
+        tags: Vec<String>,
+        draft: Option<String>,
+    }
+
+    let (metadata, _) = read("---\ntitle: Hi\nyear: 2024\ntags: css\n---");
+    assert_eq!(
+        metadata.unwrap().deserialize::<Post>().unwrap(),
+        Post {
+            title: String::from("Hi"),
+            year: 2024,
+            tags: vec![String::from("css")],
+            draft: None,
+        }
+    );
+
+    let (metadata, _) = read("meta [\n    title(Hi);\n    year(soon);\n]");
+    let error = metadata.unwrap().deserialize::<Post>().unwrap_err();
+    assert_eq!(error.line(), Some(3));
+}
+
+#[test]
+fn front_matter_is_kept_by_source_tools() {
+    let source = b"---\ntitle: Hi  \n---\np{\n  Text\n}\n";
+    assert_eq!(front_matter(source).unwrap(), b"---\ntitle: Hi  \n---\n");
+    assert_eq!(
+        front_matter(b"meta [ a(b); ]  \nl;").unwrap(),
+        b"meta [ a(b); ]  \n"
+    );
+    assert_eq!(front_matter(b"\nl;").unwrap(), b"");
+
+    assert_eq!(
+        AmarkFormatter::new().format_bytes(source).unwrap(),
+        b"---\ntitle: Hi  \n---\np {\n    Text\n}\n"
+    );
+    let mut linter = Linter::new();
+    assert!(linter.lint(source).unwrap().is_empty());
+    assert_eq!(
+        linter.fix(source).unwrap(),
+        b"---\ntitle: Hi  \n---\np {\n    Text\n}\n"
+    );
+
+    let mut out = Vec::new();
+    xml::write_tree(source, &mut out).unwrap();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("<item name=\"p\" line=\"4\" col=\"1\">"),);
+}
@@ -0,0 +1,83 @@
+use amark::{
+    macros::MacroExpander, repeat::RepeatExpander, AmarkError, AmarkFormatter, AmarkStream,
+};
+
+fn expand(source: &str, max_tokens: usize) -> Result<String, AmarkError<'static>> {
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(
+        RepeatExpander::new(AmarkStream::new(source.as_bytes())).max_tokens(max_tokens),
+        &mut out,
+    )?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn items_are_repeated() {
+    let expanded = expand(
+        "repeat(2, i) [\n    h2 {\n        Part $i of $n\n    }\n    \
+         repeat($i, j) [\n        p($i.$j);\n    ]\n]\nrepeat(0) [ l; ]",
+        100,
+    )
+    .unwrap();
+
+    assert_eq!(
+        expanded,
+        "h2 {\n    Part 1 of $n\n}\np(1.1);\nh2 {\n    Part 2 of $n\n}\np(2.1);\np(2.2);\n"
+    );
+}
+
+#[test]
+fn line_breaks_are_repeated() {
+    let expanded = expand("br(3);\np {\n    A\n    @br(2);\n    B\n    @br;\n}", 100).unwrap();
+
+    assert_eq!(
+        expanded,
+        "br;\nbr;\nbr;\np {\n    A\n    @br;\n    @br;\n    B\n    @br;\n}\n"
+    );
+
+    for source in [
+        "br(x);",
+        "br(2) { a }",
+        "repeat [ l; ]",
+        "repeat(x) [ l; ]",
+        "repeat(2);",
+    ] {
+        assert!(expand(source, 100).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn expansions_are_limited() {
+    assert!(expand("repeat(10) [ l; ]", 20).is_ok());
+
+    let error = expand(
+        "repeat(1000000000) [\n    repeat(1000000000) [ l; ]\n]",
+        1000,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Expanding the document creates more than 1000 tokens"
+    );
+    assert!(expand("br(18446744073709551615);", 1000).is_err());
+}
+
+#[test]
+fn indices_are_left_to_repeat_in_macros() {
+    let mut out = Vec::new();
+    AmarkFormatter::new()
+        .format(
+            RepeatExpander::new(MacroExpander::new(AmarkStream::new(
+                b"define(list, n) [\n    repeat($n, i) [\n        p {\n            Item $i of $n\n        \
+                  }\n    ]\n]\nlist(2);"
+                    .as_slice(),
+            ))),
+            &mut out,
+        )
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "p {\n    Item 1 of 2\n}\np {\n    Item 2 of 2\n}\n"
+    );
+}
@@ -0,0 +1,136 @@
+use amark::{
+    event::ItemKind,
+    schema::{ParamType, Schema, Validator, Violation, ViolationKind},
+    AmarkStream,
+};
+
+fn validate(schema: &str, source: &str) -> Vec<Violation> {
+    let schema = Schema::load(schema.as_bytes())
+        .unwrap_or_else(|e| panic!("Failure while loading the schema: {:?}", e));
+    Validator::new(&schema)
+        .validate(AmarkStream::new(source.as_bytes()))
+        .unwrap_or_else(|e| panic!("Failure while validating: {:?}", e))
+}
+
+fn violation(name: &str, line: usize, kind: ViolationKind) -> Violation {
+    Violation {
+        name: name.as_bytes().to_vec(),
+        line,
+        kind,
+    }
+}
+
+const SCHEMA: &str = "p [ form(block); ]\nsep;\n\
+     img [\n    form(bare);\n    params(text, int?, left|right?);\n]\n\
+     tags [ params(text*); ]\nlist [\n    form(container);\n    children(li);\n]\n\
+     li [\n    form(block);\n    parents(list);\n]";
+
+#[test]
+fn valid_documents_have_no_violations() {
+    let violations = validate(
+        SCHEMA,
+        "p {\n    Text\n    @sep;\n}\nimg(a.png);\nimg(a.png, 20, left);\ntags;\ntags(a, b, c);\n\
+         list [\n    li { One }\n    li { Two }\n]\nsep [ p { Hi } ]",
+    );
+
+    assert_eq!(violations, []);
+}
+
+#[test]
+fn violations_are_reported_with_lines() {
+    let violations = validate(
+        SCHEMA,
+        "p [ sep; ]\nimg;\nimg(a.png, big, top, x);\nlist [\n    p { Hi }\n]\nli { Alone }\ngb { Typo }",
+    );
+
+    assert_eq!(
+        violations,
+        [
+            violation("p", 1, ViolationKind::Form(ItemKind::Container)),
+            violation(
+                "img",
+                2,
+                ViolationKind::ParamCount {
+                    got: 0,
+                    min: 1,
+                    max: Some(3)
+                }
+            ),
+            violation(
+                "img",
+                3,
+                ViolationKind::ParamCount {
+                    got: 4,
+                    min: 1,
+                    max: Some(3)
+                }
+            ),
+            violation(
+                "img",
+                3,
+                ViolationKind::ParamType {
+                    idx: 1,
+                    expected: ParamType::Int,
+                    got: b"big".to_vec()
+                }
+            ),
+            violation(
+                "img",
+                3,
+                ViolationKind::ParamType {
+                    idx: 2,
+                    expected: ParamType::Choice(vec![b"left".to_vec(), b"right".to_vec()]),
+                    got: b"top".to_vec()
+                }
+            ),
+            violation(
+                "p",
+                5,
+                ViolationKind::Child {
+                    parent: b"list".to_vec()
+                }
+            ),
+            violation(
+                "li",
+                7,
+                ViolationKind::Parent {
+                    parents: vec![b"list".to_vec()]
+                }
+            ),
+            violation("gb", 8, ViolationKind::UnknownItem),
+        ]
+    );
+
+    let messages: Vec<_> = violations[1..4].iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        [
+            "Item \"img\" takes 1 to 3 parameters, got 0",
+            "Item \"img\" takes 1 to 3 parameters, got 4",
+            "Parameter 2 of item \"img\" has to be an int, got \"big\"",
+        ]
+    );
+    assert_eq!(
+        violations[6].to_string(),
+        "Item \"li\" has to be inside of \"list\""
+    );
+}
+
+#[test]
+fn invalid_schemas_are_rejected() {
+    for schema in [
+        "p(a);",
+        "p { form(block); }",
+        "p;\np;",
+        "p [ form; ]",
+        "p [ form(inline); ]",
+        "p [\n    form(block);\n    form(bare);\n]",
+        "p [ params(float); ]",
+        "p [ params(int?, text); ]",
+        "p [ params(int*, text); ]",
+        "p [ children(q); ]",
+        "p [ size(1); ]",
+    ] {
+        assert!(Schema::load(schema.as_bytes()).is_err(), "{}", schema);
+    }
+}
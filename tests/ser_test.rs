@@ -0,0 +1,169 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Site {
+    title: String,
+    draft: bool,
+    author: Option<String>,
+    theme: Theme,
+    cards: Vec<Card>,
+    shapes: Vec<Shape>,
+    links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct Card {
+    name: String,
+    initial: char,
+    price: i64,
+    size: (u8, u8),
+    tags: Vec<String>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+enum Theme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+enum Shape {
+    Dot,
+    Circle(f64),
+    Label(String),
+    Rect { w: u32, h: u32 },
+}
+
+#[test]
+fn site_is_serialized() {
+    let site = Site {
+        title: "My @site {1}".into(),
+        draft: false,
+        author: None,
+        theme: Theme::Dark,
+        cards: vec![Card {
+            name: "Green box".into(),
+            initial: 'G',
+            price: 5,
+            size: (1, 2),
+            tags: vec!["new".into(), " sale ".into()],
+            note: Some("first line\n\nlast line\n".into()),
+        }],
+        shapes: vec![Shape::Dot, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }],
+        links: [("home".into(), "/".into())].into_iter().collect(),
+    };
+
+    let source = amark::to_string(&site).unwrap();
+    assert_eq!(
+        source,
+        "title {
+    My @site {1\\}}
+draft(false);
+theme [
+    Dark;
+]
+cards [
+    Card [
+        name {
+            Green box
+        }
+        initial {
+            G
+        }
+        price(5);
+        size(1,2);
+        tags [
+            item {
+                new
+            }
+            item {
+                \\ssale\\s}
+        ]
+        note {
+            first line
+
+            last line\\n}
+    ]
+]
+shapes [
+    Dot;
+    Circle(1.5);
+    Rect [
+        w(2);
+        h(3);
+    ]
+]
+links [
+    home {
+        /
+    }
+]
+"
+    );
+    assert_eq!(amark::from_slice::<Site>(source.as_bytes()).unwrap(), site);
+}
+
+#[test]
+fn unrepresentable_values_are_rejected() {
+    assert!(amark::to_string("top level text").is_err());
+    assert!(amark::to_string(&vec![Some(1), None]).is_err());
+
+    let map: BTreeMap<_, _> = [("two words", 1)].into_iter().collect();
+    assert!(amark::to_string(&map).is_err());
+}
+
+/// Text with every character that has a meaning in Amark
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z \\t\\r\\n\\\\{}()\\[\\];,@]{0,20}", "\\PC{0,10}"]
+}
+
+fn card() -> impl Strategy<Value = Card> {
+    (
+        text(),
+        any::<char>(),
+        any::<i64>(),
+        any::<(u8, u8)>(),
+        prop::collection::vec(text(), 0..3),
+        prop::option::of(text()),
+    )
+        .prop_map(|(name, initial, price, size, tags, note)| Card {
+            name,
+            initial,
+            price,
+            size,
+            tags,
+            note,
+        })
+}
+
+fn shape() -> impl Strategy<Value = Shape> {
+    prop_oneof![
+        Just(Shape::Dot),
+        (-1e9..1e9f64).prop_map(Shape::Circle),
+        text().prop_map(Shape::Label),
+        any::<(u32, u32)>().prop_map(|(w, h)| Shape::Rect { w, h }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn values_are_read_back(
+        title in text(),
+        draft in any::<bool>(),
+        author in prop::option::of(text()),
+        theme in prop_oneof![Just(Theme::Light), Just(Theme::Dark)],
+        cards in prop::collection::vec(card(), 0..3),
+        shapes in prop::collection::vec(shape(), 0..4),
+        links in prop::collection::btree_map("[a-z_][a-z0-9_]{0,5}", text(), 0..3),
+    ) {
+        let site = Site { title, draft, author, theme, cards, shapes, links };
+        let source = amark::to_string(&site)?;
+        prop_assert_eq!(amark::from_slice::<Site>(source.as_bytes())?, site, "{}", source);
+    }
+}
@@ -1,6 +1,6 @@
 use std::io::Cursor;
 
-use amarkl::{AmarkReader, AmarkToken};
+use amark::{AmarkReader, AmarkToken};
 
 #[test]
 fn reference_holds() {
@@ -96,7 +96,7 @@ fn reference_holds() {
     let mut source = Cursor::new(include_bytes!("../example_files/simple_reference.amark"));
     let mut expected_iter = expected.into_iter();
 
-    while let Some(expected_token) = expected_iter.next() {
+    for expected_token in expected_iter.by_ref() {
         let (got_token, line) = aml_reader.parse_next_get_cur_line(&mut source);
         let got_token =
             got_token.unwrap_or_else(|e| panic!("Failure while parsing on line {}: {:?}", line, e));
@@ -173,7 +173,7 @@ fn functions_test() {
     let mut source = Cursor::new(include_bytes!("../example_files/functions.amark"));
     let mut expected_iter = expected.into_iter();
 
-    while let Some(expected_token) = expected_iter.next() {
+    for expected_token in expected_iter.by_ref() {
         let (got_token, line) = aml_reader.parse_next_get_cur_line(&mut source);
         let got_token =
             got_token.unwrap_or_else(|e| panic!("Failure while parsing on line {}: {:?}", line, e));
@@ -354,7 +354,7 @@ fn large_file_works() {
     let mut block_starts = 0u32;
     let mut block_ends = 0u32;
 
-    while let Some(expected_token) = expected_iter.next() {
+    for expected_token in expected_iter.by_ref() {
         let (got_token, line) = aml_reader.parse_next_get_cur_line(&mut source);
         let got_token =
             got_token.unwrap_or_else(|e| panic!("Failure while parsing on line {}: {:?}", line, e));
@@ -0,0 +1,100 @@
+use std::io::Cursor;
+
+use amark::{
+    template::{MissingTemplate, Template, TemplateRenderer},
+    AmarkStream,
+};
+
+fn render(renderer: &TemplateRenderer, source: &str) -> (String, Vec<MissingTemplate>) {
+    let mut out = Vec::new();
+    let missing = renderer
+        .render(AmarkStream::new(Cursor::new(source)), &mut out)
+        .unwrap_or_else(|e| panic!("Failure while rendering: {:?}", e));
+    (String::from_utf8(out).unwrap(), missing)
+}
+
+#[test]
+fn templates_fill_items() {
+    let renderer = TemplateRenderer::new()
+        .template(
+            "card",
+            Template::parse("<section id=\"{{param.0}}\">{{body}}</section>").unwrap(),
+        )
+        .template(
+            "title",
+            Template::parse("<h2 class={{name}}>{{body}}</h2>").unwrap(),
+        )
+        .template(
+            "br",
+            Template::parse("{{#repeat param.0}}<br>{{ #repeat 2 }}-{{/repeat}}{{/repeat}}")
+                .unwrap(),
+        );
+    let (html, missing) = render(
+        &renderer,
+        "card(a&b) [\n    title {\n        Fish & Chips\n    }\n    br(2);\n    br;\n]",
+    );
+
+    assert_eq!(
+        html,
+        "<section id=\"a&amp;b\"><h2 class=title>Fish &amp; Chips\n</h2>\
+         <br>--<br>--<br>--</section>"
+    );
+    assert!(missing.is_empty());
+
+    // An escape sequence at the end of a line keeps the text in one paragraph
+    let (html, _) = render(&renderer, "title {\n    50\\%\n    off\\n\n    today\n}");
+    assert_eq!(html, "<h2 class=title>50%\noff<br>\ntoday\n</h2>");
+}
+
+#[test]
+fn definitions_are_loaded() {
+    let renderer = TemplateRenderer::load(
+        b"gb {\n    <div class=\"box green\">\n    {{body\\}\\}\n    </div>\n}\n\
+          em(<em>{{body}}</em>);\nsep(<hr>\\,);",
+    )
+    .unwrap();
+    let (html, _) = render(&renderer, "gb {\n    Some\n    @em { text }\n}\nsep;");
+
+    assert_eq!(
+        html,
+        "<div class=\"box green\">\nSome\n<em>text \n</em>\n</div><hr>,"
+    );
+
+    for definitions in [
+        b"gb;".as_ref(),
+        b"gb(a, b);",
+        b"gb({{unknown}});",
+        b"gb({{body);",
+        b"gb({{#repeat x}}{{/repeat}});",
+        b"gb({{#repeat 2}});",
+    ] {
+        assert!(
+            TemplateRenderer::load(definitions).is_err(),
+            "{}",
+            String::from_utf8_lossy(definitions)
+        );
+    }
+}
+
+#[test]
+fn missing_templates_are_reported() {
+    let renderer =
+        TemplateRenderer::new().template("p", Template::parse("<p>{{body}}</p>").unwrap());
+    let (html, missing) = render(
+        &renderer,
+        "p {\n    A\n    @x { b }\n}\nl;\nc [\n    p { d }\n]",
+    );
+
+    assert_eq!(html, "<p>A\nb \n</p><p>d \n</p>");
+    assert_eq!(
+        missing
+            .iter()
+            .map(|m| format!("{}: {}", m.line, m))
+            .collect::<Vec<_>>(),
+        [
+            "3: No template for item \"x\"",
+            "5: No template for item \"l\"",
+            "6: No template for item \"c\"",
+        ]
+    );
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use amark::text::TextRenderer;
+use common::render;
+
+const SOURCE: &str = "h1 { Plain text }
+p {
+    Words   with
+    @em { markup }
+    are kept\\nafter a break
+
+    \\s(4)Indented paragraph
+}
+list [
+    li { First }
+    br(2);
+    li { Second }
+]";
+
+#[test]
+fn markup_is_dropped() {
+    assert_eq!(
+        render(&TextRenderer::new(), SOURCE),
+        "Plain text
+
+Words with markup are kept
+after a break
+
+    Indented paragraph
+
+First
+
+Second
+"
+    );
+}
+
+#[test]
+fn lines_are_wrapped() {
+    assert_eq!(
+        render(&TextRenderer::new().wrap(12), SOURCE),
+        "Plain text
+
+Words with
+markup are
+kept
+after a
+break
+
+    Indented
+paragraph
+
+First
+
+Second
+"
+    );
+}
+
+#[test]
+fn items_are_labeled() {
+    assert_eq!(
+        render(
+            &TextRenderer::new().labels(true),
+            "gb { A green box }\nl;\nbb { A blue box }"
+        ),
+        "gb:\nA green box\n\nbb:\nA blue box\n"
+    );
+}
+
+#[test]
+fn br_items_break_lines() {
+    assert_eq!(
+        render(
+            &TextRenderer::new(),
+            "p {\n    Additional text\n    @br(3);\n    Another\n    @br;\n}\nbr;\np { End\\n }\np { Last }"
+        ),
+        "Additional text\n\n\nAnother\n\nEnd\n\nLast\n"
+    );
+    assert_eq!(
+        render(
+            &TextRenderer::new(),
+            "p {\n    a\n    @br;\n\n    b\\n\n\n    c\\n\n    d\n}"
+        ),
+        "a\n\nb\n\nc\nd\n"
+    );
+}
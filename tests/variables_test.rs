@@ -0,0 +1,85 @@
+use amark::{
+    variables::{UndefinedVariable, VariableExpander},
+    AmarkError, AmarkFormatter, AmarkStream,
+};
+
+fn expand(expander: VariableExpander<AmarkStream<&[u8]>>) -> Result<String, AmarkError<'static>> {
+    let mut out = Vec::new();
+    AmarkFormatter::new().format(expander, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn expander(source: &str) -> VariableExpander<AmarkStream<&[u8]>> {
+    VariableExpander::new(AmarkStream::new(source.as_bytes()))
+}
+
+#[test]
+fn variables_are_interpolated() {
+    let expanded = expand(
+        expander(
+            "set(name, Amark);\nset(full, ${name} ${version});\nc [\n    set(name, Inner);\n    \
+             p {\n        All \\v(name)s and \\v(full)\n    }\n]\nl(${name}, x \\v(list), $name, ${});",
+        )
+        .variable("version", "1.2")
+        .variable("list", "a, b"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        expanded,
+        "c [\n    p {\n        All Inners and Amark 1.2\n    }\n]\n\
+         l(Amark, x a\\, b, $name, ${});\n"
+    );
+
+    // A value at the end of a line doesn't continue on the next line
+    let expanded = expand(expander(
+        "set(v, 1.2);\np {\n    Version \\v(v)\n    next line\n}",
+    ))
+    .unwrap();
+    assert_eq!(expanded, "p {\n    Version 1.2\n    next line\n}\n");
+}
+
+#[test]
+fn undefined_variables_are_reported() {
+    let error = expand(expander("p {\n    \\v(missing)\n}")).unwrap_err();
+    assert_eq!(error.to_string(), "Undefined variable \"missing\"");
+
+    let mut expander =
+        expander("l(${a});\np {\n    @set(a, 1);\n}\np {\n    \\v(a)\n}").strict(false);
+    let mut out = Vec::new();
+    AmarkFormatter::new()
+        .format(&mut expander, &mut out)
+        .unwrap();
+    assert_eq!(out, b"l();\np {}\np {}\n");
+    assert_eq!(
+        expander.undefined(),
+        [
+            UndefinedVariable {
+                name: b"a".to_vec(),
+                line: 1
+            },
+            UndefinedVariable {
+                name: b"a".to_vec(),
+                line: 6
+            }
+        ]
+    );
+}
+
+#[test]
+fn invalid_variables_are_rejected() {
+    for source in [
+        "set(a);",
+        "set(a, b, c);",
+        "set(a b, c);",
+        "set(a, b) { c }",
+        "p {\n    \\v\n}",
+        "p {\n    \\v(a, b)\n}",
+    ] {
+        assert!(
+            expand(expander(source).variable("a", "")).is_err(),
+            "{}",
+            source
+        );
+    }
+}
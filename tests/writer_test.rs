@@ -0,0 +1,97 @@
+use amark::{
+    event::{Event, EventReader, Item, ItemKind},
+    tree::Document,
+    AmarkError, AmarkStream, AmarkWriter,
+};
+use proptest::prelude::*;
+
+#[test]
+fn writer_builds_document() -> Result<(), AmarkError<'static>> {
+    let mut writer = AmarkWriter::new(Vec::new());
+    writer.item("page")?.container(|w| {
+        w.item("l")?;
+        w.item("card")?.params(["a, b", "(c)"])?.block(|w| {
+            w.text("  Hello } world\\\n\nnext")?;
+            w.escape(b's', Some(["4"]))?;
+            w.item("em")?.block(|w| {
+                w.text("@me")?;
+                w.line("kept  ")?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        Ok(())
+    })?;
+    let source = writer.finish()?;
+
+    assert_eq!(
+        String::from_utf8(source).unwrap(),
+        "page [\n    l;\n    card(a\\,\\sb,(c\\)) {\n        \\s\\sHello \\}\\sworld\\\\\n\n        \
+         next\\s(4)\n        @em {\n            \\@me\n            kept  \n        }\n    }\n]\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn writer_rejects_invalid_input() {
+    let mut writer = AmarkWriter::new(Vec::new());
+    assert!(writer.item("two words").is_err());
+    assert!(writer.item("a{").is_err());
+    assert!(writer.text("top level text").is_err());
+    assert!(writer.params(["no item"]).is_err());
+
+    writer
+        .item("c")
+        .unwrap()
+        .container(|w| w.text("text in a container").map(|_| ()))
+        .unwrap_err();
+}
+
+/// Parse the source and collect the text and the parameters of the single item in it
+fn read_back(source: &[u8]) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let mut events = EventReader::new(AmarkStream::new(source));
+    let mut text = Vec::new();
+    let mut params = Vec::new();
+
+    while let Some(event) = events.next_event().unwrap() {
+        match event {
+            Event::Start(Item {
+                params: Some(p),
+                kind: ItemKind::Block,
+                ..
+            }) => params.clone_from(p),
+            Event::Text(t) => text.extend_from_slice(t),
+            Event::Escape(e) => text.extend(std::iter::repeat_n(e.resolved(), e.count())),
+            _ => (),
+        }
+    }
+
+    (text, params)
+}
+
+proptest! {
+    #[test]
+    fn text_and_params_are_escaped(
+        text in "[a-z @\\\\(){}\\[\\];,\t]{1,20}",
+        params in prop::collection::vec("[a-z@\\\\(){}\\[\\];,]([a-z @\\\\(){}\\[\\];,\n]{0,6}[a-z@\\\\(){}\\[\\];,])?", 1..4),
+    ) {
+        let mut writer = AmarkWriter::new(Vec::new());
+        writer.item("p")?.params(&params)?.block(|w| w.text(&text).map(|_| ()))?;
+        let source = writer.finish()?;
+
+        let (got_text, got_params) = read_back(&source);
+        prop_assert_eq!(String::from_utf8(got_text).unwrap(), text);
+        prop_assert_eq!(got_params, params.into_iter().map(String::into_bytes).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn lines_of_text_are_read_back(text in "[a-z @\\\\}\t\n]{0,30}") {
+        let mut writer = AmarkWriter::new(Vec::new());
+        writer.item("p")?.block(|w| w.text(&text).map(|_| ()))?;
+        let source = writer.finish()?;
+
+        let document = Document::parse(&source)?;
+        let element = document.elements().next().unwrap();
+        prop_assert_eq!(String::from_utf8(element.text()).unwrap(), text);
+    }
+}
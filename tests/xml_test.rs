@@ -0,0 +1,120 @@
+use amark::{tree::Document, xml, AmarkStream, AmarkToken, OwnedToken, TokenStream};
+
+const EXAMPLES: [&[u8]; 3] = [
+    include_bytes!("../example_files/simple_reference.amark"),
+    include_bytes!("../example_files/large_file.amark"),
+    include_bytes!("../example_files/functions.amark"),
+];
+
+fn export(source: &[u8]) -> String {
+    let mut out = Vec::new();
+    xml::write_tree(source, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn tokens(source: &[u8]) -> Vec<OwnedToken> {
+    let mut stream = AmarkStream::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let tok = stream.next_token().unwrap();
+        if tok == AmarkToken::End {
+            return tokens;
+        }
+        tokens.push(tok.to_owned_token());
+    }
+}
+
+#[test]
+fn tree_is_exported() {
+    let xml = export(b"rb(a, b) {\n    Hello \\s(2) world\n\n  @em { \"x\" <&> }\n}\nl;\nc [ d; ]");
+
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<amark>
+  <item name="rb" line="1" col="1">
+    <params>
+      <text>a</text>
+      <text>b</text>
+    </params>
+    <block>
+      <text>Hello </text>
+      <escape seq="s">
+        <params>
+          <text>2</text>
+        </params>
+      </escape>
+      <text>world</text>
+      <empty-line/>
+      <item name="em" line="4" col="3">
+        <block>
+          <text>"x" &lt;&amp;&gt; </text>
+        </block>
+      </item>
+    </block>
+  </item>
+  <item name="l" line="6" col="1"/>
+  <item name="c" line="7" col="1">
+    <container>
+      <item name="d" line="7" col="5"/>
+    </container>
+  </item>
+</amark>
+"#
+    );
+}
+
+#[test]
+fn exports_are_imported() {
+    for source in EXAMPLES {
+        let xml = export(source);
+        assert_eq!(
+            xml::read_tree(xml.as_bytes()).unwrap(),
+            Document::parse(source).unwrap()
+        );
+
+        let mut amark = Vec::new();
+        xml::import(xml.as_bytes(), &mut amark).unwrap();
+        assert_eq!(tokens(&amark), tokens(source));
+    }
+
+    // Trailing whitespace is part of the text
+    let mut amark = Vec::new();
+    xml::import(
+        b"<amark><item name=\"p\"><block><text>a  </text></block></item></amark>",
+        &mut amark,
+    )
+    .unwrap();
+    assert_eq!(amark, b"p {\n    a  \n}\n");
+
+    // What other XML tools might write
+    let edited = b"<?xml version='1.0'?>\r\n<!-- edited -->\r\n<amark><item name='p'>\
+                   <block><text>a &#x26; b<![CDATA[ <c>]]></text><escape seq=\"&#92;\"/>\
+                   </block></item></amark>";
+    let mut amark = Vec::new();
+    xml::import(edited, &mut amark).unwrap();
+    assert_eq!(amark, b"p {\n    a & b <c>\\\\}\n");
+}
+
+#[test]
+fn invalid_xml_is_rejected() {
+    for xml in [
+        b"".as_ref(),
+        b"<amark>",
+        b"<doc/>",
+        b"<!DOCTYPE amark><amark/>",
+        b"<amark>text outside of a block</amark>",
+        b"<amark><item/></amark>",
+        b"<amark><item name=\"two words\"/></amark>",
+        b"<amark><escape seq=\"ab\"/></amark>",
+        b"<amark><item name=\"p\"><block/><block/></item></amark>",
+        b"<amark><item name=\"p\"></amark></item>",
+        b"<amark><text>&unknown;</text></amark>",
+    ] {
+        assert!(
+            xml::import(xml, &mut Vec::new()).is_err(),
+            "{}",
+            String::from_utf8_lossy(xml)
+        );
+    }
+}